@@ -1,7 +1,5 @@
 use std::{collections::HashMap, fs::File, io::Read, path::Path};
 
-use anyhow::anyhow;
-
 #[derive(Clone)]
 pub struct Asset {
     pub bytes: Vec<u8>,
@@ -18,13 +16,11 @@ impl Loader {
         }
     }
 
-    pub fn get_font_by_family(&self) -> anyhow::Result<Asset> {
-        let path = String::from("/");
-        let asset = self
-            .assets
-            .get(&path)
-            .ok_or(anyhow!("Path doesn't exist in asset map."))?;
-        Ok(asset.clone())
+    /// Every loaded asset, in the order of their paths so that it's the same every time.
+    pub fn assets(&self) -> Vec<&Asset> {
+        let mut paths: Vec<&String> = self.assets.keys().collect();
+        paths.sort();
+        paths.into_iter().map(|path| &self.assets[path]).collect()
     }
 
     pub fn load<S: AsRef<Path>>(&mut self, path: S) -> anyhow::Result<()> {
@@ -5,7 +5,14 @@ use std::{
     rc::Rc,
 };
 
+use anyhow::{anyhow, Result};
 use fontdue::{Font, FontSettings, Metrics};
+use gggg::text::{
+    font_collection::{FontCollection, FontStyle},
+    layout::{FontMetrics, TextShaper},
+    rich_text::SpanStyle,
+    shaping::ShapedGlyph,
+};
 
 use crate::{
     context::Context,
@@ -20,6 +27,8 @@ pub struct TextStyleHandle(u32);
 pub struct TextStyle {
     pub font_family: String,
     pub font_size: f32,
+    /// 400 is normal, 700 bold.
+    pub font_weight: u16,
     pub color: Color,
 }
 
@@ -29,13 +38,35 @@ impl TextStyle {
             color,
             font_family,
             font_size,
+            font_weight: 400,
+        }
+    }
+
+    pub fn with_font_weight(mut self, font_weight: u16) -> Self {
+        self.font_weight = font_weight;
+        self
+    }
+
+    /// The style with a span's changes on top, see [SpanStyle].
+    pub fn with_span_style(&self, style: &SpanStyle) -> Self {
+        let mut span_style = self.clone();
+        if let Some([r, g, b, a]) = style.color {
+            span_style.color = Color::RGBA(r, g, b, a);
+        }
+        if let Some(size) = style.size {
+            span_style.font_size = size;
         }
+        if let Some(weight) = style.weight {
+            span_style.font_weight = weight;
+        }
+        span_style
     }
 
     pub(crate) fn handle(&self) -> TextStyleHandle {
         let mut hasher = DefaultHasher::default();
         hasher.write(self.font_family.as_bytes());
         hasher.write(&self.font_size.to_ne_bytes());
+        hasher.write_u16(self.font_weight);
         let value = u32::try_from(hasher.finish()).unwrap();
         TextStyleHandle(value)
     }
@@ -48,10 +79,17 @@ impl TextStyle {
             return computed.clone();
         }
 
-        // resolve font family into filepath
-        // let asset = Loader::load(&mut self, path).unwrap();
-        let asset = context.borrow().loader.get_font_by_family().unwrap();
-        let computed = TextStyleComputed::new(asset.bytes, self.font_size);
+        // resolve font family into one of the loaded fonts, faces are picked the same way as for the renderer's text
+        let mut collection = FontCollection::new();
+        for asset in context.borrow().loader.assets() {
+            // anything that isn't a font is skipped
+            let _ = collection.add_font_data(asset.bytes.clone());
+        }
+        let face = collection
+            .resolve(&self.font_family, self.font_weight, FontStyle::Normal)
+            .ok_or(anyhow!("No font loaded for family '{}'", self.font_family))
+            .unwrap();
+        let computed = TextStyleComputed::new(face.data.to_vec(), face.index, self.font_size);
 
         context
             .borrow_mut()
@@ -74,14 +112,21 @@ impl Default for TextStyle {
 
 /// Contains the atlas and metrics of a text style's font, in addition to any other contents worth 'caching'.
 pub struct TextStyleComputed {
+    font: Font,
+    px: f32,
     font_image: Image,
     atlas: TextureAtlas<char>,
     char_metrics: HashMap<char, Metrics>,
 }
 
 impl TextStyleComputed {
-    pub fn new(font_bytes: Vec<u8>, px: f32) -> Self {
-        let font = Font::from_bytes(font_bytes, FontSettings::default()).unwrap();
+    /// `collection_index` picks the face in a font collection (.ttc), 0 for plain font files.
+    pub fn new(font_bytes: Vec<u8>, collection_index: u32, px: f32) -> Self {
+        let settings = FontSettings {
+            collection_index,
+            ..Default::default()
+        };
+        let font = Font::from_bytes(font_bytes, settings).unwrap();
         let mut atlas = TextureAtlas::<char>::new();
 
         let mut char_to_handle = HashMap::new();
@@ -107,6 +152,8 @@ impl TextStyleComputed {
         let font_image = atlas.merge_bitmaps(images, char_to_handle);
 
         Self {
+            font,
+            px,
             font_image,
             atlas,
             char_metrics,
@@ -125,3 +172,39 @@ impl TextStyleComputed {
         self.char_metrics.get(c).unwrap()
     }
 }
+
+/// There's no shaping here, every char gets its own glyph from the style's atlas. The char is at the glyph's cluster.
+impl TextShaper for TextStyleComputed {
+    fn shape(&self, text: &str, size: f32) -> Result<Vec<ShapedGlyph>> {
+        let scale = size / self.px;
+        Ok(text
+            .char_indices()
+            .map(|(cluster, c)| ShapedGlyph {
+                face: 0,
+                glyph_id: self.font.lookup_glyph_index(c),
+                cluster,
+                x_advance: self
+                    .char_metrics
+                    .get(&c)
+                    .map_or(0.0, |metrics| metrics.advance_width * scale),
+                y_advance: 0.0,
+                x_offset: 0.0,
+                y_offset: 0.0,
+            })
+            .collect())
+    }
+
+    fn metrics(&self, size: f32) -> FontMetrics {
+        self.font
+            .horizontal_line_metrics(size)
+            .map(|metrics| FontMetrics {
+                ascent: metrics.ascent,
+                descent: metrics.descent,
+                line_gap: metrics.line_gap,
+                // fontdue doesn't read the font's decorations
+                underline: None,
+                strikeout: None,
+            })
+            .unwrap_or_default()
+    }
+}
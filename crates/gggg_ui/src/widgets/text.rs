@@ -1,19 +1,24 @@
 use std::{cell::RefCell, rc::Rc};
 
-use gggg_asset::loader::Loader;
+use gggg::text::{
+    layout::{layout_spans, LayoutOptions, LayoutSpan, TextShaper},
+    rich_text::{decorations, join_spans, Decoration, DecorationKind, TextSpan},
+};
 
 use crate::{
     context::Context,
-    shape::{GlyphShape, ShapeType, UIShape},
+    shape::{GlyphShape, RectangleShape, ShapeType, UIShape},
     styles::text_style::TextStyle,
 };
 
-use super::{widget::Widget, BoxConstraints, Size};
+use super::{widget::Widget, BoxConstraints, Color, Size};
 
 // TODO: implement this widget (and others) as a macro, this will eliminate the Box problem and make it easier to write ui.
 
 #[derive(Clone)]
 struct LetterRect {
+    c: char,
+    span: usize,
     x: f32,
     y: f32,
     w: f32,
@@ -22,17 +27,29 @@ struct LetterRect {
 
 #[derive(Clone)]
 pub struct Text {
-    value: String,
+    spans: Vec<TextSpan>,
     style: TextStyle,
+    layout: LayoutOptions,
+    // the style of every span, worked out during layout
+    span_styles: Vec<TextStyle>,
     letter_bounds: Option<Vec<LetterRect>>,
+    decorations: Vec<Decoration>,
 }
 
 impl Text {
     pub fn new(value: String) -> Self {
+        Self::rich(vec![TextSpan::new(&value)])
+    }
+
+    /// Text made of spans with their own color, size, weight and decorations on top of the widget's style.
+    pub fn rich(spans: Vec<TextSpan>) -> Self {
         Self {
-            value,
+            spans,
             style: TextStyle::default(),
+            layout: LayoutOptions::new(),
+            span_styles: Vec::new(),
             letter_bounds: None,
+            decorations: Vec::new(),
         }
     }
 
@@ -40,79 +57,105 @@ impl Text {
         self.style = style;
         self
     }
+
+    /// Wrapping, alignment, line height etc. The max width and height are further limited by the widget's constraints.
+    pub fn with_layout(mut self, layout: LayoutOptions) -> Self {
+        self.layout = layout;
+        self
+    }
 }
 
 impl Widget for Text {
     /// Here we attempt to fit our characters within the bounding box.
     /// [Text] will layout such that word's characters will prefer to be together on the same line, putting the word on another line if necessary.
-    /// If there isn't enough space, we can (optionally) insert an ellipsis to communicate that the text is cut off.
+    /// If there isn't enough space, we can (optionally) insert an ellipsis to communicate that the text is cut off, see [LayoutOptions::with_ellipsis].
     fn layout(&mut self, constraints: BoxConstraints, context: Rc<RefCell<Context>>) -> Size {
-        let mut letter_bounds = Vec::new();
-        let loader = Loader::new();
-        let computed = self.style.compute(&context);
-        let mut max_line_width = 0_f32;
-        let mut line_width = 0_f32;
-        let line_height = self.style.font_size * 1.5;
-        let mut y = 0_f32;
-
-        // separate our string by spaces and newlines
-        for word in self.value.split(' ').flat_map(|char| char.split('\n')) {
-            let mut word_letter_bounds: Vec<LetterRect> = Vec::new();
-
-            // handle the newline character
-            if word.starts_with('\n') {
-                line_width = 0.0;
-                y += line_height;
-                continue;
-            }
+        let (value, ranges) = join_spans(&self.spans);
+        self.span_styles = self
+            .spans
+            .iter()
+            .map(|span| self.style.with_span_style(&span.style))
+            .collect();
+        // no spans is the same as no text
+        if self.span_styles.is_empty() {
+            self.span_styles.push(self.style.clone());
+        }
+        let computed: Vec<_> = self
+            .span_styles
+            .iter_mut()
+            .map(|style| style.compute(&context))
+            .collect();
+        let spans: Vec<LayoutSpan> = computed
+            .iter()
+            .zip(&self.span_styles)
+            .enumerate()
+            .map(|(idx, (computed, style))| LayoutSpan {
+                range: ranges.get(idx).cloned().unwrap_or(0..0),
+                shaper: &**computed,
+                size: style.font_size,
+            })
+            .collect();
+
+        let max_width = self
+            .layout
+            .max_width
+            .map_or(constraints.max.width, |width| {
+                width.min(constraints.max.width)
+            });
+        let max_height = self
+            .layout
+            .max_height
+            .map_or(constraints.max.height, |height| {
+                height.min(constraints.max.height)
+            });
+        let options = self
+            .layout
+            .clone()
+            .with_max_width(max_width)
+            .with_max_height(max_height);
+        // the layout is shared with the renderer's text, it has y going up so it gets flipped here
+        let text_layout = layout_spans(&value, &spans, &options).unwrap();
 
-            let mut word_width = 0_f32;
-            let mut word_height = 0_f32;
-            // we want to go through each letter and check if the entire word fits on this line
-            for char in word.chars() {
-                let metrics = computed.get_char_metrics(&char);
-                let rect = computed.get_char_rect(char);
-                // check whether we have to break the word
-                if word_width > constraints.max.width {
-                    y += line_height;
-                    max_line_width = max_line_width.max(line_width);
-                    line_width = 0.0;
-                    word_width = 0.0;
-                    word_height = 0.0;
-                }
-                word_letter_bounds.push(LetterRect {
-                    x: word_width,
-                    y: line_height,
-                    w: rect.w as f32,
-                    h: rect.h as f32,
-                });
-                word_width += metrics.advance_width;
-                word_height = word_height.max(metrics.advance_height);
-            }
-            if word_width + line_width > constraints.max.width {
-                if word_height + line_height + y <= constraints.max.width {
-                    // if the word won't fit and we have enough vertical space, break the word onto the next line
-                    y += line_height;
-                    max_line_width = max_line_width.max(line_width);
-                    line_width = 0.0;
-
-                    // update the line heights for this word
-                    word_letter_bounds
-                        .iter_mut()
-                        .for_each(|rect| rect.y = line_height);
+        let letter_bounds = text_layout
+            .glyphs
+            .iter()
+            // glyphs the font doesn't have aren't in the atlas
+            .filter(|glyph| glyph.glyph_id != 0)
+            .filter_map(|glyph| {
+                let text = if glyph.ellipsis {
+                    text_layout.ellipsis
                 } else {
-                    // if we don't have enough vertical space we need to chop
-                    break;
-                }
-            }
-            // finally, we need to append this word's letters onto our letters
-            letter_bounds.extend(word_letter_bounds);
-        }
+                    value.as_str()
+                };
+                let c = text[glyph.cluster..].chars().next()?;
+                let metrics = computed[glyph.span].get_char_metrics(&c);
+                Some(LetterRect {
+                    c,
+                    span: glyph.span,
+                    x: glyph.x + metrics.xmin as f32,
+                    y: -(glyph.y + metrics.ymin as f32 + metrics.height as f32),
+                    w: metrics.width as f32,
+                    h: metrics.height as f32,
+                })
+            })
+            .collect();
         self.letter_bounds = Some(letter_bounds);
 
+        let metrics: Vec<_> = computed
+            .iter()
+            .zip(&self.span_styles)
+            .map(|(computed, style)| computed.metrics(style.font_size))
+            .collect();
+        self.decorations = decorations(
+            &text_layout,
+            &self.spans,
+            &metrics,
+            self.style.color.as_rgba_f32(),
+        );
+
         Size {
-            width: max_line_width,
-            height: y,
+            width: text_layout.bounds.max[0].max(0.0),
+            height: -text_layout.bounds.min[1],
         }
     }
 
@@ -122,37 +165,59 @@ impl Widget for Text {
         constraints: super::BoxConstraints,
         context: Rc<RefCell<Context>>,
     ) -> Vec<crate::shape::UIShape> {
-        let handle = self.style.handle();
         let ctx = context.borrow();
-        let computed = ctx.text_styles.get(&handle).unwrap();
 
-        self.letter_bounds
-            .as_ref()
-            .unwrap()
-            .clone()
+        let letters = self.letter_bounds.as_ref().unwrap().iter().map(|letter| {
+            let c = letter.c;
+            let style = &self.span_styles[letter.span];
+            let handle = style.handle();
+            let rect = ctx.text_styles.get(&handle).unwrap().get_char_rect(c);
+            UIShape {
+                offset: crate::Offset {
+                    dx: letter.x,
+                    dy: letter.y,
+                },
+                size: Size {
+                    width: letter.w,
+                    height: letter.h,
+                },
+                shape: ShapeType::Glyph(GlyphShape {
+                    character: c,
+                    font_family: style.font_family.clone(),
+                    color: style.color,
+                    atlas_rect: rect,
+                    text_style_handle: handle,
+                }),
+            }
+        });
+
+        // backgrounds go behind the letters, lines on top of them
+        let (backgrounds, lines): (Vec<_>, Vec<_>) = self
+            .decorations
             .iter()
-            .enumerate()
-            .map(|(idx, letter)| {
-                let c = self.value.chars().nth(idx).unwrap();
-                let rect = computed.get_char_rect(c);
-                UIShape {
-                    offset: crate::Offset {
-                        dx: letter.x,
-                        dy: letter.y,
-                    },
-                    size: Size {
-                        width: letter.w,
-                        height: letter.h,
-                    },
-                    shape: ShapeType::Glyph(GlyphShape {
-                        character: c,
-                        font_family: self.style.font_family.clone(),
-                        color: self.style.color,
-                        atlas_rect: rect,
-                        text_style_handle: handle,
-                    }),
-                }
-            })
+            .partition(|decoration| decoration.kind == DecorationKind::Background);
+        let decoration_shape = |decoration: &Decoration| {
+            let [r, g, b, a] = decoration.color;
+            UIShape {
+                offset: crate::Offset {
+                    dx: decoration.rect.min[0],
+                    dy: -decoration.rect.max[1],
+                },
+                size: Size {
+                    width: decoration.rect.width(),
+                    height: decoration.rect.height(),
+                },
+                shape: ShapeType::Rectangle(RectangleShape {
+                    color: Color::RGBA(r, g, b, a),
+                }),
+            }
+        };
+
+        backgrounds
+            .into_iter()
+            .map(decoration_shape)
+            .chain(letters)
+            .chain(lines.into_iter().map(decoration_shape))
             .collect()
     }
 
@@ -75,12 +75,14 @@ impl<'a> AppLoop for App<'a> {
         render.write_buffer(camera_bytes, defaults_bind, 0);
         render.write_buffer(camera_bytes, text_bind, 0);
 
-        let font_atlas_handle =
-            render.register_atlas(text_bind, 1, gggg::texture::TextureFormat::R8Unorm);
-        let roboto_manager = Rc::new(
-            FontBitmapManager::new(&mut render, "Roboto.ttf", 4096.0 / 4.0, font_atlas_handle)
-                .unwrap(),
-        );
+        let font_atlas_handle = render
+            .register_atlas(text_bind, 1, gggg::texture::TextureFormat::R8Unorm)
+            .unwrap();
+        let roboto_manager =
+            Rc::new(FontBitmapManager::new("Roboto.ttf", 64.0, font_atlas_handle).unwrap());
+        roboto_manager
+            .preload(&mut render, "helo wrd".chars())
+            .unwrap();
 
         App {
             render,
@@ -123,7 +125,7 @@ impl<'a> AppLoop for App<'a> {
         .into_iter()
         .for_each(|obj| self.render.add_render_object(obj));
 
-        self.render.draw();
+        self.render.draw().unwrap();
     }
 
     fn resized(&mut self, new_size: PhysicalSize<u32>) {
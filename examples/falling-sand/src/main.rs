@@ -107,7 +107,7 @@ impl<'a> AppLoop for App<'a> {
                 mesh_handle: self.mesh_handle,
             });
         }
-        self.render.draw();
+        self.render.draw().unwrap();
     }
 
     fn resized(&mut self, new_size: PhysicalSize<u32>) {
@@ -1,10 +1,11 @@
 use std::sync::Arc;
 
 use gggg::{
+    atlas::Atlas,
     bind::{
         vertex_attr_array, BindEntry, BindEntryType, BindHandle, BufferUsages, Extent3d, Face,
-        SamplerBindingType, SamplerDescriptor, ShaderStages, TextureFormat, TextureSampleType,
-        TextureUsages, TextureViewDimension, VertexStepMode,
+        FilterMode, SamplerBindingType, SamplerDescriptor, ShaderStages, TextureFormat,
+        TextureSampleType, TextureUsages, TextureViewDimension, VertexStepMode,
     },
     camera::{Camera, ProjectionType},
     geometry::Geometry,
@@ -14,7 +15,6 @@ use gggg::{
     plain::Plain,
     render::{AtlasHandle, Mesh, MeshHandle, PhysicalSize, Render, TextureHandle, Window},
     render_object::BasicRenderObject,
-    texture::Texture,
     window::{make_app, AppLoop},
 };
 use nalgebra::{point, Matrix4, Translation3, Vector4};
@@ -199,7 +199,9 @@ impl<'a> AppLoop for App<'a> {
     type App = Self;
 
     fn init(window: Arc<Window>, gggg: &gggg::window::App<Self>) -> App<'a> {
-        let mut render = Render::new(window.clone()).unwrap();
+        let mut render = Render::new(window.clone())
+            .unwrap()
+            .with_missing_texture_fallback(true);
 
         let camera = Camera::new(
             point![1.0, 0.0, 5.0],
@@ -233,7 +235,7 @@ impl<'a> AppLoop for App<'a> {
             BindEntry {
                 visibility: ShaderStages::FRAGMENT,
                 ty: BindEntryType::Texture {
-                    sample_type: TextureSampleType::Float { filterable: false },
+                    sample_type: TextureSampleType::Float { filterable: true },
                     view_dimension: TextureViewDimension::D2,
                     sample_count: 1,
                     format: TextureFormat::Rgba8Unorm,
@@ -243,16 +245,28 @@ impl<'a> AppLoop for App<'a> {
                         depth_or_array_layers: 1,
                     },
                     usage: TextureUsages::COPY_DST | TextureUsages::TEXTURE_BINDING,
+                    // the atlas is limited to as many levels as its padding allows
+                    mip_level_count: u32::MAX,
                 },
                 count: None,
             },
         ]);
 
-        let atlas_handle =
-            render.register_atlas(defaults_bind, 1, gggg::texture::TextureFormat::Rgba8Unorm);
+        // enough padding for a few mip levels
+        let atlas_handle = render
+            .add_atlas(
+                defaults_bind,
+                1,
+                Atlas::new(gggg::texture::TextureFormat::Rgba8Unorm).with_padding(8),
+            )
+            .unwrap();
 
-        let cobble_tex = Texture::from_path("cobble.png");
-        let stone_tex = Texture::from_path("stone.png");
+        let cobble_tex = render
+            .load_texture("cobble.png", gggg::texture::TextureFormat::Rgba8Unorm)
+            .unwrap();
+        let stone_tex = render
+            .load_texture("stone.png", gggg::texture::TextureFormat::Rgba8Unorm)
+            .unwrap();
 
         let cobble_handle = render.add_texture(cobble_tex, atlas_handle).unwrap();
         let stone_handle = render.add_texture(stone_tex, atlas_handle).unwrap();
@@ -260,8 +274,12 @@ impl<'a> AppLoop for App<'a> {
         let sampler_bind_handle = render.build_bind(&mut [BindEntry {
             visibility: ShaderStages::FRAGMENT,
             ty: BindEntryType::Sampler {
-                binding_type: SamplerBindingType::NonFiltering,
-                descriptor: SamplerDescriptor::default(),
+                binding_type: SamplerBindingType::Filtering,
+                descriptor: SamplerDescriptor {
+                    // keep the pixel art crisp up close but blend between mips in the distance
+                    mipmap_filter: FilterMode::Linear,
+                    ..Default::default()
+                },
             },
             count: None,
         }]);
@@ -304,7 +322,6 @@ impl<'a> AppLoop for App<'a> {
             .with_bind(defaults_bind)
             .with_bind(sampler_bind_handle)
             .with_bind(lights_bind_handle)
-            .with_format(TextureFormat::Bgra8UnormSrgb)
             .with_shader(include_str!("shader.wgsl"))
             .with_vb::<Vertex>(
                 VertexStepMode::Vertex,
@@ -322,16 +339,8 @@ impl<'a> AppLoop for App<'a> {
 
         // render.write_texture(
         //     img.as_bytes(),
-        //     ImageDataLayout {
-        //         offset: 0,
-        //         bytes_per_row: Some(img.width() * 4),
-        //         rows_per_image: None,
-        //     },
-        //     Extent3d {
-        //         width: img.width(),
-        //         height: img.height(),
-        //         depth_or_array_layers: 1,
-        //     },
+        //     gggg::texture::TextureFormat::Rgba8Unorm,
+        //     TextureRegion::new(img.width(), img.height()),
         //     texture_bind_handle,
         //     0,
         // );
@@ -415,7 +424,7 @@ impl<'a> AppLoop for App<'a> {
             atlas_handle: self.atlas_handle,
         });
 
-        self.render.draw();
+        self.render.draw().unwrap();
     }
 
     fn input(&mut self, input: gggg::input::InputEvent, gggg: &gggg::window::App<Self>) {
@@ -427,7 +436,10 @@ impl<'a> AppLoop for App<'a> {
                 } else if key == "e" {
                     self.zoom_camera((0.0, -10.0));
                 } else if key == "t" && pressed {
-                    let texture = Texture::from_path("red.png");
+                    let texture = self
+                        .render
+                        .load_texture("red.png", gggg::texture::TextureFormat::Rgba8Unorm)
+                        .unwrap();
                     let _ = self.render.add_texture(texture, self.atlas_handle);
                 }
             }
@@ -0,0 +1,168 @@
+// Measures the per-frame cost of submitting 100k quads.
+// Press `b` to switch between `Render::batch` and `Render::add_render_object`.
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use gggg::{
+    bind::BindHandle,
+    camera::{Camera, ProjectionType},
+    input::InputEvent,
+    material::BasicMaterial,
+    pipeline::PipelineHandle,
+    plain::Plain,
+    render::{Mesh, MeshHandle, PhysicalSize, Render, Window},
+    shapes::{quad_geometry, shape_pipeline, ShapeGeometry, ShapeInstance, ShapeRenderObject},
+    window::{make_app, AppLoop},
+};
+use nalgebra::{point, Matrix4, Scale3, Translation3};
+
+const QUADS_PER_SIDE: usize = 316; // ~100k quads
+const REPORT_EVERY: u32 = 120;
+
+struct App<'a> {
+    render: Render<'a>,
+    camera: Camera,
+    bind: BindHandle,
+    pipeline: PipelineHandle,
+    mesh_handle: MeshHandle,
+    transforms: Vec<Matrix4<f32>>,
+    use_batch: bool,
+    frames: u32,
+    submit_time: Duration,
+    draw_time: Duration,
+}
+
+impl<'a> App<'a> {
+    fn camera() -> Camera {
+        Camera::new(
+            point![0.0, 0.0, 100.0],
+            point![0.0, 0.0, 0.0],
+            ProjectionType::Orthographic {
+                left: 0.0,
+                right: QUADS_PER_SIDE as f32,
+                top: QUADS_PER_SIDE as f32,
+                bottom: 0.0,
+                near: -200.0,
+                far: 200.0,
+            },
+        )
+    }
+
+    fn report(&mut self) {
+        let frames = self.frames as f64;
+        println!(
+            "{} quads via {}: submit {:.3}ms, draw {:.3}ms per frame",
+            self.transforms.len(),
+            if self.use_batch {
+                "batch"
+            } else {
+                "add_render_object"
+            },
+            self.submit_time.as_secs_f64() * 1000.0 / frames,
+            self.draw_time.as_secs_f64() * 1000.0 / frames,
+        );
+        self.frames = 0;
+        self.submit_time = Duration::ZERO;
+        self.draw_time = Duration::ZERO;
+    }
+}
+
+impl<'a> AppLoop for App<'a> {
+    type App = App<'a>;
+
+    fn init(window: Arc<Window>, _gggg: &gggg::window::App<Self>) -> Self::App {
+        let mut render = Render::new(window).unwrap();
+        let (pipeline, bind) = shape_pipeline(&mut render);
+        let pipeline = render.add_pipeline(pipeline);
+
+        let mesh_handle = render.add_mesh::<ShapeGeometry, ShapeInstance, BasicMaterial>(Mesh {
+            material: BasicMaterial {},
+            geometry: quad_geometry(),
+        });
+
+        let camera = Self::camera();
+        render.write_buffer(camera.uniform().as_bytes(), bind, 0);
+
+        let transforms = (0..QUADS_PER_SIDE * QUADS_PER_SIDE)
+            .map(|idx| {
+                let x = (idx % QUADS_PER_SIDE) as f32 + 0.5;
+                let y = (idx / QUADS_PER_SIDE) as f32 + 0.5;
+                Translation3::new(x, y, 0.0).to_homogeneous()
+                    * Scale3::new(0.8, 0.8, 1.0).to_homogeneous()
+            })
+            .collect();
+
+        App {
+            render,
+            camera,
+            bind,
+            pipeline,
+            mesh_handle,
+            transforms,
+            use_batch: true,
+            frames: 0,
+            submit_time: Duration::ZERO,
+            draw_time: Duration::ZERO,
+        }
+    }
+
+    fn input(&mut self, input: InputEvent, _gggg: &gggg::window::App<Self>) {
+        if let InputEvent::KeyboardInput { key, pressed: true } = input {
+            if key == "b" {
+                self.report();
+                self.use_batch = !self.use_batch;
+            }
+        }
+    }
+
+    fn draw(&mut self, _gggg: &gggg::window::App<Self>) {
+        let count = self.transforms.len();
+        let quad = |idx: usize, transform: Matrix4<f32>| ShapeRenderObject {
+            transform,
+            albedo: [idx as f32 / count as f32, 0.5, 1.0, 1.0],
+            pipeline_handle: self.pipeline,
+            mesh_handle: self.mesh_handle,
+        };
+
+        let start = Instant::now();
+        if self.use_batch {
+            let mut batch = self
+                .render
+                .batch::<ShapeRenderObject>(self.pipeline, self.mesh_handle);
+            batch.reserve(count);
+            for (idx, transform) in self.transforms.iter().enumerate() {
+                batch.push(&quad(idx, *transform));
+            }
+        } else {
+            for (idx, transform) in self.transforms.iter().enumerate() {
+                self.render.add_render_object(quad(idx, *transform));
+            }
+        }
+        let submitted = Instant::now();
+        self.render.draw().unwrap();
+
+        self.submit_time += submitted - start;
+        self.draw_time += submitted.elapsed();
+        self.frames += 1;
+
+        if self.frames == REPORT_EVERY {
+            self.report();
+        }
+    }
+
+    fn resized(&mut self, new_size: PhysicalSize<u32>) {
+        self.render.resize(new_size);
+        self.camera = Self::camera();
+        self.render
+            .write_buffer(self.camera.uniform().as_bytes(), self.bind, 0);
+    }
+}
+
+fn main() {
+    make_app()
+        .with_window_size((700, 700))
+        .with_title("quad bench")
+        .run(App::init);
+}
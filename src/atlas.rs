@@ -1,75 +1,273 @@
-use std::hash::Hash;
+use std::{fmt::Display, hash::Hash};
 
+use anyhow::{anyhow, Result};
 use generational_arena::{Arena, Index};
-use itertools::Itertools;
 
 use crate::texture::TextureFormat;
 
 #[derive(Eq, Hash, PartialEq, Clone, Copy)]
 pub struct RectHandle(pub Index);
 
+/// Rects smaller than this still get an atlas of this size, saves us from growing the atlas a bunch of times while it's being filled.
+const MIN_SIZE: u32 = 64;
+
+/// The error [Atlas::add] fails with when there's no room left, check for it with [anyhow::Error::is].
+#[derive(Debug)]
+pub struct AtlasFull {
+    pub max_pages: u32,
+    pub max_size: u32,
+}
+
+impl Display for AtlasFull {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Atlas is full, it can't grow beyond {} page(s) of {}x{}",
+            self.max_pages, self.max_size, self.max_size
+        )
+    }
+}
+
+impl std::error::Error for AtlasFull {}
+
+/// A skyline packed texture atlas.
+///
+/// Rects are placed as soon as they're added and never move afterwards, so texture coordinates handed out earlier stay valid (in pixels).
+/// When a rect doesn't fit the atlas grows, up to [Atlas::with_max_size]. Once it can't grow any further new pages are added, up to [Atlas::with_max_pages].
+/// Every page has the same size so that pages can be stored as layers of a single texture array.
+///
+/// The space of removed rects is reused by later rects that fit into it, see [Atlas::remove].
 #[derive(Debug)]
 pub struct Atlas {
     rects: Arena<Rect>,
+    pages: Vec<Skyline>,
+    // (padded) space left behind by removed rects
+    free: Vec<Rect>,
     pub width: u32,
     pub height: u32,
     pub changed: bool,
     pub format: TextureFormat,
+    padding: u32,
+    max_size: u32,
+    max_pages: u32,
+    power_of_two: bool,
 }
 
 impl Atlas {
     pub fn new(format: TextureFormat) -> Self {
         Self {
             rects: Arena::new(),
+            pages: Vec::new(),
+            free: Vec::new(),
             width: 0,
             height: 0,
             changed: false,
             format,
+            padding: 1,
+            max_size: 4096,
+            max_pages: 1,
+            power_of_two: false,
         }
     }
 
-    pub fn add(&mut self, w: u32, h: u32) -> RectHandle {
-        let rect = Rect { x: 0, y: 0, w, h };
-        let index = self.rects.insert(rect);
+    /// Pixels kept around every rect so that linearly filtered samples don't bleed into neighbouring rects. Defaults to 1.
+    /// The padding is filled with the rect's edge pixels when the atlas is uploaded, see [Atlas::max_mip_levels] for mipmapped atlases.
+    pub fn with_padding(mut self, padding: u32) -> Self {
+        self.padding = padding;
+        self
+    }
 
-        RectHandle(index)
+    /// The maximum width and height of a page. Defaults to 4096, the renderer further limits this to what the device supports.
+    pub fn with_max_size(mut self, max_size: u32) -> Self {
+        self.max_size = max_size;
+        self.normalise_max_size();
+        self
     }
 
-    pub fn pack(&mut self) {
-        self.changed = true;
-        let mut x = 0;
-        let mut y = 0;
-        // self.width = 512;
-        let total_area = self
-            .rects
+    /// The maximum number of pages. Defaults to 1.
+    pub fn with_max_pages(mut self, max_pages: u32) -> Self {
+        self.max_pages = max_pages.max(1);
+        self
+    }
+
+    /// Keeps the atlas' width and height at powers of two. Defaults to false.
+    pub fn with_power_of_two(mut self, power_of_two: bool) -> Self {
+        self.power_of_two = power_of_two;
+        self.normalise_max_size();
+        self
+    }
+
+    fn normalise_max_size(&mut self) {
+        if self.power_of_two && !self.max_size.is_power_of_two() {
+            // round down so that we never exceed the requested size
+            self.max_size = self.max_size.next_power_of_two() / 2;
+        }
+    }
+
+    pub fn padding(&self) -> u32 {
+        self.padding
+    }
+
+    pub fn max_size(&self) -> u32 {
+        self.max_size
+    }
+
+    pub fn max_pages(&self) -> u32 {
+        self.max_pages
+    }
+
+    /// How many mip levels can be generated before rects start bleeding into each other.
+    /// Every level halves the padding, so e.g. a padding of 4 allows for 3 levels.
+    pub fn max_mip_levels(&self) -> u32 {
+        self.padding.max(1).ilog2() + 1
+    }
+
+    pub fn pages(&self) -> u32 {
+        self.pages.len() as u32
+    }
+
+    /// Places a `w` x `h` rect in the atlas without moving any of the existing rects.
+    pub fn add(&mut self, w: u32, h: u32) -> Result<RectHandle> {
+        let padded_w = w + self.padding * 2;
+        let padded_h = h + self.padding * 2;
+        if padded_w > self.max_size || padded_h > self.max_size {
+            return Err(anyhow!(
+                "Rect of size {}x{} (with padding) doesn't fit into an atlas with a maximum size of {}",
+                padded_w,
+                padded_h,
+                self.max_size
+            ));
+        }
+
+        if let Some(handle) = self.add_to_free_space(w, h) {
+            return Ok(handle);
+        }
+
+        if self.pages.is_empty() {
+            let size = |s: u32| {
+                let s = s.max(MIN_SIZE);
+                let s = if self.power_of_two {
+                    s.next_power_of_two()
+                } else {
+                    s
+                };
+                s.min(self.max_size)
+            };
+            self.width = size(padded_w);
+            self.height = size(padded_h);
+            self.pages.push(Skyline::new(self.width));
+        }
+
+        loop {
+            for (page, skyline) in self.pages.iter_mut().enumerate() {
+                if let Some((node, x, y)) =
+                    skyline.find_position(padded_w, padded_h, self.width, self.height)
+                {
+                    skyline.insert(node, x, y, padded_w, padded_h);
+                    let rect = Rect {
+                        x: x + self.padding,
+                        y: y + self.padding,
+                        w,
+                        h,
+                        page: page as u32,
+                    };
+                    self.changed = true;
+                    return Ok(RectHandle(self.rects.insert(rect)));
+                }
+            }
+
+            if self.grow() {
+                continue;
+            }
+
+            if self.pages.len() < self.max_pages as usize {
+                self.pages.push(Skyline::new(self.width));
+                continue;
+            }
+
+            return Err(AtlasFull {
+                max_pages: self.max_pages,
+                max_size: self.max_size,
+            }
+            .into());
+        }
+    }
+
+    /// Places the rect into the smallest bit of free space that fits it, splitting off whatever's left over.
+    fn add_to_free_space(&mut self, w: u32, h: u32) -> Option<RectHandle> {
+        let padded_w = w + self.padding * 2;
+        let padded_h = h + self.padding * 2;
+        let (idx, space) = self
+            .free
             .iter()
-            .fold(0.0, |acc, (_, rect)| (rect.w * rect.h) as f32 + acc);
-        self.width = total_area.sqrt().round() as u32;
-        // sort s.t. the tallest rect is first
-        // decreasing rect height means we can pack everything in a row
-        let mut sorted_rects = self
-            .rects
-            .iter_mut()
-            .sorted_by(|a, b| b.1.h.partial_cmp(&a.1.h).unwrap())
-            // .rev()
-            .collect::<Vec<_>>();
-
-        let mut max_h = sorted_rects.first().unwrap().1.h;
-        // for (_, rect) in self.rects.iter_mut() {
-        for (_, rect) in sorted_rects.iter_mut() {
-            // bounds check
-            if x + rect.w >= self.width {
-                y += max_h;
-                x = 0;
-                max_h = rect.h;
+            .enumerate()
+            .filter(|(_, space)| space.w >= padded_w && space.h >= padded_h)
+            .min_by_key(|(_, space)| space.w * space.h)
+            .map(|(idx, space)| (idx, *space))?;
+        self.free.swap_remove(idx);
+
+        // whatever's to the right of the rect, then everything below it
+        let right = Rect {
+            x: space.x + padded_w,
+            w: space.w - padded_w,
+            h: padded_h,
+            ..space
+        };
+        let below = Rect {
+            y: space.y + padded_h,
+            h: space.h - padded_h,
+            ..space
+        };
+        self.free.extend(
+            [right, below]
+                .into_iter()
+                .filter(|rest| rest.w > 0 && rest.h > 0),
+        );
+
+        let rect = Rect {
+            x: space.x + self.padding,
+            y: space.y + self.padding,
+            w,
+            h,
+            page: space.page,
+        };
+        self.changed = true;
+        Some(RectHandle(self.rects.insert(rect)))
+    }
+
+    /// Frees up the rect's space for later rects. Free space isn't merged, so it's best reused by rects of a similar size (e.g. glyphs).
+    pub fn remove(&mut self, handle: RectHandle) -> Option<Rect> {
+        let rect = self.rects.remove(handle.0)?;
+        self.free.push(Rect {
+            x: rect.x - self.padding,
+            y: rect.y - self.padding,
+            w: rect.w + self.padding * 2,
+            h: rect.h + self.padding * 2,
+            page: rect.page,
+        });
+        self.changed = true;
+        Some(rect)
+    }
+
+    /// Doubles the smaller side of the atlas, returns false if the atlas is already at its maximum size.
+    /// Existing rects keep their pixel positions.
+    fn grow(&mut self) -> bool {
+        let can_grow_width = self.width < self.max_size;
+        let can_grow_height = self.height < self.max_size;
+
+        if can_grow_width && (self.width <= self.height || !can_grow_height) {
+            let width = (self.width * 2).min(self.max_size);
+            for skyline in self.pages.iter_mut() {
+                skyline.extend(self.width, width);
             }
-            // place rect
-            rect.x = x;
-            rect.y = y;
-            // move along
-            x += rect.w;
+            self.width = width;
+            true
+        } else if can_grow_height {
+            self.height = (self.height * 2).min(self.max_size);
+            true
+        } else {
+            false
         }
-        self.height = y + max_h;
     }
 
     pub fn get_rect(&self, handle: RectHandle) -> Option<&Rect> {
@@ -77,22 +275,129 @@ impl Atlas {
     }
 }
 
+#[derive(Clone, Copy, Debug)]
+struct SkylineNode {
+    x: u32,
+    y: u32,
+    w: u32,
+}
+
+/// The top edge of everything packed into a page so far, from left to right.
+#[derive(Debug)]
+struct Skyline {
+    nodes: Vec<SkylineNode>,
+}
+
+impl Skyline {
+    fn new(width: u32) -> Self {
+        Self {
+            nodes: vec![SkylineNode {
+                x: 0,
+                y: 0,
+                w: width,
+            }],
+        }
+    }
+
+    /// Widens the skyline from `old_width` to `new_width`.
+    fn extend(&mut self, old_width: u32, new_width: u32) {
+        self.nodes.push(SkylineNode {
+            x: old_width,
+            y: 0,
+            w: new_width - old_width,
+        });
+        self.merge();
+    }
+
+    /// Returns the node to place a `w` x `h` rect at, along with the rect's position.
+    /// Prefers the position with the lowest top edge, then the narrowest node to keep waste down.
+    fn find_position(&self, w: u32, h: u32, width: u32, height: u32) -> Option<(usize, u32, u32)> {
+        let mut best: Option<(usize, u32, u32)> = None;
+        let mut best_top = u32::MAX;
+        let mut best_width = u32::MAX;
+
+        for (idx, node) in self.nodes.iter().enumerate() {
+            let Some(y) = self.fits(idx, w, h, width, height) else {
+                continue;
+            };
+            let top = y + h;
+            if top < best_top || (top == best_top && node.w < best_width) {
+                best = Some((idx, node.x, y));
+                best_top = top;
+                best_width = node.w;
+            }
+        }
+
+        best
+    }
+
+    /// Returns the y position a `w` x `h` rect would rest at when placed at the start of `idx`, if it fits.
+    fn fits(&self, idx: usize, w: u32, h: u32, width: u32, height: u32) -> Option<u32> {
+        let x = self.nodes[idx].x;
+        if x + w > width {
+            return None;
+        }
+
+        let mut y = 0;
+        let mut covered = 0;
+        for node in &self.nodes[idx..] {
+            if covered >= w {
+                break;
+            }
+            y = y.max(node.y);
+            if y + h > height {
+                return None;
+            }
+            covered += node.w;
+        }
+
+        Some(y)
+    }
+
+    fn insert(&mut self, idx: usize, x: u32, y: u32, w: u32, h: u32) {
+        self.nodes.insert(idx, SkylineNode { x, y: y + h, w });
+
+        // shrink or remove the nodes now covered by the new one
+        let end = x + w;
+        let next = idx + 1;
+        while next < self.nodes.len() {
+            let node = &mut self.nodes[next];
+            if node.x >= end {
+                break;
+            }
+            let overlap = end - node.x;
+            if node.w <= overlap {
+                self.nodes.remove(next);
+            } else {
+                node.x += overlap;
+                node.w -= overlap;
+                break;
+            }
+        }
+
+        self.merge();
+    }
+
+    /// Joins neighbouring nodes at the same height.
+    fn merge(&mut self) {
+        let mut idx = 0;
+        while idx + 1 < self.nodes.len() {
+            if self.nodes[idx].y == self.nodes[idx + 1].y {
+                self.nodes[idx].w += self.nodes[idx + 1].w;
+                self.nodes.remove(idx + 1);
+            } else {
+                idx += 1;
+            }
+        }
+    }
+}
+
 #[derive(Clone, Copy, Eq, PartialEq, Debug)]
 pub struct Rect {
     pub x: u32,
     pub y: u32,
     pub w: u32,
     pub h: u32,
+    /// The page (texture array layer) the rect was placed on.
+    pub page: u32,
 }
-
-// impl Ord for Rect {
-//     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-//         (self.h).cmp(&other.h)
-//     }
-// }
-
-// impl PartialOrd for Rect {
-//     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-//         self.h.partial_cmp(&other.h)
-//     }
-// }
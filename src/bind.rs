@@ -9,10 +9,12 @@ use wgpu::{
     VertexAttribute, VertexBufferLayout,
 };
 
+use crate::mipmap::full_mip_level_count;
+
 pub use wgpu::{
-    vertex_attr_array, BufferUsages, Extent3d, Face, SamplerBindingType, SamplerDescriptor,
-    ShaderStages, TextureFormat, TextureSampleType, TextureUsages, TextureViewDimension,
-    VertexStepMode,
+    vertex_attr_array, BufferUsages, Extent3d, Face, FilterMode, SamplerBindingType,
+    SamplerDescriptor, ShaderStages, TextureFormat, TextureSampleType, TextureUsages,
+    TextureViewDimension, VertexStepMode,
 };
 
 #[derive(Clone, Copy)]
@@ -40,6 +42,9 @@ pub enum BindEntryType<'a> {
         format: TextureFormat,
         size: Extent3d,
         usage: TextureUsages,
+        /// The maximum number of mip levels, 1 disables mipmapping. Clamped to a full mip chain for the texture's size,
+        /// so `u32::MAX` always gets a full chain. Mips are generated on upload, see [crate::render::Render::generate_mipmaps].
+        mip_level_count: u32,
     },
     StorageTexture {
         access: StorageTextureAccess,
@@ -51,9 +56,22 @@ pub enum BindEntryType<'a> {
     },
 }
 
+/// What [BindEntry::texture] creates, see [BindEntryType::Texture].
+#[derive(Clone, Copy, Debug)]
+pub struct TextureDesc {
+    pub size: Extent3d,
+    pub sample_count: u32,
+    pub view_dimension: TextureViewDimension,
+    pub format: TextureFormat,
+    pub usage: TextureUsages,
+    pub mip_level_count: u32,
+}
+
 pub enum BindEntryResource {
     Buffer(Buffer),
     Texture(Texture, TextureView),
+    /// One texture per element of a binding array, see [BindEntry::count].
+    TextureArray(Vec<(Texture, TextureView)>),
     Sampler(Sampler),
 }
 
@@ -76,12 +94,20 @@ impl BindEntryResource {
             _ => unreachable!(),
         }
     }
+    pub fn texture_array(&self) -> &[(Texture, TextureView)] {
+        match self {
+            BindEntryResource::TextureArray(textures) => textures,
+            _ => unreachable!(),
+        }
+    }
 }
 
 #[derive(Clone)]
 pub struct BindEntry<'a> {
     pub visibility: ShaderStages,
     pub ty: BindEntryType<'a>,
+    /// Turns the entry into a binding array of `count` elements.
+    /// For [BindEntryType::Texture] each element gets its own texture, this requires [wgpu::Features::TEXTURE_BINDING_ARRAY].
     pub count: Option<NonZeroU32>,
     // Pass as None. This will be removed.
     // pub resource: Option<BindEntryResource>,
@@ -139,6 +165,8 @@ impl<'a> BindEntry<'a> {
         binding: u32,
         // device: &Device,
         resource: &'b BindEntryResource,
+        // views of a texture binding array, empty for every other resource
+        views: &'b [&'b TextureView],
     ) -> BindGroupEntry {
         let binding_resource = match &self.ty {
             BindEntryType::BufferUniform { size, usages }
@@ -153,6 +181,9 @@ impl<'a> BindEntry<'a> {
                 binding_type,
                 descriptor,
             } => wgpu::BindingResource::Sampler(resource.sampler()),
+            BindEntryType::Texture { .. } if self.count.is_some() => {
+                wgpu::BindingResource::TextureViewArray(views)
+            }
             BindEntryType::Texture {
                 view_dimension,
                 sample_count,
@@ -190,26 +221,44 @@ impl<'a> BindEntry<'a> {
         })
     }
 
-    pub fn texture(
-        &self,
-        device: &Device,
-        size: Extent3d,
-        sample_count: u32,
-        view_dimension: TextureViewDimension,
-        format: TextureFormat,
-        usage: TextureUsages,
-    ) -> (Texture, TextureView) {
+    pub fn texture(&self, device: &Device, desc: TextureDesc) -> (Texture, TextureView) {
+        let TextureDesc {
+            size,
+            sample_count,
+            view_dimension,
+            format,
+            usage,
+            mip_level_count,
+        } = desc;
+        // textures we write into may need to be copied into a bigger texture when they grow
+        let usage = if usage.contains(TextureUsages::COPY_DST) {
+            usage | TextureUsages::COPY_SRC
+        } else {
+            usage
+        };
+        let mip_level_count =
+            mip_level_count.clamp(1, full_mip_level_count(size.width, size.height));
+        // mips are generated by rendering into each level
+        let usage = if mip_level_count > 1 && !format.is_compressed() {
+            usage | TextureUsages::RENDER_ATTACHMENT
+        } else {
+            usage
+        };
         let texture = device.create_texture(&TextureDescriptor {
             label: None,
             size,
-            mip_level_count: 1,
+            mip_level_count,
             sample_count,
             dimension: view_dimension.compatible_texture_dimension(),
             format,
             usage,
             view_formats: &[],
         });
-        let view = texture.create_view(&TextureViewDescriptor::default());
+        // the default view would pick a 2d view for an array with a single layer
+        let view = texture.create_view(&TextureViewDescriptor {
+            dimension: Some(view_dimension),
+            ..Default::default()
+        });
         (texture, view)
     }
 
@@ -228,15 +277,44 @@ impl<'a> BindEntry<'a> {
                 format,
                 size,
                 usage,
+                mip_level_count,
+                ..
+            } if self.count.is_some() => BindEntryResource::TextureArray(
+                (0..self.count.unwrap().get())
+                    .map(|_| {
+                        self.texture(
+                            device,
+                            TextureDesc {
+                                size: *size,
+                                sample_count: *sample_count,
+                                view_dimension: *view_dimension,
+                                format: *format,
+                                usage: *usage,
+                                mip_level_count: *mip_level_count,
+                            },
+                        )
+                    })
+                    .collect(),
+            ),
+            BindEntryType::Texture {
+                view_dimension,
+                sample_count,
+                format,
+                size,
+                usage,
+                mip_level_count,
                 ..
             } => {
                 let (texture, view) = self.texture(
                     device,
-                    *size,
-                    *sample_count,
-                    *view_dimension,
-                    *format,
-                    *usage,
+                    TextureDesc {
+                        size: *size,
+                        sample_count: *sample_count,
+                        view_dimension: *view_dimension,
+                        format: *format,
+                        usage: *usage,
+                        mip_level_count: *mip_level_count,
+                    },
                 );
                 BindEntryResource::Texture(texture, view)
             }
@@ -250,11 +328,14 @@ impl<'a> BindEntry<'a> {
             } => {
                 let (texture, view) = self.texture(
                     device,
-                    *size,
-                    *sample_count,
-                    *view_dimension,
-                    *format,
-                    *usage,
+                    TextureDesc {
+                        size: *size,
+                        sample_count: *sample_count,
+                        view_dimension: *view_dimension,
+                        format: *format,
+                        usage: *usage,
+                        mip_level_count: 1,
+                    },
                 );
                 BindEntryResource::Texture(texture, view)
             }
@@ -298,11 +379,27 @@ impl<'a> Bind<'a> {
     }
 
     pub fn create_bind_group(&mut self, device: &Device) {
+        let views = self
+            .resources
+            .iter()
+            .map(|resource| match resource {
+                BindEntryResource::TextureArray(textures) => {
+                    textures.iter().map(|(_, view)| view).collect_vec()
+                }
+                _ => Vec::new(),
+            })
+            .collect_vec();
         let group_entries = self
             .bind_entries
             .iter_mut()
             .enumerate()
-            .map(|(idx, g)| g.group_entry(idx as u32, self.resources.get(idx).unwrap()))
+            .map(|(idx, g)| {
+                g.group_entry(
+                    idx as u32,
+                    self.resources.get(idx).unwrap(),
+                    views.get(idx).unwrap(),
+                )
+            })
             .collect::<Vec<_>>();
         let bg = device.create_bind_group(&BindGroupDescriptor {
             label: None,
@@ -325,6 +422,25 @@ impl<'a> Bind<'a> {
 
         self.create_bind_group(device);
     }
+
+    /// Replaces a single texture of a texture binding array.
+    pub fn replace_array_element(
+        &mut self,
+        texture: Texture,
+        view: TextureView,
+        binding: u32,
+        index: u32,
+        device: &Device,
+    ) {
+        match self.resources.get_mut(binding as usize).unwrap() {
+            BindEntryResource::TextureArray(textures) => {
+                textures[index as usize] = (texture, view);
+            }
+            _ => unreachable!(),
+        }
+
+        self.create_bind_group(device);
+    }
 }
 
 pub struct VertexBufferEntry {
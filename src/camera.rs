@@ -82,6 +82,21 @@ impl Camera {
         OPENGL_TO_WGPU_MATRIX * self.projection() * (self.view() * model)
     }
 
+    /// How many world units a pixel covers at `point`, on a viewport `viewport_height` pixels tall.
+    pub fn world_units_per_pixel(&self, point: &Point3<f32>, viewport_height: f32) -> f32 {
+        match self.projection_type {
+            ProjectionType::Perspective { fovy, .. } => {
+                // depth along the view direction rather than distance to the eye,
+                // so that things at the same depth are the same size across the screen
+                let depth = -self.view().transform_point(point).z;
+                2.0 * depth.max(0.0) * (fovy / 2.0).tan() / viewport_height
+            }
+            ProjectionType::Orthographic { top, bottom, .. } => {
+                (top - bottom).abs() / viewport_height
+            }
+        }
+    }
+
     pub fn uniform(&self) -> CameraUniform {
         CameraUniform {
             view_proj: self.view_projection().into(),
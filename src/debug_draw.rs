@@ -0,0 +1,421 @@
+// immediate mode debug drawing
+// everything is queued up during the frame and uploaded in one go by [DebugDraw::flush]
+// lines are drawn with a line list pipeline out of a single streaming vertex buffer per depth mode
+
+use std::{f32::consts::TAU, rc::Rc};
+
+use nalgebra::{Matrix4, Point3, Scale3, Translation3, Vector3};
+use wgpu::{vertex_attr_array, BufferUsages, ShaderStages};
+
+use crate::{
+    bind::{BindEntry, BindEntryType, BindHandle},
+    camera::{Camera, CameraUniform},
+    geometry::Geometry,
+    instance::InstanceData,
+    material::BasicMaterial,
+    pipeline::{PipelineBuilder, PipelineHandle, PrimitiveTopology},
+    plain::Plain,
+    render::{Mesh, MeshHandle, Render},
+    text::{font_bitmap_manager::FontBitmapManager, text_builder::TextBuilder},
+};
+
+/// Debug geometry is drawn after everything else so that the on-top mode can't be drawn over.
+pub const DEBUG_DRAW_ORDER: i32 = 1000;
+
+const CIRCLE_SEGMENTS: usize = 32;
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct LineVertex {
+    pub pos: [f32; 3],
+    pub color: [f32; 4],
+}
+
+unsafe impl Plain for LineVertex {}
+
+#[derive(Debug, Default)]
+pub struct LineGeometry {
+    pub vertices: Vec<LineVertex>,
+}
+
+impl Geometry for LineGeometry {
+    fn contents(&self) -> &[u8] {
+        self.vertices.as_bytes()
+    }
+
+    fn length(&self) -> u32 {
+        self.vertices.len() as u32
+    }
+
+    fn indices(&self) -> Option<&[u8]> {
+        None
+    }
+}
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct LineInstance {
+    pub transform: Matrix4<f32>,
+}
+
+unsafe impl Plain for LineInstance {}
+
+impl InstanceData for LineInstance {}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DepthMode {
+    /// Lines are hidden behind closer geometry.
+    Tested,
+    /// Lines are drawn on top of everything.
+    OnTop,
+}
+
+struct LineLayer {
+    pipeline_handle: PipelineHandle,
+    mesh_handle: MeshHandle,
+    vertices: Vec<LineVertex>,
+}
+
+impl LineLayer {
+    fn new(render: &mut Render, bind: BindHandle, depth_test: bool) -> Self {
+        let pipeline = PipelineBuilder::new()
+            .with_cull_mode(None)
+            .with_topology(PrimitiveTopology::LineList)
+            .with_depth_test(depth_test)
+            .with_order(DEBUG_DRAW_ORDER)
+            .with_bind(bind)
+            .with_shader(include_str!("shaders/debug_lines.wgsl"))
+            .with_vb::<LineVertex>(
+                wgpu::VertexStepMode::Vertex,
+                &vertex_attr_array![
+                    // position
+                    0 => Float32x3,
+                    // color
+                    1 => Float32x4,
+                ],
+            )
+            .with_vb::<LineInstance>(
+                wgpu::VertexStepMode::Instance,
+                &vertex_attr_array![
+                    // transform
+                    2 => Float32x4,
+                    3 => Float32x4,
+                    4 => Float32x4,
+                    5 => Float32x4,
+                ],
+            )
+            .build(render);
+        let pipeline_handle = render.add_pipeline(pipeline);
+        let mesh_handle = render.add_streaming_mesh(Mesh {
+            material: BasicMaterial {},
+            geometry: LineGeometry::default(),
+        });
+
+        Self {
+            pipeline_handle,
+            mesh_handle,
+            vertices: Vec::new(),
+        }
+    }
+
+    fn flush(&mut self, render: &mut Render) {
+        if self.vertices.is_empty() {
+            return;
+        }
+
+        let geometry = LineGeometry {
+            vertices: std::mem::take(&mut self.vertices),
+        };
+        render
+            .update_mesh(self.mesh_handle, geometry)
+            .expect("Debug mesh should exist");
+        render.add_instance(
+            self.mesh_handle,
+            self.pipeline_handle,
+            LineInstance {
+                transform: Matrix4::identity(),
+            },
+        );
+    }
+}
+
+struct DebugText {
+    manager: Rc<FontBitmapManager>,
+    pipeline_handle: PipelineHandle,
+    mesh_handle: MeshHandle,
+    queued: Vec<(Point3<f32>, String, [f32; 4], f32)>,
+}
+
+/// Immediate mode drawing of lines, shapes and labels for visualising things like bounding boxes, normals and paths.
+///
+/// Queue up whatever needs drawing during the frame then call [DebugDraw::flush] before [Render::draw].
+///
+/// ```ignore
+/// let mut debug = DebugDraw::new(&mut render);
+/// debug.set_camera(&mut render, &camera);
+///
+/// // every frame
+/// debug.aabb(min, max, [0.0, 1.0, 0.0, 1.0]);
+/// debug.set_depth_mode(DepthMode::OnTop);
+/// debug.axes(&transform, 1.0);
+/// debug.flush(&mut render);
+/// render.draw()?;
+/// ```
+pub struct DebugDraw {
+    bind: BindHandle,
+    tested: LineLayer,
+    on_top: LineLayer,
+    depth_mode: DepthMode,
+    view: Matrix4<f32>,
+    text: Option<DebugText>,
+}
+
+impl DebugDraw {
+    pub fn new(render: &mut Render) -> Self {
+        let bind = render.build_bind(&mut [BindEntry {
+            visibility: ShaderStages::VERTEX,
+            ty: BindEntryType::BufferUniform {
+                size: std::mem::size_of::<CameraUniform>() as u64,
+                usages: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            },
+            count: None,
+        }]);
+
+        Self {
+            bind,
+            tested: LineLayer::new(render, bind, true),
+            on_top: LineLayer::new(render, bind, false),
+            depth_mode: DepthMode::Tested,
+            view: Matrix4::identity(),
+            text: None,
+        }
+    }
+
+    /// Enables [DebugDraw::text_at] using an existing text pipeline and glyph mesh, see [crate::text::pipeline::text_pipeline].
+    pub fn with_text(
+        mut self,
+        manager: Rc<FontBitmapManager>,
+        pipeline_handle: PipelineHandle,
+        mesh_handle: MeshHandle,
+    ) -> Self {
+        self.text = Some(DebugText {
+            manager,
+            pipeline_handle,
+            mesh_handle,
+            queued: Vec::new(),
+        });
+        self
+    }
+
+    pub fn set_camera(&mut self, render: &mut Render, camera: &Camera) {
+        self.view = camera.view();
+        render.write_buffer(camera.uniform().as_bytes(), self.bind, 0);
+    }
+
+    /// Sets the depth mode used by everything drawn after this call.
+    pub fn set_depth_mode(&mut self, depth_mode: DepthMode) {
+        self.depth_mode = depth_mode;
+    }
+
+    fn vertices(&mut self) -> &mut Vec<LineVertex> {
+        match self.depth_mode {
+            DepthMode::Tested => &mut self.tested.vertices,
+            DepthMode::OnTop => &mut self.on_top.vertices,
+        }
+    }
+
+    pub fn line(&mut self, a: Point3<f32>, b: Point3<f32>, color: [f32; 4]) {
+        self.vertices().extend([
+            LineVertex {
+                pos: a.into(),
+                color,
+            },
+            LineVertex {
+                pos: b.into(),
+                color,
+            },
+        ]);
+    }
+
+    /// Draws a line through each consecutive pair of points.
+    pub fn path(&mut self, points: &[Point3<f32>], color: [f32; 4]) {
+        for pair in points.windows(2) {
+            self.line(pair[0], pair[1], color);
+        }
+    }
+
+    pub fn aabb(&mut self, min: Point3<f32>, max: Point3<f32>, color: [f32; 4]) {
+        let corner = |x: bool, y: bool, z: bool| {
+            Point3::new(
+                if x { max.x } else { min.x },
+                if y { max.y } else { min.y },
+                if z { max.z } else { min.z },
+            )
+        };
+
+        for a in [false, true] {
+            for b in [false, true] {
+                self.line(corner(false, a, b), corner(true, a, b), color);
+                self.line(corner(a, false, b), corner(a, true, b), color);
+                self.line(corner(a, b, false), corner(a, b, true), color);
+            }
+        }
+    }
+
+    /// Draws a circle around `center` in the plane spanned by `u` and `v`, which should be orthonormal.
+    pub fn circle(
+        &mut self,
+        center: Point3<f32>,
+        radius: f32,
+        u: Vector3<f32>,
+        v: Vector3<f32>,
+        color: [f32; 4],
+    ) {
+        let point = |idx: usize| {
+            let angle = idx as f32 / CIRCLE_SEGMENTS as f32 * TAU;
+            center + (u * angle.cos() + v * angle.sin()) * radius
+        };
+        for idx in 0..CIRCLE_SEGMENTS {
+            self.line(point(idx), point(idx + 1), color);
+        }
+    }
+
+    /// Draws a sphere as three circles, one around each axis.
+    pub fn sphere(&mut self, center: Point3<f32>, radius: f32, color: [f32; 4]) {
+        let (x, y, z) = (Vector3::x(), Vector3::y(), Vector3::z());
+        self.circle(center, radius, x, y, color);
+        self.circle(center, radius, y, z, color);
+        self.circle(center, radius, z, x, color);
+    }
+
+    /// Draws a grid on the xz plane, centered on `center` and `size` wide with `divisions` cells along each side.
+    pub fn grid(&mut self, center: Point3<f32>, size: f32, divisions: u32, color: [f32; 4]) {
+        let half = size / 2.0;
+        let step = size / divisions.max(1) as f32;
+        for idx in 0..=divisions.max(1) {
+            let offset = -half + idx as f32 * step;
+            self.line(
+                center + Vector3::new(offset, 0.0, -half),
+                center + Vector3::new(offset, 0.0, half),
+                color,
+            );
+            self.line(
+                center + Vector3::new(-half, 0.0, offset),
+                center + Vector3::new(half, 0.0, offset),
+                color,
+            );
+        }
+    }
+
+    /// Draws a line from `from` to `to` with an arrow head at `to`.
+    pub fn arrow(&mut self, from: Point3<f32>, to: Point3<f32>, color: [f32; 4]) {
+        self.line(from, to, color);
+
+        let direction = to - from;
+        let length = direction.norm();
+        if length <= f32::EPSILON {
+            return;
+        }
+        let direction = direction / length;
+        // any vector that isn't parallel to the arrow will do to build the head's basis
+        let helper = if direction.y.abs() < 0.99 {
+            Vector3::y()
+        } else {
+            Vector3::x()
+        };
+        let side = direction.cross(&helper).normalize();
+        let up = direction.cross(&side);
+        let head = length * 0.2;
+        let base = to - direction * head;
+        for offset in [side, -side, up, -up] {
+            self.line(to, base + offset * head * 0.5, color);
+        }
+    }
+
+    /// Draws the x, y and z axes of `transform` in red, green and blue.
+    pub fn axes(&mut self, transform: &Matrix4<f32>, length: f32) {
+        let origin = transform.transform_point(&Point3::origin());
+        let axes = [
+            (Vector3::x(), [1.0, 0.0, 0.0, 1.0]),
+            (Vector3::y(), [0.0, 1.0, 0.0, 1.0]),
+            (Vector3::z(), [0.0, 0.0, 1.0, 1.0]),
+        ];
+        for (axis, color) in axes {
+            let end = transform.transform_point(&(Point3::origin() + axis * length));
+            self.arrow(origin, end, color);
+        }
+    }
+
+    /// Draws the frustum described by `view_projection`, as returned by [Camera::view_projection].
+    pub fn frustum(&mut self, view_projection: &Matrix4<f32>, color: [f32; 4]) {
+        let Some(inverse) = view_projection.try_inverse() else {
+            return;
+        };
+        let corner = |x: f32, y: f32, z: f32| inverse.transform_point(&Point3::new(x, y, z));
+
+        for z in [-1.0, 1.0] {
+            self.path(
+                &[
+                    corner(-1.0, -1.0, z),
+                    corner(1.0, -1.0, z),
+                    corner(1.0, 1.0, z),
+                    corner(-1.0, 1.0, z),
+                    corner(-1.0, -1.0, z),
+                ],
+                color,
+            );
+        }
+        for (x, y) in [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)] {
+            self.line(corner(x, y, -1.0), corner(x, y, 1.0), color);
+        }
+    }
+
+    /// Queues a label at `world_pos` that faces the camera. Requires [DebugDraw::with_text].
+    pub fn text_at(&mut self, world_pos: Point3<f32>, text: &str, color: [f32; 4], scale: f32) {
+        if let Some(debug_text) = self.text.as_mut() {
+            debug_text
+                .queued
+                .push((world_pos, text.to_string(), color, scale));
+        } else {
+            log::warn!("DebugDraw::text_at called without text support, see DebugDraw::with_text");
+        }
+    }
+
+    /// Uploads everything queued this frame to the renderer. Call this once per frame before [Render::draw].
+    pub fn flush(&mut self, render: &mut Render) {
+        self.tested.flush(render);
+        self.on_top.flush(render);
+
+        let Some(debug_text) = self.text.as_mut() else {
+            return;
+        };
+
+        // undo the camera's rotation so that labels face it
+        let mut facing = self.view.transpose();
+        facing.fixed_view_mut::<3, 1>(0, 3).fill(0.0);
+        facing.fixed_view_mut::<1, 3>(3, 0).fill(0.0);
+
+        for (world_pos, text, color, scale) in debug_text.queued.drain(..) {
+            let transform = Translation3::from(world_pos.coords).to_homogeneous()
+                * facing
+                * Scale3::new(scale, scale, 1.0).to_homogeneous();
+            let built = TextBuilder::new(
+                &text,
+                color,
+                transform,
+                debug_text.manager.clone(),
+                debug_text.pipeline_handle,
+                debug_text.mesh_handle,
+                1.0,
+            )
+            .build(render);
+
+            match built {
+                Ok(render_objects) => render_objects
+                    .into_iter()
+                    .for_each(|render_object| render.add_render_object(render_object)),
+                Err(err) => log::warn!("Couldn't draw debug text {:?}: {}", text, err),
+            }
+        }
+    }
+}
@@ -1,5 +1,7 @@
 use std::fmt::Debug;
 
+pub use wgpu::IndexFormat;
+
 use crate::plain::Plain;
 
 #[repr(C)]
@@ -12,10 +14,61 @@ pub struct Vertex {
 
 unsafe impl Plain for Vertex {}
 
+/// Index data in either of the formats supported by wgpu.
+#[derive(Debug, Clone)]
+pub enum Indices {
+    U16(Vec<u16>),
+    U32(Vec<u32>),
+}
+
+impl Indices {
+    /// Picks the smallest index format able to address `vertex_count` vertices.
+    pub fn for_vertex_count(indices: impl IntoIterator<Item = u32>, vertex_count: usize) -> Self {
+        if vertex_count <= u16::MAX as usize + 1 {
+            Indices::U16(indices.into_iter().map(|index| index as u16).collect())
+        } else {
+            Indices::U32(indices.into_iter().collect())
+        }
+    }
+
+    pub fn format(&self) -> IndexFormat {
+        match self {
+            Indices::U16(_) => IndexFormat::Uint16,
+            Indices::U32(_) => IndexFormat::Uint32,
+        }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        match self {
+            Indices::U16(indices) => indices.as_bytes(),
+            Indices::U32(indices) => indices.as_bytes(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            Indices::U16(indices) => indices.len(),
+            Indices::U32(indices) => indices.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// The size in bytes of a single index of `format`.
+pub fn index_format_size(format: IndexFormat) -> u32 {
+    match format {
+        IndexFormat::Uint16 => std::mem::size_of::<u16>() as u32,
+        IndexFormat::Uint32 => std::mem::size_of::<u32>() as u32,
+    }
+}
+
 #[derive(Debug)]
 pub struct BasicGeometry {
     pub vertices: Vec<Vertex>,
-    pub indices: Option<Vec<u16>>,
+    pub indices: Option<Indices>,
 }
 
 impl Geometry for BasicGeometry {
@@ -30,6 +83,12 @@ impl Geometry for BasicGeometry {
     fn indices(&self) -> Option<&[u8]> {
         self.indices.as_ref().map(|indices| indices.as_bytes())
     }
+
+    fn index_format(&self) -> IndexFormat {
+        self.indices
+            .as_ref()
+            .map_or(IndexFormat::Uint16, |indices| indices.format())
+    }
 }
 
 pub trait Geometry: Debug {
@@ -38,6 +97,12 @@ pub trait Geometry: Debug {
     fn length(&self) -> u32;
 
     fn indices(&self) -> Option<&[u8]>;
+
+    /// The format of the bytes returned by [Geometry::indices]. Ignored for geometry without indices.
+    /// Defaults to u16, only geometry that can have more than 65536 vertices needs to override it.
+    fn index_format(&self) -> IndexFormat {
+        IndexFormat::Uint16
+    }
 }
 
 impl Geometry for Box<dyn Geometry> {
@@ -52,4 +117,8 @@ impl Geometry for Box<dyn Geometry> {
     fn indices(&self) -> Option<&[u8]> {
         self.as_ref().indices()
     }
+
+    fn index_format(&self) -> IndexFormat {
+        self.as_ref().index_format()
+    }
 }
@@ -3,7 +3,7 @@ use std::{fmt::Debug, path::Path};
 use anyhow::{anyhow, Result};
 
 use crate::{
-    geometry::{BasicGeometry, Vertex},
+    geometry::{BasicGeometry, Indices, Vertex},
     material::BasicMaterial,
     render::Mesh,
 };
@@ -18,7 +18,7 @@ pub fn load_mesh<P: AsRef<Path> + Debug + Copy>(
     let mut meshes = Vec::new();
 
     for model in models {
-        let vertices = model
+        let vertices: Vec<Vertex> = model
             .vertices()
             .iter()
             .map(|vertex| Vertex {
@@ -28,9 +28,10 @@ pub fn load_mesh<P: AsRef<Path> + Debug + Copy>(
             })
             .collect();
 
-        let indices = model
-            .indices()
-            .map(|indices| indices.iter().map(|index| *index as u16).collect());
+        // meshes with more than 65536 vertices can't be addressed with u16 indices
+        let indices = model.indices().map(|indices| {
+            Indices::for_vertex_count(indices.iter().map(|index| *index as u32), vertices.len())
+        });
 
         meshes.push(Mesh {
             material: BasicMaterial {},
@@ -25,16 +25,11 @@ use std::fmt::Debug;
 use crate::plain::Plain;
 
 // TODO: rename to just `Instance`
-pub trait InstanceData: Debug {
-    fn data(&self) -> &[u8];
-    // {
-    // self.as_bytes()
-    // }
-}
-
-impl InstanceData for Box<dyn InstanceData> {
+/// Per-instance data that gets copied straight into an instance buffer.
+/// Instances are required to be [Plain] so that they can be written as contiguous bytes without boxing.
+pub trait InstanceData: Plain + Debug {
     fn data(&self) -> &[u8] {
-        self.as_ref().data()
+        self.as_bytes()
     }
 }
 
@@ -52,3 +47,20 @@ impl InstanceData for BasicInstance {
 }
 
 unsafe impl Plain for BasicInstance {}
+
+/// Instance data for textures stored in a texture array rather than an atlas, see [crate::render::Render::add_texture_to_array].
+///
+/// With a layered array the texture is sampled with `textureSample(t, s, uv * uv_scale, layer)`,
+/// with a bindless array it's sampled with `textureSample(textures[layer], s, uv)`.
+#[repr(C)]
+#[derive(Debug)]
+pub struct LayeredInstance {
+    pub transform: Matrix4<f32>,
+    pub uv_scale: [f32; 2],
+    pub layer: u32,
+    pub padding: u32,
+}
+
+impl InstanceData for LayeredInstance {}
+
+unsafe impl Plain for LayeredInstance {}
@@ -1,18 +1,24 @@
 pub mod atlas;
 pub mod bind;
 pub mod camera;
+pub mod debug_draw;
 pub mod geometry;
 pub mod gltf;
 pub mod input;
 pub mod instance;
 pub mod material;
+pub mod mipmap;
 pub mod pipeline;
 pub mod plain;
+pub mod post;
 pub mod render;
 pub mod render_object;
 pub mod shapes;
+pub mod skybox;
+pub mod sprite;
 pub mod text;
 pub mod texture;
+pub mod vector;
 pub mod window;
 
 // how is ui going to work?
@@ -0,0 +1,201 @@
+use std::{collections::HashMap, ops::Range};
+
+use wgpu::{
+    BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, BindingResource, BindingType, Color, ColorTargetState, ColorWrites,
+    CommandEncoderDescriptor, Device, FilterMode, FragmentState, LoadOp, MultisampleState,
+    Operations, PipelineLayout, PipelineLayoutDescriptor, PrimitiveState, Queue,
+    RenderPassColorAttachment, RenderPassDescriptor, RenderPipeline, RenderPipelineDescriptor,
+    Sampler, SamplerBindingType, SamplerDescriptor, ShaderModule, ShaderModuleDescriptor,
+    ShaderSource, ShaderStages, StoreOp, Texture, TextureFormat, TextureFormatFeatureFlags,
+    TextureSampleType, TextureViewDescriptor, TextureViewDimension, VertexState,
+};
+
+/// The number of mip levels in a full mip chain for a texture of this size.
+pub fn full_mip_level_count(width: u32, height: u32) -> u32 {
+    32 - width.max(height).max(1).leading_zeros()
+}
+
+/// Whether `format` can be sampled with a linear filter on this device.
+pub fn is_filterable(device: &Device, format: TextureFormat) -> bool {
+    format
+        .guaranteed_format_features(device.features())
+        .flags
+        .contains(TextureFormatFeatureFlags::FILTERABLE)
+}
+
+/// Generates mip chains on the GPU, every level is a linearly filtered downsample of the level above it.
+///
+/// Textures need [wgpu::TextureUsages::RENDER_ATTACHMENT] and a renderable, filterable format,
+/// [crate::bind::BindEntry::texture] takes care of the usage for textures with more than one mip level.
+pub struct MipmapGenerator {
+    shader: ShaderModule,
+    sampler: Sampler,
+    bgl: BindGroupLayout,
+    layout: PipelineLayout,
+    // one pipeline per target format, created the first time a format shows up
+    pipelines: HashMap<TextureFormat, RenderPipeline>,
+}
+
+impl MipmapGenerator {
+    pub fn new(device: &Device) -> Self {
+        let shader = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("mipmap shader"),
+            source: ShaderSource::Wgsl(include_str!("shaders/mipmap.wgsl").into()),
+        });
+
+        let sampler = device.create_sampler(&SamplerDescriptor {
+            label: Some("mipmap sampler"),
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let bgl = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("mipmap bind group layout"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float { filterable: true },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+
+        let layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("mipmap pipeline layout"),
+            bind_group_layouts: &[&bgl],
+            push_constant_ranges: &[],
+        });
+
+        Self {
+            shader,
+            sampler,
+            bgl,
+            layout,
+            pipelines: HashMap::new(),
+        }
+    }
+
+    fn prepare_pipeline(&mut self, device: &Device, format: TextureFormat) {
+        let (shader, layout) = (&self.shader, &self.layout);
+        self.pipelines.entry(format).or_insert_with(|| {
+            device.create_render_pipeline(&RenderPipelineDescriptor {
+                label: Some("mipmap pipeline"),
+                layout: Some(layout),
+                vertex: VertexState {
+                    module: shader,
+                    entry_point: "vertex",
+                    compilation_options: Default::default(),
+                    buffers: &[],
+                },
+                fragment: Some(FragmentState {
+                    module: shader,
+                    entry_point: "fragment",
+                    compilation_options: Default::default(),
+                    targets: &[Some(ColorTargetState {
+                        format,
+                        blend: None,
+                        write_mask: ColorWrites::ALL,
+                    })],
+                }),
+                primitive: PrimitiveState::default(),
+                depth_stencil: None,
+                multisample: MultisampleState::default(),
+                multiview: None,
+            })
+        });
+    }
+
+    /// Regenerates mip levels 1 and up of the given array layers from mip level 0.
+    pub fn generate(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        texture: &Texture,
+        layers: Range<u32>,
+    ) {
+        // compressed textures can't be rendered into, their mips have to come with the texture
+        if texture.mip_level_count() < 2 || texture.format().is_compressed() {
+            return;
+        }
+        // e.g. Rgba32Float, which can't be linearly filtered without an extra feature
+        if !is_filterable(device, texture.format()) {
+            log::warn!(
+                "Can't generate mipmaps for {:?} textures since they aren't filterable",
+                texture.format()
+            );
+            return;
+        }
+
+        self.prepare_pipeline(device, texture.format());
+        let pipeline = &self.pipelines[&texture.format()];
+        let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("mipmap encoder"),
+        });
+
+        for layer in layers {
+            let view = |mip_level| {
+                texture.create_view(&TextureViewDescriptor {
+                    dimension: Some(TextureViewDimension::D2),
+                    base_mip_level: mip_level,
+                    mip_level_count: Some(1),
+                    base_array_layer: layer,
+                    array_layer_count: Some(1),
+                    ..Default::default()
+                })
+            };
+
+            for mip_level in 1..texture.mip_level_count() {
+                let source = view(mip_level - 1);
+                let target = view(mip_level);
+
+                let bind_group = device.create_bind_group(&BindGroupDescriptor {
+                    label: None,
+                    layout: &self.bgl,
+                    entries: &[
+                        BindGroupEntry {
+                            binding: 0,
+                            resource: BindingResource::TextureView(&source),
+                        },
+                        BindGroupEntry {
+                            binding: 1,
+                            resource: BindingResource::Sampler(&self.sampler),
+                        },
+                    ],
+                });
+
+                let mut pass = encoder.begin_render_pass(&RenderPassDescriptor {
+                    label: Some("mipmap pass"),
+                    color_attachments: &[Some(RenderPassColorAttachment {
+                        view: &target,
+                        resolve_target: None,
+                        ops: Operations {
+                            load: LoadOp::Clear(Color::TRANSPARENT),
+                            store: StoreOp::Store,
+                        },
+                    })],
+                    depth_stencil_attachment: None,
+                    timestamp_writes: None,
+                    occlusion_query_set: None,
+                });
+                pass.set_pipeline(pipeline);
+                pass.set_bind_group(0, &bind_group, &[]);
+                pass.draw(0..3, 0..1);
+            }
+        }
+
+        queue.submit([encoder.finish()]);
+    }
+}
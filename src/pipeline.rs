@@ -7,6 +7,7 @@ use wgpu::{
     RenderPipelineDescriptor, ShaderModule, ShaderModuleDescriptor, TextureFormat, VertexAttribute,
     VertexState, VertexStepMode,
 };
+pub use wgpu::{CompareFunction, PrimitiveTopology};
 
 use crate::{
     bind::{BindHandle, VertexBufferEntry},
@@ -30,6 +31,8 @@ pub struct PipelineHandle(pub Index);
 pub struct Pipeline {
     pub pipeline: RenderPipeline,
     pub binds: Vec<BindHandle>,
+    /// Pipelines are drawn in ascending order, pipelines sharing an order are drawn in no particular order.
+    pub order: i32,
 }
 
 pub struct PipelineBuilder {
@@ -37,8 +40,11 @@ pub struct PipelineBuilder {
     binds: Vec<BindHandle>,
     shader_src: Option<String>,
     primitive_state: PrimitiveState,
-    format: TextureFormat,
+    format: Option<TextureFormat>,
     vertex_entries: Vec<VertexBufferEntry>,
+    depth_write_enabled: bool,
+    depth_compare: CompareFunction,
+    order: i32,
 }
 
 impl PipelineBuilder {
@@ -47,11 +53,47 @@ impl PipelineBuilder {
             binds: Vec::new(),
             shader_src: None,
             primitive_state: PrimitiveState::default(),
-            format: TextureFormat::Bgra8UnormSrgb,
+            format: None,
             vertex_entries: Vec::new(),
+            depth_write_enabled: true,
+            depth_compare: CompareFunction::Less,
+            order: 0,
         }
     }
 
+    pub fn with_topology(mut self, topology: PrimitiveTopology) -> Self {
+        self.primitive_state.topology = topology;
+        self
+    }
+
+    /// When disabled, fragments always pass the depth test and never write depth, i.e. they're drawn on top of whatever was drawn before them.
+    pub fn with_depth_test(mut self, enabled: bool) -> Self {
+        if enabled {
+            self.depth_compare = CompareFunction::Less;
+            self.depth_write_enabled = true;
+        } else {
+            self.depth_compare = CompareFunction::Always;
+            self.depth_write_enabled = false;
+        }
+        self
+    }
+
+    pub fn with_depth_compare(mut self, depth_compare: CompareFunction) -> Self {
+        self.depth_compare = depth_compare;
+        self
+    }
+
+    pub fn with_depth_write(mut self, enabled: bool) -> Self {
+        self.depth_write_enabled = enabled;
+        self
+    }
+
+    /// See [Pipeline::order]. Defaults to 0.
+    pub fn with_order(mut self, order: i32) -> Self {
+        self.order = order;
+        self
+    }
+
     pub fn with_cull_mode(mut self, cull_mode: Option<Face>) -> Self {
         self.primitive_state.cull_mode = cull_mode;
         self
@@ -62,8 +104,9 @@ impl PipelineBuilder {
         self
     }
 
+    /// The colour target's format, defaults to [Render::color_format].
     pub fn with_format(mut self, format: TextureFormat) -> Self {
-        self.format = format;
+        self.format = Some(format);
         self
     }
 
@@ -135,9 +178,9 @@ impl PipelineBuilder {
                 primitive: self.primitive_state,
                 depth_stencil: Some(wgpu::DepthStencilState {
                     format: TextureFormat::Depth32Float,
-                    depth_write_enabled: true,
-                    depth_compare: wgpu::CompareFunction::Less, // 1.
-                    stencil: wgpu::StencilState::default(),     // 2.
+                    depth_write_enabled: self.depth_write_enabled,
+                    depth_compare: self.depth_compare,
+                    stencil: wgpu::StencilState::default(),
                     bias: wgpu::DepthBiasState::default(),
                 }),
                 multisample: MultisampleState::default(),
@@ -145,7 +188,7 @@ impl PipelineBuilder {
                     module: &module,
                     entry_point: "fragment",
                     targets: &[Some(ColorTargetState {
-                        format: self.format,
+                        format: self.format.unwrap_or(render.color_format()),
                         blend: Some(BlendState::ALPHA_BLENDING),
                         write_mask: ColorWrites::all(),
                     })],
@@ -157,6 +200,7 @@ impl PipelineBuilder {
         Pipeline {
             pipeline,
             binds: take(&mut self.binds),
+            order: self.order,
         }
     }
 }
@@ -0,0 +1,947 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+use naga::valid::{Capabilities, ValidationFlags, Validator};
+use wgpu::{
+    AddressMode, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, BindingResource, BindingType, Buffer, BufferBindingType,
+    BufferDescriptor, BufferUsages, Color, ColorTargetState, ColorWrites, CommandEncoder, Device,
+    Extent3d, FilterMode, FragmentState, ImageCopyTexture, ImageDataLayout, LoadOp,
+    MultisampleState, Operations, Origin3d, PipelineLayout, PipelineLayoutDescriptor,
+    PrimitiveState, Queue, RenderPassColorAttachment, RenderPassDescriptor, RenderPipeline,
+    RenderPipelineDescriptor, Sampler, SamplerBindingType, SamplerDescriptor,
+    ShaderModuleDescriptor, ShaderSource, ShaderStages, StoreOp, TextureAspect, TextureDescriptor,
+    TextureDimension, TextureSampleType, TextureUsages, TextureView, TextureViewDescriptor,
+    TextureViewDimension, VertexState,
+};
+
+use crate::{
+    plain::Plain,
+    texture::{Texture, TextureFormat},
+};
+
+/// The format the scene is rendered into when post-processing is enabled, see [crate::render::Render::color_format].
+pub const HDR_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+/// The largest params a [CustomEffect] can have, in bytes.
+pub const MAX_PARAMS_SIZE: usize = 256;
+
+/// Prepended to every effect, declares the bindings and the fullscreen vertex shader.
+const HEADER: &str = include_str!("shaders/post.wgsl");
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Tonemapper {
+    /// A fit of the ACES filmic curve, contrasty with desaturated highlights.
+    Aces,
+    /// `x / (1 + x)`, softer and keeps hues.
+    Reinhard,
+}
+
+/// Maps the HDR scene into the displayable 0..1 range.
+#[derive(Clone, Copy, Debug)]
+pub struct Tonemapping {
+    pub enabled: bool,
+    pub tonemapper: Tonemapper,
+    /// Colours are multiplied by this before being tonemapped.
+    pub exposure: f32,
+}
+
+impl Default for Tonemapping {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            tonemapper: Tonemapper::Aces,
+            exposure: 1.0,
+        }
+    }
+}
+
+#[repr(C)]
+struct TonemappingParams {
+    exposure: f32,
+    tonemapper: u32,
+    _padding: [u32; 2],
+}
+
+unsafe impl Plain for TonemappingParams {}
+
+/// Makes bright parts of the scene glow.
+#[derive(Clone, Copy, Debug)]
+pub struct Bloom {
+    pub enabled: bool,
+    /// Brightness above which pixels start to glow. Values above 1 only exist in the HDR scene.
+    pub threshold: f32,
+    /// How far below the threshold the glow fades in, 0 for a hard cutoff.
+    pub knee: f32,
+    /// How much of the glow is added to the scene.
+    pub intensity: f32,
+    /// Every pass widens the glow, at the cost of two more fullscreen passes at half resolution.
+    pub blur_passes: u32,
+}
+
+impl Default for Bloom {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            threshold: 1.0,
+            knee: 0.5,
+            intensity: 0.3,
+            blur_passes: 2,
+        }
+    }
+}
+
+#[repr(C)]
+struct BloomThresholdParams {
+    threshold: f32,
+    knee: f32,
+    _padding: [f32; 2],
+}
+
+unsafe impl Plain for BloomThresholdParams {}
+
+#[repr(C)]
+struct BloomBlurParams {
+    direction: [f32; 2],
+    _padding: [f32; 2],
+}
+
+unsafe impl Plain for BloomBlurParams {}
+
+#[repr(C)]
+struct IntensityParams {
+    intensity: f32,
+    _padding: [f32; 3],
+}
+
+unsafe impl Plain for IntensityParams {}
+
+/// Fast approximate anti-aliasing, smooths edges after everything else has been applied.
+#[derive(Clone, Copy, Debug)]
+pub struct Fxaa {
+    pub enabled: bool,
+    /// The local contrast (relative to the brightest pixel) needed for a pixel to count as an edge.
+    pub edge_threshold: f32,
+    /// Stops dark areas from being smoothed, where the relative threshold would be tiny.
+    pub edge_threshold_min: f32,
+    /// The furthest (in pixels) an edge is blurred along.
+    pub span_max: f32,
+}
+
+impl Default for Fxaa {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            edge_threshold: 0.125,
+            edge_threshold_min: 0.0312,
+            span_max: 8.0,
+        }
+    }
+}
+
+#[repr(C)]
+struct FxaaParams {
+    edge_threshold: f32,
+    edge_threshold_min: f32,
+    span_max: f32,
+    _padding: f32,
+}
+
+unsafe impl Plain for FxaaParams {}
+
+/// Remaps colours through a lookup table, applied after tonemapping.
+///
+/// The LUT is a strip of `n` slices of `n` x `n` pixels (so `n * n` wide and `n` tall) with red along x, green along y and blue picking the slice.
+/// Grade a screenshot in an image editor, apply the same adjustments to [ColorGrading::identity_lut] and load the result.
+pub struct ColorGrading {
+    pub enabled: bool,
+    /// Blends between the original (0) and the graded (1) colours.
+    pub intensity: f32,
+    lut: Option<Texture>,
+    lut_changed: bool,
+}
+
+impl Default for ColorGrading {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            intensity: 1.0,
+            lut: None,
+            lut_changed: false,
+        }
+    }
+}
+
+impl ColorGrading {
+    pub fn from_lut(lut: Texture) -> Result<Self> {
+        let mut color_grading = Self {
+            enabled: true,
+            ..Default::default()
+        };
+        color_grading.set_lut(lut)?;
+        Ok(color_grading)
+    }
+
+    /// A LUT that leaves colours unchanged, `size` slices of `size` x `size`.
+    pub fn identity_lut(size: u32) -> Texture {
+        let max = (size.max(2) - 1) as f32;
+        let mut data = Vec::with_capacity((size * size * size * 4) as usize);
+        for y in 0..size {
+            for x in 0..size * size {
+                let (slice, r) = (x / size, x % size);
+                data.extend([
+                    (r as f32 / max * 255.0).round() as u8,
+                    (y as f32 / max * 255.0).round() as u8,
+                    (slice as f32 / max * 255.0).round() as u8,
+                    255,
+                ]);
+            }
+        }
+        Texture {
+            data,
+            width: size * size,
+            height: size,
+            format: TextureFormat::Rgba8Unorm,
+        }
+    }
+
+    pub fn set_lut(&mut self, lut: Texture) -> Result<()> {
+        if lut.format != TextureFormat::Rgba8Unorm {
+            return Err(anyhow!(
+                "Color grading LUTs need to be Rgba8Unorm, got {:?}",
+                lut.format
+            ));
+        }
+        if lut.height < 2 || lut.width != lut.height * lut.height {
+            return Err(anyhow!(
+                "A color grading LUT of size n is n*n x n pixels, got {}x{}",
+                lut.width,
+                lut.height
+            ));
+        }
+        self.lut = Some(lut);
+        self.lut_changed = true;
+        Ok(())
+    }
+
+    pub fn lut(&self) -> Option<&Texture> {
+        self.lut.as_ref()
+    }
+}
+
+/// Where a [CustomEffect] runs in the chain.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EffectStage {
+    /// After bloom and before tonemapping, colours are linear and can go above 1.
+    Hdr,
+    /// After tonemapping and color grading and before FXAA, colours are linear and within 0..1.
+    Ldr,
+}
+
+/// A fullscreen effect written in WGSL.
+///
+/// The effect's source gets the following prepended to it:
+///
+/// ```wgsl
+/// // the output of the previous effect, or the scene for the first one
+/// @group(0) @binding(0)
+/// var input: texture_2d<f32>;
+/// // linear filtering, clamped to the edge
+/// @group(0) @binding(1)
+/// var input_sampler: sampler;
+/// // the scene as it was drawn, before any effects
+/// @group(0) @binding(3)
+/// var aux: texture_2d<f32>;
+///
+/// struct FullscreenOutput {
+///     @builtin(position) position: vec4<f32>,
+///     @location(0) uv: vec2<f32>,
+/// };
+///
+/// fn input_texel_size() -> vec2<f32>;
+/// ```
+///
+/// along with a vertex shader, so an effect only declares a `fragment` entry point writing to location 0.
+/// Params set with [CustomEffect::with_params] are bound as a uniform at binding 2, declared by the effect:
+///
+/// ```wgsl
+/// struct Params {
+///     strength: f32,
+/// }
+///
+/// @group(0) @binding(2)
+/// var<uniform> params: Params;
+///
+/// @fragment
+/// fn fragment(in: FullscreenOutput) -> @location(0) vec4<f32> {
+///     let color = textureSampleLevel(input, input_sampler, in.uv, 0.0);
+///     let vignette = 1.0 - params.strength * length(in.uv - 0.5);
+///     return vec4<f32>(color.rgb * vignette, color.a);
+/// }
+/// ```
+#[derive(Debug)]
+pub struct CustomEffect {
+    pub enabled: bool,
+    shader: String,
+    params: Vec<u8>,
+}
+
+impl CustomEffect {
+    pub fn new(shader: &str) -> Self {
+        Self {
+            enabled: true,
+            shader: shader.into(),
+            params: Vec::new(),
+        }
+    }
+
+    pub fn with_params<T: Plain>(mut self, params: &T) -> Self {
+        self.set_params(params);
+        self
+    }
+
+    /// Panics if `params` are larger than [MAX_PARAMS_SIZE].
+    pub fn set_params<T: Plain>(&mut self, params: &T) {
+        let bytes = params.as_bytes();
+        assert!(
+            bytes.len() <= MAX_PARAMS_SIZE,
+            "Effect params can be at most {} bytes, got {}",
+            MAX_PARAMS_SIZE,
+            bytes.len()
+        );
+        self.params = bytes.to_vec();
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct EffectHandle(usize);
+
+// effects are only compiled once they're first drawn, where an error would take down the frame,
+// so they're checked on the cpu up front
+fn validate_effect(shader: &str) -> Result<()> {
+    let source = effect_source(shader);
+    let module = naga::front::wgsl::parse_str(&source).map_err(|err| {
+        anyhow!(
+            "Effect shader doesn't compile:\n{}",
+            err.emit_to_string(&source)
+        )
+    })?;
+    Validator::new(ValidationFlags::all(), Capabilities::empty())
+        .validate(&module)
+        .map_err(|err| {
+            anyhow!(
+                "Effect shader doesn't compile:\n{}",
+                err.emit_to_string(&source)
+            )
+        })?;
+    let has_fragment = module
+        .entry_points
+        .iter()
+        .any(|entry| entry.name == "fragment" && entry.stage == naga::ShaderStage::Fragment);
+    if !has_fragment {
+        return Err(anyhow!("Effect shaders need a `fragment` entry point"));
+    }
+    Ok(())
+}
+
+fn effect_source(shader: &str) -> String {
+    format!("{}\n{}", HEADER, shader)
+}
+
+/// A chain of fullscreen passes run on the scene before it's presented, enabled with [crate::render::Render::with_post_processing].
+///
+/// The scene is drawn into an HDR ([HDR_FORMAT]) target, then goes through:
+/// bloom, [EffectStage::Hdr] effects, tonemapping, color grading, [EffectStage::Ldr] effects and FXAA.
+/// Disabled effects are skipped. Everything drawn goes through the chain, UI included.
+///
+/// ```ignore
+/// let render = Render::new(window)?.with_post_processing(
+///     PostProcess::new().with_bloom(Bloom {
+///         intensity: 0.5,
+///         ..Default::default()
+///     }),
+/// );
+///
+/// // later on
+/// render.post_processing_mut().unwrap().tonemapping.exposure = 2.0;
+/// ```
+#[derive(Default)]
+pub struct PostProcess {
+    pub bloom: Bloom,
+    pub tonemapping: Tonemapping,
+    pub color_grading: ColorGrading,
+    pub fxaa: Fxaa,
+    effects: Vec<(EffectStage, CustomEffect)>,
+    gpu: Option<PostProcessGpu>,
+}
+
+impl PostProcess {
+    /// Bloom, ACES tonemapping and FXAA, with color grading disabled until it's given a LUT.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_bloom(mut self, bloom: Bloom) -> Self {
+        self.bloom = bloom;
+        self
+    }
+
+    pub fn with_tonemapping(mut self, tonemapping: Tonemapping) -> Self {
+        self.tonemapping = tonemapping;
+        self
+    }
+
+    pub fn with_color_grading(mut self, color_grading: ColorGrading) -> Self {
+        self.color_grading = color_grading;
+        self
+    }
+
+    pub fn with_fxaa(mut self, fxaa: Fxaa) -> Self {
+        self.fxaa = fxaa;
+        self
+    }
+
+    pub fn with_effect(mut self, stage: EffectStage, effect: CustomEffect) -> Result<Self> {
+        self.add_effect(stage, effect)?;
+        Ok(self)
+    }
+
+    /// Effects in the same stage run in the order they were added.
+    ///
+    /// Fails if the effect's shader doesn't compile, see [CustomEffect].
+    pub fn add_effect(&mut self, stage: EffectStage, effect: CustomEffect) -> Result<EffectHandle> {
+        validate_effect(&effect.shader)?;
+        self.effects.push((stage, effect));
+        Ok(EffectHandle(self.effects.len() - 1))
+    }
+
+    pub fn effect_mut(&mut self, handle: EffectHandle) -> Option<&mut CustomEffect> {
+        self.effects.get_mut(handle.0).map(|(_, effect)| effect)
+    }
+
+    /// Makes sure the scene target matches the frame's size, creating the GPU resources the first time around.
+    pub(crate) fn prepare(&mut self, device: &Device, queue: &Queue, width: u32, height: u32) {
+        let gpu = self.gpu.get_or_insert_with(|| PostProcessGpu::new(device));
+        gpu.resize(device, width, height);
+
+        if self.color_grading.lut_changed {
+            self.color_grading.lut_changed = false;
+            if let Some(lut) = &self.color_grading.lut {
+                gpu.upload_lut(device, queue, lut);
+            }
+        }
+    }
+
+    /// The view the scene is drawn into, only valid after [PostProcess::prepare].
+    pub(crate) fn scene_view(&self) -> &TextureView {
+        &self
+            .gpu
+            .as_ref()
+            .expect("Post-processing should be prepared before drawing")
+            .scene
+            .view
+    }
+
+    /// Records the chain, the last pass writes into `output`.
+    pub(crate) fn run(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        encoder: &mut CommandEncoder,
+        output: &TextureView,
+        output_format: wgpu::TextureFormat,
+    ) {
+        let passes = self.passes();
+        let Some(gpu) = self.gpu.as_mut() else {
+            return;
+        };
+
+        for pass in &passes {
+            let format = match pass.target {
+                Slot::Output => output_format,
+                _ => HDR_FORMAT,
+            };
+            let source = match pass.shader {
+                ShaderKey::Custom(idx) => self.effects[idx].1.shader.as_str(),
+                key => key.builtin_source(),
+            };
+            gpu.prepare_pipeline(device, pass.shader, source, format);
+        }
+
+        while gpu.params.len() < passes.len() {
+            gpu.params.push(device.create_buffer(&BufferDescriptor {
+                label: Some("post-processing params"),
+                size: MAX_PARAMS_SIZE as u64,
+                usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }));
+        }
+
+        for (idx, pass) in passes.iter().enumerate() {
+            if !pass.params.is_empty() {
+                queue.write_buffer(&gpu.params[idx], 0, &pass.params);
+            }
+
+            let view = |slot: Slot| match slot {
+                Slot::Scene => &gpu.scene.view,
+                Slot::Ping(i) => &gpu.ping[i].view,
+                Slot::Bloom(i) => &gpu.bloom[i].view,
+                Slot::Lut => gpu.lut.as_ref().map_or(&gpu.dummy.view, |lut| &lut.view),
+                Slot::Dummy => &gpu.dummy.view,
+                Slot::Output => output,
+            };
+
+            let bind_group = device.create_bind_group(&BindGroupDescriptor {
+                label: None,
+                layout: &gpu.bgl,
+                entries: &[
+                    BindGroupEntry {
+                        binding: 0,
+                        resource: BindingResource::TextureView(view(pass.input)),
+                    },
+                    BindGroupEntry {
+                        binding: 1,
+                        resource: BindingResource::Sampler(&gpu.sampler),
+                    },
+                    BindGroupEntry {
+                        binding: 2,
+                        resource: gpu.params[idx].as_entire_binding(),
+                    },
+                    BindGroupEntry {
+                        binding: 3,
+                        resource: BindingResource::TextureView(view(pass.aux)),
+                    },
+                ],
+            });
+
+            let format = match pass.target {
+                Slot::Output => output_format,
+                _ => HDR_FORMAT,
+            };
+
+            let mut rpass = encoder.begin_render_pass(&RenderPassDescriptor {
+                label: Some("post-processing pass"),
+                color_attachments: &[Some(RenderPassColorAttachment {
+                    view: view(pass.target),
+                    resolve_target: None,
+                    ops: Operations {
+                        load: LoadOp::Clear(Color::BLACK),
+                        store: StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            rpass.set_pipeline(&gpu.pipelines[&(pass.shader, format)]);
+            rpass.set_bind_group(0, &bind_group, &[]);
+            rpass.draw(0..3, 0..1);
+        }
+    }
+
+    /// Works out which passes to run this frame, and which targets they read from and write to.
+    fn passes(&self) -> Vec<Pass> {
+        let mut passes = Vec::new();
+        let mut chain = Chain {
+            current: Slot::Scene,
+            next_ping: 0,
+            last: None,
+        };
+
+        if self.bloom.enabled {
+            passes.push(Pass {
+                shader: ShaderKey::BloomThreshold,
+                params: BloomThresholdParams {
+                    threshold: self.bloom.threshold,
+                    knee: self.bloom.knee,
+                    _padding: [0.0; 2],
+                }
+                .as_bytes()
+                .to_vec(),
+                input: chain.current,
+                aux: Slot::Dummy,
+                target: Slot::Bloom(0),
+            });
+            for _ in 0..self.bloom.blur_passes {
+                for (direction, input, target) in [([1.0, 0.0], 0, 1), ([0.0, 1.0], 1, 0)] {
+                    passes.push(Pass {
+                        shader: ShaderKey::BloomBlur,
+                        params: BloomBlurParams {
+                            direction,
+                            _padding: [0.0; 2],
+                        }
+                        .as_bytes()
+                        .to_vec(),
+                        input: Slot::Bloom(input),
+                        aux: Slot::Dummy,
+                        target: Slot::Bloom(target),
+                    });
+                }
+            }
+            chain.push(
+                &mut passes,
+                ShaderKey::BloomComposite,
+                IntensityParams {
+                    intensity: self.bloom.intensity,
+                    _padding: [0.0; 3],
+                }
+                .as_bytes(),
+                Slot::Bloom(0),
+            );
+        }
+
+        self.push_effects(&mut passes, &mut chain, EffectStage::Hdr);
+
+        if self.tonemapping.enabled {
+            chain.push(
+                &mut passes,
+                ShaderKey::Tonemapping,
+                TonemappingParams {
+                    exposure: self.tonemapping.exposure,
+                    tonemapper: match self.tonemapping.tonemapper {
+                        Tonemapper::Aces => 0,
+                        Tonemapper::Reinhard => 1,
+                    },
+                    _padding: [0; 2],
+                }
+                .as_bytes(),
+                Slot::Dummy,
+            );
+        }
+
+        if self.color_grading.enabled && self.color_grading.lut.is_some() {
+            chain.push(
+                &mut passes,
+                ShaderKey::ColorGrading,
+                IntensityParams {
+                    intensity: self.color_grading.intensity,
+                    _padding: [0.0; 3],
+                }
+                .as_bytes(),
+                Slot::Lut,
+            );
+        }
+
+        self.push_effects(&mut passes, &mut chain, EffectStage::Ldr);
+
+        if self.fxaa.enabled {
+            chain.push(
+                &mut passes,
+                ShaderKey::Fxaa,
+                FxaaParams {
+                    edge_threshold: self.fxaa.edge_threshold,
+                    edge_threshold_min: self.fxaa.edge_threshold_min,
+                    span_max: self.fxaa.span_max,
+                    _padding: 0.0,
+                }
+                .as_bytes(),
+                Slot::Dummy,
+            );
+        }
+
+        // everything's disabled, the scene still has to make it onto the screen
+        if chain.last.is_none() {
+            chain.push(&mut passes, ShaderKey::Copy, &[], Slot::Dummy);
+        }
+
+        // the last pass of the chain writes straight into the frame rather than another target
+        if let Some(last) = chain.last {
+            passes[last].target = Slot::Output;
+        }
+
+        passes
+    }
+
+    fn push_effects(&self, passes: &mut Vec<Pass>, chain: &mut Chain, stage: EffectStage) {
+        for (idx, (effect_stage, effect)) in self.effects.iter().enumerate() {
+            if *effect_stage != stage || !effect.enabled {
+                continue;
+            }
+            chain.push(passes, ShaderKey::Custom(idx), &effect.params, Slot::Scene);
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+enum ShaderKey {
+    Copy,
+    BloomThreshold,
+    BloomBlur,
+    BloomComposite,
+    Tonemapping,
+    ColorGrading,
+    Fxaa,
+    Custom(usize),
+}
+
+impl ShaderKey {
+    fn builtin_source(self) -> &'static str {
+        match self {
+            ShaderKey::Copy => include_str!("shaders/post_copy.wgsl"),
+            ShaderKey::BloomThreshold => include_str!("shaders/bloom_threshold.wgsl"),
+            ShaderKey::BloomBlur => include_str!("shaders/bloom_blur.wgsl"),
+            ShaderKey::BloomComposite => include_str!("shaders/bloom_composite.wgsl"),
+            ShaderKey::Tonemapping => include_str!("shaders/tonemap.wgsl"),
+            ShaderKey::ColorGrading => include_str!("shaders/color_grading.wgsl"),
+            ShaderKey::Fxaa => include_str!("shaders/fxaa.wgsl"),
+            ShaderKey::Custom(_) => unreachable!("Custom effects bring their own source"),
+        }
+    }
+}
+
+/// The textures a pass can read from or write to.
+#[derive(Debug, Clone, Copy)]
+enum Slot {
+    Scene,
+    Ping(usize),
+    Bloom(usize),
+    Lut,
+    Dummy,
+    Output,
+}
+
+struct Pass {
+    shader: ShaderKey,
+    params: Vec<u8>,
+    input: Slot,
+    aux: Slot,
+    target: Slot,
+}
+
+/// Tracks the output of the chain so far, passes alternate between the two ping targets.
+struct Chain {
+    current: Slot,
+    next_ping: usize,
+    last: Option<usize>,
+}
+
+impl Chain {
+    fn push(&mut self, passes: &mut Vec<Pass>, shader: ShaderKey, params: &[u8], aux: Slot) {
+        let target = Slot::Ping(self.next_ping);
+        passes.push(Pass {
+            shader,
+            params: params.to_vec(),
+            input: self.current,
+            aux,
+            target,
+        });
+        self.current = target;
+        self.next_ping = 1 - self.next_ping;
+        self.last = Some(passes.len() - 1);
+    }
+}
+
+struct Target {
+    // kept alive for the view
+    _texture: wgpu::Texture,
+    view: TextureView,
+}
+
+impl Target {
+    fn new(device: &Device, label: &str, width: u32, height: u32) -> Self {
+        let texture = device.create_texture(&TextureDescriptor {
+            label: Some(label),
+            size: Extent3d {
+                width: width.max(1),
+                height: height.max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: HDR_FORMAT,
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&TextureViewDescriptor::default());
+        Self {
+            _texture: texture,
+            view,
+        }
+    }
+}
+
+struct PostProcessGpu {
+    bgl: BindGroupLayout,
+    layout: PipelineLayout,
+    sampler: Sampler,
+    pipelines: HashMap<(ShaderKey, wgpu::TextureFormat), RenderPipeline>,
+    // one params buffer per pass, passes all get recorded before any of them run
+    params: Vec<Buffer>,
+    size: (u32, u32),
+    scene: Target,
+    ping: [Target; 2],
+    // half resolution
+    bloom: [Target; 2],
+    lut: Option<Target>,
+    // bound when a pass has no aux texture
+    dummy: Target,
+}
+
+impl PostProcessGpu {
+    fn new(device: &Device) -> Self {
+        let texture_entry = |binding| BindGroupLayoutEntry {
+            binding,
+            visibility: ShaderStages::FRAGMENT,
+            ty: BindingType::Texture {
+                sample_type: TextureSampleType::Float { filterable: true },
+                view_dimension: TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+
+        let bgl = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("post-processing bind group layout"),
+            entries: &[
+                texture_entry(0),
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                texture_entry(3),
+            ],
+        });
+
+        let layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("post-processing pipeline layout"),
+            bind_group_layouts: &[&bgl],
+            push_constant_ranges: &[],
+        });
+
+        let sampler = device.create_sampler(&SamplerDescriptor {
+            label: Some("post-processing sampler"),
+            address_mode_u: AddressMode::ClampToEdge,
+            address_mode_v: AddressMode::ClampToEdge,
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            ..Default::default()
+        });
+
+        Self {
+            bgl,
+            layout,
+            sampler,
+            pipelines: HashMap::new(),
+            params: Vec::new(),
+            size: (1, 1),
+            scene: Target::new(device, "post-processing scene", 1, 1),
+            ping: [
+                Target::new(device, "post-processing ping", 1, 1),
+                Target::new(device, "post-processing pong", 1, 1),
+            ],
+            bloom: [
+                Target::new(device, "bloom ping", 1, 1),
+                Target::new(device, "bloom pong", 1, 1),
+            ],
+            lut: None,
+            dummy: Target::new(device, "post-processing dummy", 1, 1),
+        }
+    }
+
+    fn resize(&mut self, device: &Device, width: u32, height: u32) {
+        if self.size == (width, height) {
+            return;
+        }
+        self.size = (width, height);
+        self.scene = Target::new(device, "post-processing scene", width, height);
+        self.ping = [
+            Target::new(device, "post-processing ping", width, height),
+            Target::new(device, "post-processing pong", width, height),
+        ];
+        self.bloom = [
+            Target::new(device, "bloom ping", width / 2, height / 2),
+            Target::new(device, "bloom pong", width / 2, height / 2),
+        ];
+    }
+
+    fn upload_lut(&mut self, device: &Device, queue: &Queue, lut: &Texture) {
+        let size = Extent3d {
+            width: lut.width,
+            height: lut.height,
+            depth_or_array_layers: 1,
+        };
+        let texture = device.create_texture(&TextureDescriptor {
+            label: Some("color grading lut"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: lut.format.wgpu_format(),
+            usage: TextureUsages::COPY_DST | TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        queue.write_texture(
+            ImageCopyTexture {
+                texture: &texture,
+                mip_level: 0,
+                origin: Origin3d::ZERO,
+                aspect: TextureAspect::All,
+            },
+            &lut.data,
+            ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(lut.format.bytes_per_row(lut.width)),
+                rows_per_image: Some(lut.height),
+            },
+            size,
+        );
+        let view = texture.create_view(&TextureViewDescriptor::default());
+        self.lut = Some(Target {
+            _texture: texture,
+            view,
+        });
+    }
+
+    fn prepare_pipeline(
+        &mut self,
+        device: &Device,
+        key: ShaderKey,
+        source: &str,
+        format: wgpu::TextureFormat,
+    ) {
+        let layout = &self.layout;
+        self.pipelines.entry((key, format)).or_insert_with(|| {
+            let module = device.create_shader_module(ShaderModuleDescriptor {
+                label: Some("post-processing shader"),
+                source: ShaderSource::Wgsl(effect_source(source).into()),
+            });
+            device.create_render_pipeline(&RenderPipelineDescriptor {
+                label: Some("post-processing pipeline"),
+                layout: Some(layout),
+                vertex: VertexState {
+                    module: &module,
+                    entry_point: "vertex",
+                    compilation_options: Default::default(),
+                    buffers: &[],
+                },
+                fragment: Some(FragmentState {
+                    module: &module,
+                    entry_point: "fragment",
+                    compilation_options: Default::default(),
+                    targets: &[Some(ColorTargetState {
+                        format,
+                        blend: None,
+                        write_mask: ColorWrites::ALL,
+                    })],
+                }),
+                primitive: PrimitiveState::default(),
+                depth_stencil: None,
+                multisample: MultisampleState::default(),
+                multiview: None,
+            })
+        });
+    }
+}
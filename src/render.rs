@@ -1,71 +1,84 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{borrow::Cow, collections::HashMap, marker::PhantomData, ops::Range, sync::Arc};
 
 use anyhow::{anyhow, Result};
 
 use generational_arena::{Arena, Index};
 use wgpu::{
     util::{BufferInitDescriptor, DeviceExt},
-    Adapter, Buffer, BufferDescriptor, BufferUsages, Color, CommandEncoderDescriptor, Device,
-    DeviceDescriptor, Extent3d, ImageDataLayout, Instance, Operations, Queue,
-    RenderPassColorAttachment, RenderPassDepthStencilAttachment, RenderPassDescriptor,
-    RequestAdapterOptions, Surface, SurfaceConfiguration, TextureDescriptor, TextureDimension,
-    TextureFormat, TextureUsages, TextureViewDescriptor,
+    Buffer, BufferDescriptor, BufferUsages, Color, CommandEncoderDescriptor, Device,
+    DeviceDescriptor, Extent3d, Features, ImageCopyTexture, ImageDataLayout, Instance, Operations,
+    Origin3d, Queue, RenderPassColorAttachment, RenderPassDepthStencilAttachment,
+    RenderPassDescriptor, RequestAdapterOptions, Surface, SurfaceConfiguration, TextureAspect,
+    TextureDescriptor, TextureDimension, TextureFormat, TextureUsages, TextureViewDescriptor,
+    TextureViewDimension, COPY_BYTES_PER_ROW_ALIGNMENT,
 };
 pub use winit::{dpi::PhysicalSize, window::Window};
 
 use crate::{
-    atlas::{Atlas, RectHandle},
-    bind::{Bind, BindEntry, BindEntryResource, BindHandle},
-    geometry::Geometry,
+    atlas::{Atlas, Rect, RectHandle},
+    bind::{Bind, BindEntry, BindEntryResource, BindEntryType, BindHandle},
+    geometry::{index_format_size, Geometry, IndexFormat},
     instance::InstanceData,
     material::Material,
+    mipmap::{full_mip_level_count, MipmapGenerator},
     pipeline::{Pipeline, PipelineHandle},
+    post::{PostProcess, HDR_FORMAT},
     render_object::RenderObject,
-    texture::Texture,
+    texture::{Cubemap, Texture},
 };
 
+/// Features needed to index into binding arrays of textures from shaders, see [Render::supports_bindless].
+pub const BINDLESS_FEATURES: Features = Features::TEXTURE_BINDING_ARRAY
+    .union(Features::SAMPLED_TEXTURE_AND_STORAGE_BUFFER_ARRAY_NON_UNIFORM_INDEXING);
+
+/// Texture format features that are enabled whenever the adapter has them, see [Render::supports_texture_format].
+pub const OPTIONAL_TEXTURE_FEATURES: Features = Features::TEXTURE_COMPRESSION_BC
+    .union(Features::TEXTURE_COMPRESSION_ETC2)
+    .union(Features::TEXTURE_COMPRESSION_ASTC)
+    .union(Features::TEXTURE_FORMAT_16BIT_NORM);
+
 #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
 struct MeshAndPipelineHandleComposite(MeshHandle, PipelineHandle);
 
+/// Instances queued for a single mesh + pipeline pair, stored as the contiguous bytes that get copied into the instance buffer.
+/// The data is cleared after every frame but both the allocation and the buffer are kept around and only grown when they're too small.
+#[derive(Default)]
+struct InstanceBatch {
+    data: Vec<u8>,
+    count: u32,
+    buffer: Option<Buffer>,
+}
+
+impl InstanceBatch {
+    fn push<T: InstanceData>(&mut self, instance: &T) {
+        self.data.extend_from_slice(instance.data());
+        self.count += 1;
+    }
+
+    fn clear(&mut self) {
+        self.data.clear();
+        self.count = 0;
+    }
+}
+
 // renderer draws meshes
 pub struct Render<'a> {
-    adapter: Adapter,
     device: Option<Device>,
     queue: Queue,
     surface: Surface<'a>,
     pipelines: Arena<Pipeline>,
     binds: Arena<Bind<'a>>,
-    meshes: Arena<(
-        Mesh<Box<dyn Geometry>, Box<dyn Material>>,
-        Buffer,         // vertex
-        Option<Buffer>, // index
-    )>,
+    meshes: Arena<GpuMesh>,
     textures: Arena<Texture>,
     atlases: Arena<(BindHandle, u32, Atlas, HashMap<RectHandle, TextureHandle>)>,
-    instances: HashMap<
-        MeshHandle,
-        (
-            Vec<Box<dyn InstanceData>>,
-            Buffer, // instance - do we need this? it seems not. although, how do we know what instances a mesh has? meshandpipelinehandlecomposite requires a pipeline. which buffer do we want to use?!
-        ),
-    >,
-    // we're in giga type hell now
-    render_objects: HashMap<
-        MeshAndPipelineHandleComposite,
-        (
-            Vec<
-                Box<
-                    dyn RenderObject<
-                        InstanceType = Box<dyn InstanceData>,
-                        GeometryType = Box<dyn Geometry>,
-                        MaterialType = Box<dyn Material>,
-                    >,
-                >,
-            >,
-            Buffer, // instance
-        ),
-    >,
+    texture_arrays: Arena<TextureArray>,
+    // instance data queued for the current frame, grouped by the mesh and pipeline that draw it
+    instances: HashMap<MeshAndPipelineHandleComposite, InstanceBatch>,
     depth_texture: wgpu::Texture,
+    mipmaps: MipmapGenerator,
+    missing_texture_fallback: bool,
+    surface_format: TextureFormat,
+    post: Option<PostProcess>,
 }
 
 impl<'a> Render<'a> {
@@ -86,60 +99,106 @@ impl<'a> Render<'a> {
         self.queue.write_buffer(buffer, 0, data);
     }
 
+    /// Writes tightly packed `data` into a region of a texture binding, e.g. to update part of a texture.
+    ///
+    /// `format` must match the texture's format (ignoring srgb) and `data` must hold exactly `region.width * region.height` pixels.
+    /// When the region reaches past the texture, the texture grows to fit it and its existing contents are copied over on the gpu.
+    /// Mips of the written layer are regenerated afterwards.
     pub fn write_texture(
         &mut self,
         data: &[u8],
-        data_layout: ImageDataLayout,
-        size: Extent3d,
+        format: crate::texture::TextureFormat,
+        region: TextureRegion,
         handle: BindHandle,
         binding: u32,
-    ) {
-        let bind = self.get_bind(handle).unwrap();
-        let resource = bind.resources.get(binding as usize).unwrap();
+    ) -> Result<()> {
+        self.write_texture_region(data, format, region, handle, binding)?;
+        self.generate_texture_mipmaps(handle, binding, region.layer..region.layer + 1);
+        Ok(())
+    }
 
-        let texture = match resource {
-            BindEntryResource::Texture(texture, ..) => texture,
-            _ => unreachable!(),
-        };
+    fn write_texture_region(
+        &mut self,
+        data: &[u8],
+        format: crate::texture::TextureFormat,
+        region: TextureRegion,
+        handle: BindHandle,
+        binding: u32,
+    ) -> Result<()> {
+        let row_len = format.bytes_per_row(region.width) as usize;
+        if data.len() != format.data_size(region.width, region.height) {
+            return Err(anyhow!(
+                "Expected {} bytes for a {}x{} {:?} region but got {}",
+                format.data_size(region.width, region.height),
+                region.width,
+                region.height,
+                format,
+                data.len()
+            ));
+        }
+        let (block_width, block_height) = format.block_dimensions();
+        if !region.x.is_multiple_of(block_width) || !region.y.is_multiple_of(block_height) {
+            return Err(anyhow!(
+                "{:?} regions must start on a {}x{} block boundary",
+                format,
+                block_width,
+                block_height
+            ));
+        }
 
-        // offset is in bytes (four bytes represents one pixel in the case of rgba8)
-        // TODO: support different texture types
-        let x = data_layout.offset * 4 % data_layout.bytes_per_row.unwrap() as u64;
-        let y = data_layout.offset * 4 / data_layout.bytes_per_row.unwrap() as u64;
-        let overflow_x = x as i64 + size.width as i64 - texture.width() as i64;
-        let overflow_y = y as i64 + size.height as i64 - texture.height() as i64;
-
-        if overflow_x > 0 || overflow_y > 0 {
-            let new_size = Extent3d {
-                width: texture.width().max(texture.width() + overflow_x as u32),
-                height: texture.height().max(texture.height() + overflow_y as u32),
-                depth_or_array_layers: texture.depth_or_array_layers(),
-            };
-            let descriptor = TextureDescriptor {
-                label: None,
-                size: new_size,
-                mip_level_count: texture.mip_level_count(),
-                sample_count: texture.sample_count(),
-                dimension: texture.dimension(),
-                format: texture.format(),
-                usage: texture.usage(),
-                view_formats: &[],
-            };
+        let bind = self.get_bind(handle)?;
+        let Some(BindEntryResource::Texture(texture, _)) = bind.resources.get(binding as usize)
+        else {
+            return Err(anyhow!("Binding {} isn't a texture", binding));
+        };
+        if !format.is_compatible_with(texture.format()) {
+            return Err(anyhow!(
+                "Can't write {:?} data into a {:?} texture",
+                format,
+                texture.format()
+            ));
+        }
 
-            let new_texture = self.device().create_texture_with_data(
-                &self.queue,
-                &descriptor,
-                wgpu::util::TextureDataOrder::LayerMajor,
-                data,
-            );
+        let size = texture.size();
+        let required = Extent3d {
+            width: size.width.max(region.x + region.width),
+            height: size.height.max(region.y + region.height),
+            depth_or_array_layers: size.depth_or_array_layers.max(region.layer + 1),
+        };
+        if required != size {
+            self.grow_texture(handle, binding, required);
+        }
 
-            let view = new_texture.create_view(&TextureViewDescriptor::default());
-            let resource = BindEntryResource::Texture(new_texture, view);
-            self.replace_resource(resource, handle, binding);
-        } else {
-            self.queue
-                .write_texture(texture.as_image_copy(), data, data_layout, size);
+        if region.width == 0 || region.height == 0 {
+            return Ok(());
         }
+
+        let (data, bytes_per_row) = pad_rows(data, row_len);
+        let (texture, _) = self.get_bind(handle)?.resources[binding as usize].texture_view();
+        self.queue.write_texture(
+            ImageCopyTexture {
+                texture,
+                mip_level: 0,
+                origin: Origin3d {
+                    x: region.x,
+                    y: region.y,
+                    z: region.layer,
+                },
+                aspect: TextureAspect::All,
+            },
+            &data,
+            ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(bytes_per_row),
+                rows_per_image: Some(format.rows(region.height)),
+            },
+            Extent3d {
+                width: region.width,
+                height: region.height,
+                depth_or_array_layers: 1,
+            },
+        );
+        Ok(())
     }
 
     pub fn new(window: Arc<Window>) -> Result<Self> {
@@ -156,8 +215,17 @@ impl<'a> Render<'a> {
                 .await
                 .ok_or(anyhow!("No suitable adapter found."))?;
 
+            // bindless and compressed textures are optional, texture arrays and uncompressed textures work everywhere
             let (device, queue) = adapter
-                .request_device(&DeviceDescriptor::default(), None)
+                .request_device(
+                    &DeviceDescriptor {
+                        label: None,
+                        required_features: adapter.features()
+                            & (BINDLESS_FEATURES | OPTIONAL_TEXTURE_FEATURES),
+                        required_limits: adapter.limits(),
+                    },
+                    None,
+                )
                 .await?;
 
             Ok::<(wgpu::Adapter, wgpu::Device, wgpu::Queue), anyhow::Error>((
@@ -165,15 +233,17 @@ impl<'a> Render<'a> {
             ))
         })?;
 
+        let surface_format = *surface
+            .get_capabilities(&adapter)
+            .formats
+            .first()
+            .ok_or(anyhow!("No formats found."))?;
+
         surface.configure(
             &device,
             &SurfaceConfiguration {
                 usage: TextureUsages::RENDER_ATTACHMENT,
-                format: *surface
-                    .get_capabilities(&adapter)
-                    .formats
-                    .first()
-                    .ok_or(anyhow!("No formats found."))?,
+                format: surface_format,
                 width: window.clone().inner_size().width,
                 height: window.clone().inner_size().height,
                 present_mode: wgpu::PresentMode::Fifo,
@@ -198,8 +268,9 @@ impl<'a> Render<'a> {
             view_formats: &[],
         });
 
+        let mipmaps = MipmapGenerator::new(&device);
+
         Ok(Self {
-            adapter,
             device: Some(device),
             queue,
             surface,
@@ -208,12 +279,66 @@ impl<'a> Render<'a> {
             meshes: Arena::new(),
             textures: Arena::new(),
             atlases: Arena::new(),
+            texture_arrays: Arena::new(),
             instances: HashMap::new(),
-            render_objects: HashMap::new(),
             depth_texture,
+            mipmaps,
+            missing_texture_fallback: false,
+            surface_format,
+            post: None,
         })
     }
 
+    /// Draws the scene into an HDR target and runs it through `post` before presenting, see [PostProcess].
+    /// Pipelines built beforehand target the wrong format, so enable this before building any.
+    pub fn with_post_processing(mut self, post: PostProcess) -> Self {
+        self.post = Some(post);
+        self
+    }
+
+    pub fn post_processing(&self) -> Option<&PostProcess> {
+        self.post.as_ref()
+    }
+
+    /// For toggling and tweaking effects between frames.
+    pub fn post_processing_mut(&mut self) -> Option<&mut PostProcess> {
+        self.post.as_mut()
+    }
+
+    /// The format pipelines draw into, [HDR_FORMAT] with post-processing and the surface's format without.
+    pub fn color_format(&self) -> TextureFormat {
+        if self.post.is_some() {
+            HDR_FORMAT
+        } else {
+            self.surface_format
+        }
+    }
+
+    /// Makes [Render::load_texture] fall back to a checkerboard texture (and log a warning) when a texture fails to load,
+    /// rather than returning the error.
+    pub fn with_missing_texture_fallback(mut self, enabled: bool) -> Self {
+        self.missing_texture_fallback = enabled;
+        self
+    }
+
+    /// Loads a texture as `format`, see [Texture::load_as].
+    /// Substitutes [Texture::missing] when the texture can't be loaded and [Render::with_missing_texture_fallback] is enabled.
+    pub fn load_texture<P: AsRef<std::path::Path>>(
+        &self,
+        path: P,
+        format: crate::texture::TextureFormat,
+    ) -> Result<Texture> {
+        match Texture::load_as(path, format) {
+            Ok(texture) => Ok(texture),
+            Err(err) if self.missing_texture_fallback => {
+                log::warn!("{}, using the missing texture instead", err);
+                // compressed formats can't be made up on the fly, so there's no fallback for them
+                Texture::missing(format).map_err(|_| err)
+            }
+            Err(err) => Err(err),
+        }
+    }
+
     pub fn add_pipeline(&mut self, pipeline: Pipeline) -> PipelineHandle {
         PipelineHandle(self.pipelines.insert(pipeline))
     }
@@ -231,21 +356,14 @@ impl<'a> Render<'a> {
     }
 
     /// Returns [x, y, x, y] for top left and top right. These values are fractional and represent where this texture is contained on the atlas_texture.
+    ///
+    /// The coordinates are relative to the texture's page, see [Render::get_atlas_page_for_texture] for atlases with more than one page.
     pub fn get_atlas_coords_for_texture(
         &self,
         texture_handle: TextureHandle,
         atlas_handle: AtlasHandle,
     ) -> Result<[f32; 4]> {
-        let (_, _, atlas, rect_to_tex) = self
-            .atlases
-            .get(atlas_handle.0)
-            .ok_or(anyhow!("No matching atlas found"))?;
-        let tex_to_rect = rect_to_tex
-            .iter()
-            .map(|(rect, tex)| (tex, rect))
-            .collect::<HashMap<_, _>>();
-        let rect_handle = **tex_to_rect.get(&texture_handle).unwrap();
-        let rect = atlas.get_rect(rect_handle).unwrap();
+        let (atlas, rect) = self.get_atlas_rect(texture_handle, atlas_handle)?;
         Ok([
             rect.x as f32 / atlas.width as f32,
             rect.y as f32 / atlas.height as f32,
@@ -254,10 +372,40 @@ impl<'a> Render<'a> {
         ])
     }
 
-    // pub fn get_texture() -> Result<()> {
-    //     // TODO
-    //     Ok(())
-    // }
+    /// Returns the page (texture array layer) of the atlas that the texture was placed on.
+    pub fn get_atlas_page_for_texture(
+        &self,
+        texture_handle: TextureHandle,
+        atlas_handle: AtlasHandle,
+    ) -> Result<u32> {
+        let (_, rect) = self.get_atlas_rect(texture_handle, atlas_handle)?;
+        Ok(rect.page)
+    }
+
+    fn get_atlas_rect(
+        &self,
+        texture_handle: TextureHandle,
+        atlas_handle: AtlasHandle,
+    ) -> Result<(&Atlas, Rect)> {
+        let (_, _, atlas, rect_to_tex) = self
+            .atlases
+            .get(atlas_handle.0)
+            .ok_or(anyhow!("No matching atlas found"))?;
+        let rect_handle = rect_to_tex
+            .iter()
+            .find_map(|(rect, tex)| (*tex == texture_handle).then_some(*rect))
+            .ok_or(anyhow!("Texture isn't part of this atlas"))?;
+        let rect = atlas
+            .get_rect(rect_handle)
+            .ok_or(anyhow!("No rect found for texture"))?;
+        Ok((atlas, *rect))
+    }
+
+    pub fn get_texture(&self, handle: TextureHandle) -> Result<&Texture> {
+        self.textures
+            .get(handle.0)
+            .ok_or(anyhow!("No texture for handle"))
+    }
 
     pub fn add_texture(
         &mut self,
@@ -270,21 +418,400 @@ impl<'a> Render<'a> {
             .atlases
             .get_mut(atlas_handle.0)
             .ok_or(anyhow!("Atlas not found."))?;
-        let rect_handle = atlas.add(texture.width(), texture.height());
+        // textures are stitched together pixel by pixel, so they all need to share the atlas' format
+        if texture.format != atlas.format || texture.format.is_compressed() {
+            return Err(anyhow!(
+                "Can't add a {:?} texture to a {:?} atlas",
+                texture.format,
+                atlas.format
+            ));
+        }
+        let rect_handle = atlas.add(texture.width(), texture.height())?;
         let texture_handle = TextureHandle(self.textures.insert(texture));
         rect_to_tex.insert(rect_handle, texture_handle);
-        atlas.pack();
         Ok(texture_handle)
     }
 
-    pub fn get_mesh(
+    /// Removes a texture from the renderer, along with its rect in any atlas so the space can be reused by later textures.
+    ///
+    /// Textures in texture arrays can't be removed since the other textures' layers would shift.
+    pub fn remove_texture(&mut self, handle: TextureHandle) -> Result<Texture> {
+        if self
+            .texture_arrays
+            .iter()
+            .any(|(_, array)| array.layers.contains_key(&handle))
+        {
+            return Err(anyhow!("Textures can't be removed from texture arrays"));
+        }
+
+        let texture = self
+            .textures
+            .remove(handle.0)
+            .ok_or(anyhow!("No texture for handle"))?;
+        for (_, (_, _, atlas, rect_to_tex)) in self.atlases.iter_mut() {
+            rect_to_tex.retain(|rect, tex| {
+                if *tex != handle {
+                    return true;
+                }
+                atlas.remove(*rect);
+                false
+            });
+        }
+        Ok(texture)
+    }
+
+    /// Whether textures of `format` can be created on this device, compressed formats depend on the adapter.
+    pub fn supports_texture_format(&self, format: crate::texture::TextureFormat) -> bool {
+        self.device()
+            .features()
+            .contains(format.wgpu_format().required_features())
+    }
+
+    /// Whether the device supports binding arrays of textures, which [Render::register_texture_array] needs for bindless texture arrays.
+    pub fn supports_bindless(&self) -> bool {
+        self.device().features().contains(BINDLESS_FEATURES)
+    }
+
+    /// Registers a texture binding as a texture array, an alternative to atlases where every texture gets its own layer or index.
+    ///
+    /// The binding must either be a [TextureViewDimension::D2Array] texture, where each texture is written into its own layer,
+    /// or a binding array of [TextureViewDimension::D2] textures (see [BindEntry::count]), where each texture gets its own slot.
+    /// The latter is only possible when [Render::supports_bindless].
+    pub fn register_texture_array(
+        &mut self,
+        handle: BindHandle,
+        binding: u32,
+    ) -> Result<TextureArrayHandle> {
+        let bind = self.get_bind(handle)?;
+        let entry = bind
+            .bind_entries
+            .get(binding as usize)
+            .ok_or(anyhow!("No bind entry at binding {}", binding))?;
+        let BindEntryType::Texture {
+            view_dimension,
+            format,
+            size,
+            mip_level_count,
+            ..
+        } = &entry.ty
+        else {
+            return Err(anyhow!("Binding {} isn't a texture", binding));
+        };
+
+        let kind = match (entry.count, view_dimension) {
+            (Some(count), TextureViewDimension::D2) => {
+                if !self.supports_bindless() {
+                    return Err(anyhow!(
+                        "Texture binding arrays aren't supported by this device"
+                    ));
+                }
+                TextureArrayKind::Bindless {
+                    capacity: count.get(),
+                }
+            }
+            (None, TextureViewDimension::D2Array) => TextureArrayKind::Layers,
+            _ => {
+                return Err(anyhow!(
+                    "Binding {} must be either a D2Array texture or a binding array of D2 textures",
+                    binding
+                ))
+            }
+        };
+
+        let texture_array = TextureArray {
+            bind: handle,
+            binding,
+            format: *format,
+            width: size.width,
+            height: size.height,
+            mip_level_count: *mip_level_count,
+            layers: HashMap::new(),
+            kind,
+        };
+        Ok(TextureArrayHandle(
+            self.texture_arrays.insert(texture_array),
+        ))
+    }
+
+    /// Uploads a texture into its own layer (or slot) of a texture array with a single texture write.
+    ///
+    /// Layered arrays double their layer count when they run out of layers. Textures smaller than a layer are placed at its top left corner,
+    /// see [TextureLayer::uv_scale].
+    pub fn add_texture_to_array(
+        &mut self,
+        mut texture: Texture,
+        array_handle: TextureArrayHandle,
+    ) -> Result<TextureHandle> {
+        let array = self
+            .texture_arrays
+            .get(array_handle.0)
+            .ok_or(anyhow!("Texture array not found."))?;
+        if !texture.format.is_compatible_with(array.format) {
+            return Err(anyhow!(
+                "Texture format {:?} doesn't match the texture array's format {:?}",
+                texture.format,
+                array.format
+            ));
+        }
+
+        let (bind_handle, binding) = (array.bind, array.binding);
+        let index = array.layers.len() as u32;
+        let size = Extent3d {
+            width: texture.width,
+            height: texture.height,
+            depth_or_array_layers: 1,
+        };
+
+        match array.kind {
+            TextureArrayKind::Layers => {
+                if texture.width > array.width || texture.height > array.height {
+                    return Err(anyhow!(
+                        "Texture of size {}x{} doesn't fit into the texture array's {}x{} layers",
+                        texture.width,
+                        texture.height,
+                        array.width,
+                        array.height
+                    ));
+                }
+
+                let size = self.get_bind(bind_handle)?.resources[binding as usize]
+                    .texture_view()
+                    .0
+                    .size();
+                if index >= size.depth_or_array_layers {
+                    // double the layers up front rather than growing one layer at a time
+                    self.grow_texture(
+                        bind_handle,
+                        binding,
+                        Extent3d {
+                            depth_or_array_layers: size.depth_or_array_layers * 2,
+                            ..size
+                        },
+                    );
+                }
+
+                self.write_texture(
+                    &texture.data,
+                    texture.format,
+                    TextureRegion::new(texture.width, texture.height).with_layer(index),
+                    bind_handle,
+                    binding,
+                )?;
+            }
+            TextureArrayKind::Bindless { capacity } => {
+                if index >= capacity {
+                    return Err(anyhow!(
+                        "Texture array is full, it was created with {} slots",
+                        capacity
+                    ));
+                }
+
+                let mip_level_count = array
+                    .mip_level_count
+                    .clamp(1, full_mip_level_count(texture.width, texture.height));
+                let mut usage = TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST;
+                if mip_level_count > 1 {
+                    usage |= TextureUsages::RENDER_ATTACHMENT;
+                }
+                let gpu_texture = self.device().create_texture(&TextureDescriptor {
+                    label: None,
+                    size,
+                    mip_level_count,
+                    sample_count: 1,
+                    dimension: TextureDimension::D2,
+                    format: array.format,
+                    usage,
+                    view_formats: &[],
+                });
+                self.queue.write_texture(
+                    gpu_texture.as_image_copy(),
+                    &texture.data,
+                    ImageDataLayout {
+                        offset: 0,
+                        bytes_per_row: Some(texture.format.bytes_per_row(texture.width)),
+                        rows_per_image: Some(texture.format.rows(texture.height)),
+                    },
+                    size,
+                );
+                self.mipmaps.generate(
+                    self.device.as_ref().unwrap(),
+                    &self.queue,
+                    &gpu_texture,
+                    0..1,
+                );
+                let view = gpu_texture.create_view(&TextureViewDescriptor::default());
+
+                let device = self.device.take();
+                let bind = self.get_bind_mut(bind_handle).unwrap();
+                bind.replace_array_element(
+                    gpu_texture,
+                    view,
+                    binding,
+                    index,
+                    device.as_ref().unwrap(),
+                );
+                self.device = device;
+            }
+        }
+
+        // the gpu holds the only copy we need
+        texture.data = Vec::new();
+        let texture_handle = TextureHandle(self.textures.insert(texture));
+        self.texture_arrays
+            .get_mut(array_handle.0)
+            .unwrap()
+            .layers
+            .insert(texture_handle, index);
+        Ok(texture_handle)
+    }
+
+    /// Returns where a texture lives inside of a texture array.
+    pub fn get_texture_layer(
         &self,
-        mesh_handle: MeshHandle,
-    ) -> Result<&(
-        Mesh<Box<dyn Geometry>, Box<dyn Material>>,
-        Buffer,
-        Option<Buffer>,
-    )> {
+        texture_handle: TextureHandle,
+        array_handle: TextureArrayHandle,
+    ) -> Result<TextureLayer> {
+        let array = self
+            .texture_arrays
+            .get(array_handle.0)
+            .ok_or(anyhow!("Texture array not found."))?;
+        let index = *array
+            .layers
+            .get(&texture_handle)
+            .ok_or(anyhow!("Texture isn't part of the texture array."))?;
+        let uv_scale = match array.kind {
+            TextureArrayKind::Layers => {
+                let texture = self
+                    .textures
+                    .get(texture_handle.0)
+                    .ok_or(anyhow!("Texture not found."))?;
+                [
+                    texture.width as f32 / array.width as f32,
+                    texture.height as f32 / array.height as f32,
+                ]
+            }
+            TextureArrayKind::Bindless { .. } => [1.0, 1.0],
+        };
+        Ok(TextureLayer {
+            index,
+            uv_scale,
+        })
+    }
+
+    /// Uploads the six faces of a cubemap into a [TextureViewDimension::Cube] (or CubeArray) texture binding, regenerating its mips.
+    /// The texture grows when the faces are bigger than it is.
+    pub fn write_cubemap(
+        &mut self,
+        cubemap: &Cubemap,
+        handle: BindHandle,
+        binding: u32,
+    ) -> Result<()> {
+        let entry = self
+            .get_bind(handle)?
+            .bind_entries
+            .get(binding as usize)
+            .ok_or(anyhow!("No bind entry at binding {}", binding))?;
+        if !matches!(
+            entry.ty,
+            BindEntryType::Texture {
+                view_dimension: TextureViewDimension::Cube | TextureViewDimension::CubeArray,
+                ..
+            }
+        ) {
+            return Err(anyhow!("Binding {} isn't a cube texture", binding));
+        }
+
+        let size = cubemap.face_size();
+        for (layer, face) in cubemap.faces.iter().enumerate() {
+            self.write_texture_region(
+                &face.data,
+                face.format,
+                TextureRegion::new(size, size).with_layer(layer as u32),
+                handle,
+                binding,
+            )?;
+        }
+        self.generate_texture_mipmaps(handle, binding, 0..6);
+        Ok(())
+    }
+
+    /// Regenerates the mip chain of a texture binding from its first mip level, for when its contents were written directly.
+    /// Textures uploaded through atlases or texture arrays get their mips generated automatically.
+    pub fn generate_mipmaps(&mut self, handle: BindHandle, binding: u32) -> Result<()> {
+        let bind = self.get_bind(handle)?;
+        let (texture, _) = bind
+            .resources
+            .get(binding as usize)
+            .ok_or(anyhow!("No resource at binding {}", binding))?
+            .texture_view();
+        let layers = 0..texture.depth_or_array_layers();
+        self.generate_texture_mipmaps(handle, binding, layers);
+        Ok(())
+    }
+
+    fn generate_texture_mipmaps(&mut self, handle: BindHandle, binding: u32, layers: Range<u32>) {
+        let bind = self.binds.get(handle.0).unwrap();
+        let (texture, _) = bind.resources[binding as usize].texture_view();
+        self.mipmaps
+            .generate(self.device.as_ref().unwrap(), &self.queue, texture, layers);
+    }
+
+    /// Replaces a texture with a bigger one, copying every mip level of the existing contents over on the gpu.
+    fn grow_texture(&mut self, handle: BindHandle, binding: u32, size: Extent3d) {
+        let bind = self.get_bind(handle).unwrap();
+        let (texture, _) = bind.resources[binding as usize].texture_view();
+        let view_dimension = match bind.bind_entries[binding as usize].ty {
+            BindEntryType::Texture { view_dimension, .. }
+            | BindEntryType::StorageTexture { view_dimension, .. } => view_dimension,
+            _ => unreachable!(),
+        };
+        let new_texture = self.device().create_texture(&TextureDescriptor {
+            label: None,
+            size,
+            mip_level_count: texture.mip_level_count(),
+            sample_count: texture.sample_count(),
+            dimension: texture.dimension(),
+            format: texture.format(),
+            // the new texture may need to grow again later on
+            usage: texture.usage() | TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+
+        // textures created without COPY_SRC can't be copied from, they start out empty instead
+        if texture.usage().contains(TextureUsages::COPY_SRC) {
+            let mut encoder = self
+                .device()
+                .create_command_encoder(&CommandEncoderDescriptor::default());
+            for mip_level in 0..texture.mip_level_count() {
+                encoder.copy_texture_to_texture(
+                    ImageCopyTexture {
+                        mip_level,
+                        ..texture.as_image_copy()
+                    },
+                    ImageCopyTexture {
+                        mip_level,
+                        ..new_texture.as_image_copy()
+                    },
+                    texture
+                        .size()
+                        .mip_level_size(mip_level, texture.dimension()),
+                );
+            }
+            self.queue.submit([encoder.finish()]);
+        }
+
+        let view = new_texture.create_view(&TextureViewDescriptor {
+            dimension: Some(view_dimension),
+            ..Default::default()
+        });
+        self.replace_resource(
+            BindEntryResource::Texture(new_texture, view),
+            handle,
+            binding,
+        );
+    }
+
+    pub fn get_mesh(&self, mesh_handle: MeshHandle) -> Result<&GpuMesh> {
         self.meshes
             .get(mesh_handle.0)
             .ok_or(anyhow!("Mesh not found for handle id {:?}", mesh_handle.0))
@@ -294,141 +821,162 @@ impl<'a> Render<'a> {
         &mut self,
         mesh: Mesh<G, M>,
     ) -> MeshHandle {
-        let buffer = self
-            .device
-            .as_ref()
-            .unwrap()
-            .create_buffer_init(&BufferInitDescriptor {
-                label: None,
-                contents: mesh.geometry.contents(),
-                // contents: mesh.geometry.contents().as_ref(),
-                usage: BufferUsages::VERTEX,
-            });
-        let index_buffer = if let Some(indices) = mesh.geometry.indices() {
-            let index_buffer =
-                self.device
-                    .as_ref()
-                    .unwrap()
-                    .create_buffer_init(&BufferInitDescriptor {
-                        label: None,
-                        contents: indices,
-                        usage: BufferUsages::INDEX,
-                    });
-            Some(index_buffer)
-        } else {
-            None
+        let device = self.device.as_ref().unwrap();
+        let contents = mesh.geometry.contents();
+        let buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: None,
+            contents,
+            // COPY_DST lets us update the mesh in place, see [Render::update_mesh]
+            usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
+        });
+        let vertex_range = 0..contents.len() as u64;
+        let index_buffer = mesh.geometry.indices().map(|indices| {
+            let format = mesh.geometry.index_format();
+            IndexBuffer {
+                buffer: device.create_buffer_init(&BufferInitDescriptor {
+                    label: None,
+                    contents: indices,
+                    usage: BufferUsages::INDEX | BufferUsages::COPY_DST,
+                }),
+                format,
+                count: indices.len() as u32 / index_format_size(format),
+                offset: 0,
+            }
+        });
+        MeshHandle(self.meshes.insert(GpuMesh {
+            mesh: mesh.boxed(),
+            vertex_buffer: buffer,
+            vertex_range,
+            index_buffer,
+            streaming: None,
+        }))
+    }
+
+    /// Adds a mesh whose geometry is expected to change every frame, e.g. debug lines or procedurally generated geometry.
+    ///
+    /// A streaming mesh is backed by a ring buffer with room for [STREAMING_MESH_SEGMENTS] copies of its geometry.
+    /// Every [Render::update_mesh] writes into the next segment so that we never overwrite geometry the gpu may still be reading.
+    /// The segments grow whenever the geometry doesn't fit.
+    pub fn add_streaming_mesh<G: Geometry + 'static, M: Material + 'static>(
+        &mut self,
+        mesh: Mesh<G, M>,
+    ) -> MeshHandle {
+        let device = self.device.as_ref().unwrap();
+        let mut gpu_mesh = GpuMesh {
+            mesh: mesh.boxed(),
+            vertex_buffer: create_mesh_buffer(device, 0, BufferUsages::VERTEX),
+            vertex_range: 0..0,
+            index_buffer: None,
+            streaming: Some(StreamingSegments {
+                current: 0,
+                vertex_capacity: 0,
+                index_capacity: 0,
+            }),
         };
-        MeshHandle(self.meshes.insert((mesh.boxed(), buffer, index_buffer)))
+        gpu_mesh.stream_geometry(device, &self.queue);
+        MeshHandle(self.meshes.insert(gpu_mesh))
     }
 
-    fn add_instance<T: InstanceData + 'static>(&mut self, mesh_handle: MeshHandle, instance: T) {
-        if let std::collections::hash_map::Entry::Vacant(e) = self.instances.entry(mesh_handle) {
-            // create hashmap entry
-            let new_buffer = self
-                .device
-                .as_ref()
-                .unwrap()
-                .create_buffer(&BufferDescriptor {
-                    label: Some("Instance buffer"),
-                    size: std::mem::size_of::<T>() as u64 * 10,
-                    usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
-                    mapped_at_creation: false,
-                });
-            let new_data = instance.data();
-            self.queue.write_buffer(&new_buffer, 0, new_data);
-            e.insert((vec![Box::new(instance)], new_buffer));
+    /// Replaces the geometry of an existing mesh.
+    ///
+    /// The new geometry is written into the mesh's existing buffers when it fits, otherwise the buffers are grown.
+    /// Streaming meshes (see [Render::add_streaming_mesh]) write into the next segment of their ring buffer instead.
+    pub fn update_mesh<G: Geometry + 'static>(
+        &mut self,
+        mesh_handle: MeshHandle,
+        geometry: G,
+    ) -> Result<()> {
+        let device = self.device.as_ref().unwrap();
+        let gpu_mesh = self
+            .meshes
+            .get_mut(mesh_handle.0)
+            .ok_or(anyhow!("Mesh not found for handle id {:?}", mesh_handle.0))?;
+        gpu_mesh.mesh.geometry = Box::new(geometry);
+
+        if gpu_mesh.is_streaming() {
+            gpu_mesh.stream_geometry(device, &self.queue);
         } else {
-            let (instances, buffer) = self.instances.get_mut(&mesh_handle).unwrap();
-            if buffer.size() < std::mem::size_of::<T>() as u64 {
-                // create a bigger buffer
-                let new_buffer = self
-                    .device
-                    .as_ref()
-                    .unwrap()
-                    .create_buffer(&BufferDescriptor {
-                        label: Some("Instance buffer"),
-                        size: buffer.size() + std::mem::size_of::<T>() as u64 * 10,
-                        usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
-                        mapped_at_creation: false,
-                    });
-                instances.push(Box::new(instance));
-                let new_data = instances.iter().fold(Vec::new(), |mut acc, instance| {
-                    acc.extend_from_slice(instance.data());
-                    acc
-                });
-                self.queue.write_buffer(&new_buffer, 0, new_data.as_slice());
-                self.instances.entry(mesh_handle).and_modify(|(_, buffer)| {
-                    let _ = std::mem::replace(buffer, new_buffer);
-                });
-
-                return;
-            }
-            let offset = instances.len() * std::mem::size_of::<T>();
-            // write this instance into the buffer, no need to resize
-            self.queue
-                .write_buffer(buffer, offset as u64, instance.data());
-            instances.push(Box::new(instance));
+            gpu_mesh.write_geometry(device, &self.queue);
         }
+        Ok(())
     }
 
-    // a render object encapsulates all the information we need, including instance data
-    // one problem: we usually write the instance data in a buffer immediately. now we have instance data that can change (needs to be determined dynamically)
-    // this was always going to be the case. note that eventually we were going to make objects non-persistent in renderer storage, e.g. the instance data would be getting written to a buffer each frame (or so) anyway.
-    pub fn add_render_object<R: RenderObject + 'static>(&mut self, render_object: R) {
+    /// Queues raw instance data to be drawn with `mesh_handle` using `pipeline_handle` during the next [Render::draw].
+    pub fn add_instance<T: InstanceData>(
+        &mut self,
+        mesh_handle: MeshHandle,
+        pipeline_handle: PipelineHandle,
+        instance: T,
+    ) {
+        let key = MeshAndPipelineHandleComposite(mesh_handle, pipeline_handle);
+        self.instances.entry(key).or_default().push(&instance);
+    }
+
+    /// Queues a render object to be drawn during the next [Render::draw].
+    /// The render object's instance data is evaluated immediately and stored alongside any raw instances for the same mesh and pipeline.
+    ///
+    /// When submitting many objects of the same type prefer [Render::batch] which avoids a map lookup per object.
+    pub fn add_render_object<R: RenderObject>(&mut self, render_object: R) {
         let instance = render_object.instance(self);
-        let mesh_handle = render_object.mesh_handle();
-        let pipeline_handle = render_object.pipeline_handle();
+        self.add_instance(
+            render_object.mesh_handle(),
+            render_object.pipeline_handle(),
+            instance,
+        );
+    }
+
+    /// Starts a typed batch of render objects which are all drawn with `mesh_handle` using `pipeline_handle`.
+    /// Instances pushed into the batch are written directly into the mesh + pipeline's instance data, without any per-object allocation.
+    ///
+    /// ```ignore
+    /// let mut batch = render.batch::<ShapeRenderObject>(pipeline_handle, mesh_handle);
+    /// for quad in quads {
+    ///     batch.push(&quad);
+    /// }
+    /// ```
+    pub fn batch<R: RenderObject>(
+        &mut self,
+        pipeline_handle: PipelineHandle,
+        mesh_handle: MeshHandle,
+    ) -> Batch<'_, 'a, R> {
         let key = MeshAndPipelineHandleComposite(mesh_handle, pipeline_handle);
-        // self.add_instance(render_object.mesh_handle(), instance);
+        // take the batch out of the map so that render objects can borrow the renderer while we write into it
+        let instances = self.instances.remove(&key).unwrap_or_default();
+        Batch {
+            render: self,
+            key,
+            instances,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Writes every queued batch into its instance buffer, growing the buffer when it can't fit this frame's instances.
+    fn write_instance_buffers(&mut self) {
+        let device = self.device.as_ref().unwrap();
+        for batch in self.instances.values_mut() {
+            if batch.count == 0 {
+                continue;
+            }
+
+            let data = batch.data.as_slice();
 
-        if let std::collections::hash_map::Entry::Vacant(e) = self.render_objects.entry(key) {
-            // create hashmap entry
-            let new_buffer = self
-                .device
+            let too_small = batch
+                .buffer
                 .as_ref()
-                .unwrap()
-                .create_buffer(&BufferDescriptor {
+                .is_none_or(|buffer| buffer.size() < data.len() as u64);
+
+            if too_small {
+                // reserve some extra space so that a slowly growing batch doesn't reallocate every frame
+                batch.buffer = Some(device.create_buffer(&BufferDescriptor {
                     label: Some("Instance buffer"),
-                    size: std::mem::size_of::<R::InstanceType>() as u64 * 10,
+                    size: (data.len() as u64).next_power_of_two(),
                     usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
                     mapped_at_creation: false,
-                });
-            let new_data = instance.data();
-            self.queue.write_buffer(&new_buffer, 0, new_data);
-            e.insert((vec![Box::new(render_object.boxed())], new_buffer));
-        } else {
-            let (instances, buffer) = self.render_objects.get_mut(&key).unwrap();
-            if buffer.size() < (std::mem::size_of::<R::InstanceType>() * instances.len() + 1) as u64
-            {
-                // create a bigger buffer
-                let new_buffer = self
-                    .device
-                    .as_ref()
-                    .unwrap()
-                    .create_buffer(&BufferDescriptor {
-                        label: Some("Instance buffer"),
-                        // size: buffer.size() + std::mem::size_of::<R::InstanceType>() as u64, // we could reserve more space than necessary here if it improves performance (at the cost of some wasted memory)
-                        size: ((instances.len() + 1) * std::mem::size_of::<R::InstanceType>())
-                            as u64,
-                        usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
-                        mapped_at_creation: false,
-                    });
-                instances.push(Box::new(render_object.boxed()));
-                let (instances, _) = self.render_objects.get(&key).unwrap();
-                let new_data = instances.iter().fold(Vec::new(), |mut acc, instance| {
-                    acc.extend_from_slice(instance.instance(self).data());
-                    acc
-                });
-                self.queue.write_buffer(&new_buffer, 0, new_data.as_slice());
-                let (_, buffer) = self.render_objects.get_mut(&key).unwrap();
-                let _ = std::mem::replace(buffer, new_buffer);
-            } else {
-                let offset = instances.len() * std::mem::size_of::<R::InstanceType>();
-                // write this instance into the buffer, no need to resize
-                self.queue
-                    .write_buffer(buffer, offset as u64, instance.data());
-                instances.push(Box::new(render_object.boxed()));
+                }));
             }
+
+            self.queue
+                .write_buffer(batch.buffer.as_ref().unwrap(), 0, data);
         }
     }
 
@@ -445,11 +993,133 @@ impl<'a> Render<'a> {
         handle: BindHandle,
         binding: u32,
         format: crate::texture::TextureFormat,
-    ) -> AtlasHandle {
+    ) -> Result<AtlasHandle> {
+        let atlas = Atlas::new(format);
+        // a D2Array binding can hold a page per layer, anything else is limited to a single page
+        let max_pages = self
+            .get_bind(handle)
+            .ok()
+            .and_then(|bind| bind.bind_entries.get(binding as usize))
+            .map_or(1, |entry| match entry.ty {
+                BindEntryType::Texture {
+                    view_dimension: TextureViewDimension::D2Array,
+                    ..
+                } => self.device().limits().max_texture_array_layers,
+                _ => 1,
+            });
+        self.add_atlas(handle, binding, atlas.with_max_pages(max_pages))
+    }
+
+    /// Like [Render::register_atlas] but with a configured [Atlas], e.g. for custom padding or size limits.
+    /// The atlas' maximum size is limited to what the device supports.
+    ///
+    /// Fails if the binding isn't a texture of the atlas' format.
+    pub fn add_atlas(
+        &mut self,
+        handle: BindHandle,
+        binding: u32,
+        atlas: Atlas,
+    ) -> Result<AtlasHandle> {
+        // the atlas is uploaded straight into the binding's texture, better to find out now than on the next draw
+        let entry = self
+            .get_bind(handle)?
+            .bind_entries
+            .get(binding as usize)
+            .ok_or(anyhow!("Binding {} not found", binding))?;
+        let BindEntryType::Texture { format, .. } = entry.ty else {
+            return Err(anyhow!("Binding {} isn't a texture", binding));
+        };
+        if !atlas.format.is_compatible_with(format) {
+            return Err(anyhow!(
+                "Atlas format {:?} doesn't match the binding's format {:?}",
+                atlas.format,
+                format
+            ));
+        }
+
+        let max_size = atlas
+            .max_size()
+            .min(self.device().limits().max_texture_dimension_2d);
+        let atlas = atlas.with_max_size(max_size);
         let idx = self
             .atlases
-            .insert((handle, binding, Atlas::new(format), HashMap::new()));
-        AtlasHandle(idx)
+            .insert((handle, binding, atlas, HashMap::new()));
+        Ok(AtlasHandle(idx))
+    }
+
+    /// Uploads every page of an atlas, recreating the atlas texture when the atlas has outgrown it.
+    fn upload_atlas(
+        &mut self,
+        handle: BindHandle,
+        binding: u32,
+        atlas: &Atlas,
+        rect_to_tex: &HashMap<RectHandle, TextureHandle>,
+    ) -> Result<()> {
+        let bind = self.get_bind(handle)?;
+        let (texture, _) = bind.resources[binding as usize].texture_view();
+        let (view_dimension, mip_level_count) = match bind.bind_entries[binding as usize].ty {
+            BindEntryType::Texture {
+                view_dimension,
+                mip_level_count,
+                ..
+            } => (view_dimension, mip_level_count),
+            _ => unreachable!(),
+        };
+        // levels past what the padding allows would blend neighbouring rects together
+        let mip_level_count = mip_level_count
+            .min(full_mip_level_count(atlas.width, atlas.height))
+            .min(atlas.max_mip_levels())
+            .max(1);
+        let layers = texture.depth_or_array_layers();
+
+        if texture.width() != atlas.width
+            || texture.height() != atlas.height
+            || texture.mip_level_count() != mip_level_count
+            || layers < atlas.pages()
+        {
+            // every page gets rewritten below so there's nothing to copy over
+            let new_texture = self.device().create_texture(&TextureDescriptor {
+                label: None,
+                size: Extent3d {
+                    width: atlas.width,
+                    height: atlas.height,
+                    depth_or_array_layers: layers.max(atlas.pages()),
+                },
+                mip_level_count,
+                sample_count: texture.sample_count(),
+                dimension: texture.dimension(),
+                format: texture.format(),
+                usage: if mip_level_count > 1 {
+                    texture.usage() | TextureUsages::RENDER_ATTACHMENT
+                } else {
+                    texture.usage()
+                },
+                view_formats: &[],
+            });
+            let view = new_texture.create_view(&TextureViewDescriptor {
+                dimension: Some(view_dimension),
+                ..Default::default()
+            });
+            self.replace_resource(
+                BindEntryResource::Texture(new_texture, view),
+                handle,
+                binding,
+            );
+        }
+
+        for page in 0..atlas.pages() {
+            let atlas_texture = Texture::from_atlas(atlas, page, rect_to_tex, &self.textures);
+            self.write_texture_region(
+                &atlas_texture.data,
+                atlas.format,
+                TextureRegion::new(atlas.width, atlas.height).with_layer(page),
+                handle,
+                binding,
+            )?;
+        }
+
+        self.generate_texture_mipmaps(handle, binding, 0..atlas.pages());
+        Ok(())
     }
 
     pub fn build_bind(&mut self, bind_entries: &mut [BindEntry<'a>]) -> BindHandle {
@@ -479,12 +1149,7 @@ impl<'a> Render<'a> {
             // TODO: this file creates a surface configuration in two different places. best to replace with a singular definition returned by a function.
             &SurfaceConfiguration {
                 usage: TextureUsages::RENDER_ATTACHMENT,
-                format: *self
-                    .surface
-                    .get_capabilities(&self.adapter)
-                    .formats
-                    .first()
-                    .unwrap(),
+                format: self.surface_format,
                 width: size.width,
                 height: size.height,
                 present_mode: wgpu::PresentMode::Fifo,
@@ -512,53 +1177,38 @@ impl<'a> Render<'a> {
         });
     }
 
-    pub fn draw(&mut self) {
+    pub fn draw(&mut self) -> Result<()> {
         let mut atlases = std::mem::take(&mut self.atlases);
-        atlases
+        let uploaded = atlases
             .iter_mut()
-            .filter_map(|(handle, (atlas_bind, binding, atlas, rect_to_tex))| {
-                if atlas.changed {
-                    Some((handle, atlas_bind, binding, atlas, rect_to_tex))
-                } else {
-                    None
+            .try_for_each(|(_, (atlas_bind, binding, atlas, rect_to_tex))| {
+                if !atlas.changed {
+                    return Ok(());
                 }
-            })
-            .for_each(|(handle, atlas_bind, binding, atlas, rect_to_tex)| {
-                atlas.pack();
                 atlas.changed = false;
-
-                // update the atlas texture
-                let atlas_texture = Texture::from_atlas(atlas, rect_to_tex, &self.textures);
-                // let _ = image::save_buffer(
-                //     format!("sdf_atlas_{:?}.png", handle.into_raw_parts()),
-                //     &atlas_texture.data,
-                //     atlas_texture.width,
-                //     atlas_texture.height,
-                //     image::ColorType::L8,
-                // );
-                self.write_texture(
-                    &atlas_texture.data,
-                    ImageDataLayout {
-                        offset: 0,
-                        bytes_per_row: Some(4 * atlas_texture.width),
-                        rows_per_image: None,
-                    },
-                    Extent3d {
-                        width: atlas_texture.width,
-                        height: atlas_texture.height,
-                        depth_or_array_layers: 1,
-                    },
-                    *atlas_bind,
-                    *binding,
-                );
+                self.upload_atlas(*atlas_bind, *binding, atlas, rect_to_tex)
             });
+        // put the atlases back before bailing out so that they're still around for the next frame
+        self.atlases = atlases;
+        uploaded?;
 
-        self.atlases = std::mem::take(&mut atlases);
+        self.write_instance_buffers();
 
         let frame = self.surface.get_current_texture().unwrap();
 
         let view = frame.texture.create_view(&TextureViewDescriptor::default());
 
+        // with post-processing the scene goes into an hdr target first
+        if let Some(post) = self.post.as_mut() {
+            post.prepare(
+                self.device.as_ref().unwrap(),
+                &self.queue,
+                frame.texture.width(),
+                frame.texture.height(),
+            );
+        }
+        let scene_view = self.post.as_ref().map_or(&view, PostProcess::scene_view);
+
         let mut encoder = self
             .device
             .as_ref()
@@ -576,17 +1226,23 @@ impl<'a> Render<'a> {
         let mut draw_map: HashMap<PipelineHandle, HashMap<MeshHandle, (u32, &Buffer)>> =
             HashMap::new();
 
-        for (key, (render_objects, buffer)) in &self.render_objects {
+        for (key, batch) in &self.instances {
+            let Some(buffer) = batch.buffer.as_ref() else {
+                continue;
+            };
+            if batch.count == 0 {
+                continue;
+            }
             draw_map
                 .entry(key.1)
-                .or_insert(HashMap::new())
-                .insert(key.0, (render_objects.len() as u32, buffer));
+                .or_default()
+                .insert(key.0, (batch.count, buffer));
         }
 
         let mut rpass: wgpu::RenderPass<'_> = encoder.begin_render_pass(&RenderPassDescriptor {
             label: None,
             color_attachments: &[Some(RenderPassColorAttachment {
-                view: &view,
+                view: scene_view,
                 resolve_target: None,
                 ops: Operations {
                     load: wgpu::LoadOp::Clear(Color {
@@ -610,7 +1266,14 @@ impl<'a> Render<'a> {
             occlusion_query_set: None,
         });
 
-        for (pipeline_handle, meshes_and_render_objects) in draw_map.iter() {
+        let mut draw_order = draw_map.iter().collect::<Vec<_>>();
+        draw_order.sort_by_key(|(pipeline_handle, _)| {
+            self.pipelines
+                .get(pipeline_handle.0)
+                .map_or(0, |pipeline| pipeline.order)
+        });
+
+        for (pipeline_handle, meshes_and_render_objects) in draw_order {
             let pipeline = self.pipelines.get(pipeline_handle.0).unwrap();
             rpass.set_pipeline(&pipeline.pipeline);
             for (idx, handle) in pipeline.binds.iter().enumerate() {
@@ -621,32 +1284,110 @@ impl<'a> Render<'a> {
 
             for (mesh_handle, (num_instances, instance_buffer)) in meshes_and_render_objects {
                 // get the mesh
-                let (mesh, vertex_buffer, index_buffer) =
-                    self.get_mesh(*mesh_handle).expect("Mesh should exist");
-                rpass.set_vertex_buffer(0, vertex_buffer.slice(..));
+                let gpu_mesh = self.get_mesh(*mesh_handle).expect("Mesh should exist");
+                // empty buffer slices aren't allowed, and there'd be nothing to draw anyway
+                if gpu_mesh.vertex_range.is_empty() {
+                    continue;
+                }
+                rpass.set_vertex_buffer(
+                    0,
+                    gpu_mesh.vertex_buffer.slice(gpu_mesh.vertex_range.clone()),
+                );
                 rpass.set_vertex_buffer(1, instance_buffer.slice(..));
-                if let Some(index_buffer) = index_buffer {
-                    rpass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+                if let Some(index_buffer) = &gpu_mesh.index_buffer {
+                    if index_buffer.count == 0 {
+                        continue;
+                    }
+                    rpass.set_index_buffer(
+                        index_buffer.buffer.slice(index_buffer.range()),
+                        index_buffer.format,
+                    );
                     rpass.draw_indexed(
-                        0..(index_buffer.size() as u32 / std::mem::size_of::<u16>() as u32),
+                        0..index_buffer.count,
                         0,
                         // 0..(instance_buffer.size() as u32
                         //     / std::mem::size_of::<TextInstance>() as u32),
                         0..*num_instances,
                     )
                 } else {
-                    rpass.draw(0..mesh.geometry.length(), 0..*num_instances);
+                    rpass.draw(0..gpu_mesh.mesh.geometry.length(), 0..*num_instances);
                 }
             }
         }
 
         drop(rpass);
 
+        if let Some(post) = self.post.as_mut() {
+            post.run(
+                self.device.as_ref().unwrap(),
+                &self.queue,
+                &mut encoder,
+                &view,
+                self.surface_format,
+            );
+        }
+
         self.queue.submit([encoder.finish()]);
 
         frame.present();
 
-        self.render_objects.clear();
+        // keep the instance buffers around for the next frame
+        self.instances.values_mut().for_each(InstanceBatch::clear);
+        Ok(())
+    }
+}
+
+/// A typed batch of render objects sharing a mesh and pipeline, created with [Render::batch].
+/// The queued instances are handed back to the renderer when the batch is dropped.
+pub struct Batch<'r, 'a, R: RenderObject> {
+    render: &'r mut Render<'a>,
+    key: MeshAndPipelineHandleComposite,
+    instances: InstanceBatch,
+    _marker: PhantomData<R>,
+}
+
+impl<'r, 'a, R: RenderObject> Batch<'r, 'a, R> {
+    /// Evaluates the render object's instance data and appends it to the batch.
+    /// The render object has to use the batch's mesh and pipeline, this is only checked in debug builds.
+    pub fn push(&mut self, render_object: &R) {
+        debug_assert_eq!(render_object.mesh_handle(), self.key.0);
+        debug_assert_eq!(render_object.pipeline_handle(), self.key.1);
+        let instance = render_object.instance(self.render);
+        self.instances.push(&instance);
+    }
+
+    /// Appends already evaluated instance data to the batch.
+    pub fn push_instance(&mut self, instance: &R::InstanceType) {
+        self.instances.push(instance);
+    }
+
+    /// Reserves space for at least `additional` more instances.
+    pub fn reserve(&mut self, additional: usize) {
+        self.instances
+            .data
+            .reserve(additional * std::mem::size_of::<R::InstanceType>());
+    }
+
+    pub fn len(&self) -> u32 {
+        self.instances.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.instances.count == 0
+    }
+}
+
+impl<'r, 'a, R: RenderObject> Extend<R> for Batch<'r, 'a, R> {
+    fn extend<T: IntoIterator<Item = R>>(&mut self, iter: T) {
+        iter.into_iter()
+            .for_each(|render_object| self.push(&render_object));
+    }
+}
+
+impl<'r, 'a, R: RenderObject> Drop for Batch<'r, 'a, R> {
+    fn drop(&mut self) {
+        let instances = std::mem::take(&mut self.instances);
+        self.render.instances.insert(self.key, instances);
     }
 }
 
@@ -656,6 +1397,250 @@ pub struct AtlasHandle(pub Index);
 #[derive(Eq, Hash, PartialEq, Clone, Copy, Debug)]
 pub struct MeshHandle(pub Index);
 
+#[derive(Eq, Hash, PartialEq, Clone, Copy, Debug)]
+pub struct TextureArrayHandle(pub Index);
+
+/// Where a texture lives inside of a texture array, see [Render::get_texture_layer].
+#[derive(Clone, Copy, Debug)]
+pub struct TextureLayer {
+    /// The layer of a layered array or the slot of a bindless array.
+    pub index: u32,
+    /// The fraction of the layer covered by the texture. Multiply uvs by this before sampling.
+    pub uv_scale: [f32; 2],
+}
+
+struct TextureArray {
+    bind: BindHandle,
+    binding: u32,
+    format: TextureFormat,
+    width: u32,
+    height: u32,
+    mip_level_count: u32,
+    // looked up for every instance, so kept by handle rather than searched for
+    layers: HashMap<TextureHandle, u32>,
+    kind: TextureArrayKind,
+}
+
+enum TextureArrayKind {
+    /// A single texture with one layer per texture.
+    Layers,
+    /// A binding array with one texture per slot.
+    Bindless { capacity: u32 },
+}
+
+/// A region of a texture to write into, see [Render::write_texture].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TextureRegion {
+    pub x: u32,
+    pub y: u32,
+    pub layer: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl TextureRegion {
+    /// A `width` x `height` region at the top left corner of the first layer.
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            ..Default::default()
+        }
+    }
+
+    pub fn at(mut self, x: u32, y: u32) -> Self {
+        self.x = x;
+        self.y = y;
+        self
+    }
+
+    pub fn with_layer(mut self, layer: u32) -> Self {
+        self.layer = layer;
+        self
+    }
+}
+
+/// Pads rows of `row_len` bytes to wgpu's row alignment, returns the (possibly copied) data along with the padded row length.
+fn pad_rows(data: &[u8], row_len: usize) -> (Cow<'_, [u8]>, u32) {
+    let alignment = COPY_BYTES_PER_ROW_ALIGNMENT as usize;
+    let padded_len = row_len.div_ceil(alignment) * alignment;
+    if padded_len == row_len {
+        return (Cow::Borrowed(data), row_len as u32);
+    }
+
+    let mut padded = Vec::with_capacity(padded_len * (data.len() / row_len));
+    for row in data.chunks_exact(row_len) {
+        padded.extend_from_slice(row);
+        padded.resize(padded.len() + padded_len - row_len, 0);
+    }
+    (Cow::Owned(padded), padded_len as u32)
+}
+
+/// How many copies of a streaming mesh's geometry are kept on the gpu, see [Render::add_streaming_mesh].
+pub const STREAMING_MESH_SEGMENTS: u64 = 3;
+
+/// A mesh along with the gpu buffers holding its geometry.
+pub struct GpuMesh {
+    pub mesh: Mesh<Box<dyn Geometry>, Box<dyn Material>>,
+    pub vertex_buffer: Buffer,
+    /// The byte range of `vertex_buffer` holding the mesh's current vertices.
+    pub vertex_range: Range<u64>,
+    pub index_buffer: Option<IndexBuffer>,
+    streaming: Option<StreamingSegments>,
+}
+
+impl GpuMesh {
+    pub fn is_streaming(&self) -> bool {
+        self.streaming.is_some()
+    }
+
+    /// Writes a static mesh's geometry into its buffers, replacing any buffer that is too small.
+    fn write_geometry(&mut self, device: &Device, queue: &Queue) {
+        let geometry = &self.mesh.geometry;
+
+        let contents = geometry.contents();
+        let size = aligned_size(contents.len() as u64);
+        if self.vertex_buffer.size() < size {
+            self.vertex_buffer =
+                create_mesh_buffer(device, size.next_power_of_two(), BufferUsages::VERTEX);
+        }
+        write_mesh_buffer(queue, &self.vertex_buffer, 0, contents);
+        self.vertex_range = 0..contents.len() as u64;
+
+        self.index_buffer = match geometry.indices() {
+            Some(indices) => {
+                let size = aligned_size(indices.len() as u64);
+                let buffer = match self.index_buffer.take() {
+                    Some(index_buffer) if index_buffer.buffer.size() >= size => index_buffer.buffer,
+                    _ => create_mesh_buffer(device, size.next_power_of_two(), BufferUsages::INDEX),
+                };
+                write_mesh_buffer(queue, &buffer, 0, indices);
+                let format = geometry.index_format();
+                Some(IndexBuffer {
+                    buffer,
+                    format,
+                    count: indices.len() as u32 / index_format_size(format),
+                    offset: 0,
+                })
+            }
+            None => None,
+        };
+    }
+
+    /// Writes a streaming mesh's geometry into the next segment of its ring buffers.
+    fn stream_geometry(&mut self, device: &Device, queue: &Queue) {
+        let geometry = &self.mesh.geometry;
+        let streaming = self
+            .streaming
+            .as_mut()
+            .expect("Mesh should be a streaming mesh");
+
+        let contents = geometry.contents();
+        let indices = geometry.indices().unwrap_or_default();
+        let vertex_size = aligned_size(contents.len() as u64);
+        let index_size = aligned_size(indices.len() as u64);
+
+        streaming.current = (streaming.current + 1) % STREAMING_MESH_SEGMENTS;
+
+        // geometry that outgrows its segments starts over in a fresh, bigger buffer
+        if vertex_size > streaming.vertex_capacity {
+            streaming.vertex_capacity = vertex_size.next_power_of_two();
+            self.vertex_buffer = create_mesh_buffer(
+                device,
+                streaming.vertex_capacity * STREAMING_MESH_SEGMENTS,
+                BufferUsages::VERTEX,
+            );
+        }
+        let vertex_offset = streaming.current * streaming.vertex_capacity;
+        write_mesh_buffer(queue, &self.vertex_buffer, vertex_offset, contents);
+        self.vertex_range = vertex_offset..vertex_offset + contents.len() as u64;
+
+        self.index_buffer = match geometry.indices() {
+            Some(indices) => {
+                let buffer = match self.index_buffer.take() {
+                    Some(index_buffer) if index_size <= streaming.index_capacity => {
+                        index_buffer.buffer
+                    }
+                    _ => {
+                        streaming.index_capacity = index_size.next_power_of_two();
+                        create_mesh_buffer(
+                            device,
+                            streaming.index_capacity * STREAMING_MESH_SEGMENTS,
+                            BufferUsages::INDEX,
+                        )
+                    }
+                };
+                let offset = streaming.current * streaming.index_capacity;
+                write_mesh_buffer(queue, &buffer, offset, indices);
+                let format = geometry.index_format();
+                Some(IndexBuffer {
+                    buffer,
+                    format,
+                    count: indices.len() as u32 / index_format_size(format),
+                    offset,
+                })
+            }
+            None => None,
+        };
+    }
+}
+
+/// Ring buffer bookkeeping for a streaming mesh. Capacities are per segment, in bytes.
+struct StreamingSegments {
+    current: u64,
+    vertex_capacity: u64,
+    index_capacity: u64,
+}
+
+/// A mesh's index buffer along with the format and number of indices it holds.
+pub struct IndexBuffer {
+    pub buffer: Buffer,
+    pub format: IndexFormat,
+    pub count: u32,
+    /// The byte offset of the first index.
+    pub offset: u64,
+}
+
+impl IndexBuffer {
+    /// The byte range of `buffer` holding the mesh's current indices.
+    pub fn range(&self) -> Range<u64> {
+        self.offset..self.offset + (self.count * index_format_size(self.format)) as u64
+    }
+}
+
+fn create_mesh_buffer(device: &Device, size: u64, usage: BufferUsages) -> Buffer {
+    device.create_buffer(&BufferDescriptor {
+        label: None,
+        size,
+        usage: usage | BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+/// Writes `data` at `offset`, padding it to [wgpu::COPY_BUFFER_ALIGNMENT] as required by [Queue::write_buffer].
+fn write_mesh_buffer(queue: &Queue, buffer: &Buffer, offset: u64, data: &[u8]) {
+    if data.is_empty() {
+        return;
+    }
+    let padding = padding_for(data.len() as u64) as usize;
+    if padding == 0 {
+        queue.write_buffer(buffer, offset, data);
+    } else {
+        let mut padded = Vec::with_capacity(data.len() + padding);
+        padded.extend_from_slice(data);
+        padded.resize(data.len() + padding, 0);
+        queue.write_buffer(buffer, offset, &padded);
+    }
+}
+
+fn padding_for(size: u64) -> u64 {
+    (wgpu::COPY_BUFFER_ALIGNMENT - size % wgpu::COPY_BUFFER_ALIGNMENT) % wgpu::COPY_BUFFER_ALIGNMENT
+}
+
+fn aligned_size(size: u64) -> u64 {
+    size + padding_for(size)
+}
+
 #[derive(Eq, Hash, PartialEq, Clone, Copy, Debug)]
 pub struct TextureHandle(pub Index);
 
@@ -2,10 +2,10 @@ use nalgebra::Matrix4;
 
 use crate::{
     geometry::{BasicGeometry, Geometry},
-    instance::{BasicInstance, InstanceData},
+    instance::{BasicInstance, InstanceData, LayeredInstance},
     material::{BasicMaterial, Material},
     pipeline::PipelineHandle,
-    render::{AtlasHandle, MeshHandle, Render, TextureHandle},
+    render::{AtlasHandle, MeshHandle, Render, TextureArrayHandle, TextureHandle},
 };
 
 // A RenderObject defines how instance data is obtained from the renderer, mesh and material data for a particular pipeline.
@@ -18,6 +18,8 @@ use crate::{
 // this feels overly complex but i think it gives us the flexibility we need to define our own materials in code.
 //
 // what does a renderobject give us that a mesh doesn't? - mesh is a struct not a trait i guess
+//
+// render objects are never boxed: the renderer only keeps the bytes of their instance data, see [Render::batch].
 pub trait RenderObject: std::fmt::Debug {
     type InstanceType: InstanceData;
     type GeometryType: Geometry + 'static;
@@ -25,61 +27,6 @@ pub trait RenderObject: std::fmt::Debug {
     fn instance(&self, render: &Render) -> Self::InstanceType;
     fn pipeline_handle(&self) -> PipelineHandle;
     fn mesh_handle(&self) -> MeshHandle;
-    fn boxed(self) -> BoxedRenderObject<Self::GeometryType, Self::InstanceType, Self::MaterialType>
-    // holy shit it works
-    where
-        Self: Sized + 'static,
-    {
-        BoxedRenderObject(Box::new(self))
-    }
-}
-
-// problem: we want to access the original render object's methods but we cannot store it. what do we do?
-// we cannot store Box<dyn RenderObject<...>> because we end up having to create a version of RenderObject with trait object associated types (which is what we need this struct for!)
-// we cannot call boxed recursively
-// we could store function pointers (lifetime / borrow issues? - we don't have the underlying data to refer to)
-// we can honestly store some of the data. eg. pipelinehandle, meshhandle. instance() is the only real problem.
-// can we store it as any? and downcast it? or can it be generic instead? - if it's a generic the boxedrenderobject requires generics too. is that a problem?
-// generics work!
-#[derive(Debug)]
-pub struct BoxedRenderObject<G, I, M>(
-    Box<dyn RenderObject<GeometryType = G, InstanceType = I, MaterialType = M>>,
-);
-
-// pub struct BoxedRenderObject(
-//     Box<
-//         dyn RenderObject<
-//             GeometryType = Box<dyn Geometry>,
-//             InstanceType = Box<dyn InstanceData>,
-//             MaterialType = Box<dyn Material>,
-//         >,
-//     >,
-// );
-
-impl<G: Geometry + 'static, I: InstanceData + 'static, M: Material + 'static> RenderObject
-    for BoxedRenderObject<G, I, M>
-{
-    type InstanceType = Box<dyn InstanceData>;
-
-    type GeometryType = Box<dyn Geometry>;
-
-    type MaterialType = Box<dyn Material>;
-
-    fn instance(
-        &self,
-        render: &Render,
-        // mesh: Mesh<Self::GeometryType, Self::MaterialType>,
-    ) -> Self::InstanceType {
-        Box::new(self.0.as_ref().instance(render))
-    }
-
-    fn pipeline_handle(&self) -> PipelineHandle {
-        self.0.as_ref().pipeline_handle()
-    }
-
-    fn mesh_handle(&self) -> MeshHandle {
-        self.0.as_ref().mesh_handle()
-    }
 }
 
 #[derive(Debug)]
@@ -121,3 +68,52 @@ impl RenderObject for BasicRenderObject {
         self.mesh_handle
     }
 }
+
+/// Like [BasicRenderObject] but for textures stored in a texture array instead of an atlas.
+/// Objects whose texture isn't in the texture array are skipped with a warning.
+#[derive(Debug)]
+pub struct LayeredRenderObject {
+    pub pipeline_handle: PipelineHandle,
+    pub mesh_handle: MeshHandle,
+    pub transform: Matrix4<f32>,
+    pub texture_handle: TextureHandle,
+    pub texture_array_handle: TextureArrayHandle,
+}
+
+impl RenderObject for LayeredRenderObject {
+    type InstanceType = LayeredInstance;
+
+    type GeometryType = BasicGeometry;
+
+    type MaterialType = BasicMaterial;
+
+    fn instance(&self, render: &Render) -> Self::InstanceType {
+        let layer = match render.get_texture_layer(self.texture_handle, self.texture_array_handle) {
+            Ok(layer) => layer,
+            Err(err) => {
+                log::warn!("Skipping layered render object: {}", err);
+                // a zero transform collapses the mesh so nothing gets drawn
+                return LayeredInstance {
+                    transform: Matrix4::zeros(),
+                    uv_scale: [0.0, 0.0],
+                    layer: 0,
+                    padding: 0,
+                };
+            }
+        };
+        LayeredInstance {
+            transform: self.transform,
+            uv_scale: layer.uv_scale,
+            layer: layer.index,
+            padding: 0,
+        }
+    }
+
+    fn pipeline_handle(&self) -> PipelineHandle {
+        self.pipeline_handle
+    }
+
+    fn mesh_handle(&self) -> MeshHandle {
+        self.mesh_handle
+    }
+}
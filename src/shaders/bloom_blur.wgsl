@@ -0,0 +1,19 @@
+struct Params {
+    // in texels, (1, 0) for a horizontal pass and (0, 1) for a vertical one
+    direction: vec2<f32>,
+}
+
+@group(0) @binding(2)
+var<uniform> params: Params;
+
+@fragment
+fn fragment(in: FullscreenOutput) -> @location(0) vec4<f32> {
+    // a 9 tap gaussian in 5 samples, linear filtering takes care of the taps in between
+    let step = params.direction * input_texel_size();
+    var color = textureSampleLevel(input, input_sampler, in.uv, 0.0).rgb * 0.2270270270;
+    color += textureSampleLevel(input, input_sampler, in.uv + step * 1.3846153846, 0.0).rgb * 0.3162162162;
+    color += textureSampleLevel(input, input_sampler, in.uv - step * 1.3846153846, 0.0).rgb * 0.3162162162;
+    color += textureSampleLevel(input, input_sampler, in.uv + step * 3.2307692308, 0.0).rgb * 0.0702702703;
+    color += textureSampleLevel(input, input_sampler, in.uv - step * 3.2307692308, 0.0).rgb * 0.0702702703;
+    return vec4<f32>(color, 1.0);
+}
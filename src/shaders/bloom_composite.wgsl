@@ -0,0 +1,14 @@
+struct Params {
+    intensity: f32,
+}
+
+@group(0) @binding(2)
+var<uniform> params: Params;
+
+@fragment
+fn fragment(in: FullscreenOutput) -> @location(0) vec4<f32> {
+    let color = textureSampleLevel(input, input_sampler, in.uv, 0.0);
+    // aux holds the blurred highlights at half resolution
+    let bloom = textureSampleLevel(aux, input_sampler, in.uv, 0.0).rgb;
+    return vec4<f32>(color.rgb + bloom * params.intensity, color.a);
+}
@@ -0,0 +1,19 @@
+struct Params {
+    threshold: f32,
+    knee: f32,
+}
+
+@group(0) @binding(2)
+var<uniform> params: Params;
+
+@fragment
+fn fragment(in: FullscreenOutput) -> @location(0) vec4<f32> {
+    // the target is half the size of the input, so a linear sample averages 2x2 input pixels
+    let color = textureSampleLevel(input, input_sampler, in.uv, 0.0).rgb;
+    let brightness = max(color.r, max(color.g, color.b));
+    // soft knee so that bloom fades in rather than popping in at the threshold
+    var soft = clamp(brightness - params.threshold + params.knee, 0.0, 2.0 * params.knee);
+    soft = soft * soft / (4.0 * params.knee + 0.00001);
+    let contribution = max(soft, brightness - params.threshold) / max(brightness, 0.00001);
+    return vec4<f32>(color * contribution, 1.0);
+}
@@ -0,0 +1,37 @@
+struct Params {
+    intensity: f32,
+}
+
+@group(0) @binding(2)
+var<uniform> params: Params;
+
+fn to_srgb(color: vec3<f32>) -> vec3<f32> {
+    let c = clamp(color, vec3<f32>(0.0), vec3<f32>(1.0));
+    return select(1.055 * pow(c, vec3<f32>(1.0 / 2.4)) - 0.055, c * 12.92, c <= vec3<f32>(0.0031308));
+}
+
+fn to_linear(c: vec3<f32>) -> vec3<f32> {
+    return select(pow((c + 0.055) / 1.055, vec3<f32>(2.4)), c / 12.92, c <= vec3<f32>(0.04045));
+}
+
+// aux is a strip of `size` slices of `size` x `size` pixels, red goes along x, green along y and blue picks the slice
+fn sample_lut(color: vec3<f32>) -> vec3<f32> {
+    let size = f32(textureDimensions(aux).y);
+    let blue = color.b * (size - 1.0);
+    let slice = floor(blue);
+    let next_slice = min(slice + 1.0, size - 1.0);
+    // half a texel in from the slice's edges so that neighbouring slices don't bleed in
+    let x = (color.r * (size - 1.0) + 0.5) / (size * size);
+    let y = (color.g * (size - 1.0) + 0.5) / size;
+    let a = textureSampleLevel(aux, input_sampler, vec2<f32>(x + slice / size, y), 0.0).rgb;
+    let b = textureSampleLevel(aux, input_sampler, vec2<f32>(x + next_slice / size, y), 0.0).rgb;
+    return mix(a, b, blue - slice);
+}
+
+@fragment
+fn fragment(in: FullscreenOutput) -> @location(0) vec4<f32> {
+    let color = textureSampleLevel(input, input_sampler, in.uv, 0.0);
+    // luts are authored against srgb encoded colours
+    let graded = to_linear(sample_lut(to_srgb(color.rgb)));
+    return vec4<f32>(mix(color.rgb, graded, params.intensity), color.a);
+}
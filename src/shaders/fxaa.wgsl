@@ -0,0 +1,56 @@
+struct Params {
+    edge_threshold: f32,
+    edge_threshold_min: f32,
+    span_max: f32,
+}
+
+@group(0) @binding(2)
+var<uniform> params: Params;
+
+const REDUCE_MUL: f32 = 0.125;
+const REDUCE_MIN: f32 = 0.0078125;
+
+fn luma(color: vec3<f32>) -> f32 {
+    // fxaa wants perceptual luma, sqrt is close enough to the srgb curve
+    return sqrt(dot(color, vec3<f32>(0.299, 0.587, 0.114)));
+}
+
+fn sample(uv: vec2<f32>) -> vec3<f32> {
+    return textureSampleLevel(input, input_sampler, uv, 0.0).rgb;
+}
+
+@fragment
+fn fragment(in: FullscreenOutput) -> @location(0) vec4<f32> {
+    let texel = input_texel_size();
+    let center = textureSampleLevel(input, input_sampler, in.uv, 0.0);
+    let luma_nw = luma(sample(in.uv + vec2<f32>(-1.0, -1.0) * texel));
+    let luma_ne = luma(sample(in.uv + vec2<f32>(1.0, -1.0) * texel));
+    let luma_sw = luma(sample(in.uv + vec2<f32>(-1.0, 1.0) * texel));
+    let luma_se = luma(sample(in.uv + vec2<f32>(1.0, 1.0) * texel));
+    let luma_m = luma(center.rgb);
+
+    let luma_min = min(luma_m, min(min(luma_nw, luma_ne), min(luma_sw, luma_se)));
+    let luma_max = max(luma_m, max(max(luma_nw, luma_ne), max(luma_sw, luma_se)));
+    // not enough contrast to be an edge worth smoothing
+    if luma_max - luma_min < max(params.edge_threshold_min, luma_max * params.edge_threshold) {
+        return center;
+    }
+
+    // blur along the edge, i.e. perpendicular to the luma gradient
+    var direction = vec2<f32>(
+        -((luma_nw + luma_ne) - (luma_sw + luma_se)),
+        (luma_nw + luma_sw) - (luma_ne + luma_se),
+    );
+    let reduce = max((luma_nw + luma_ne + luma_sw + luma_se) * 0.25 * REDUCE_MUL, REDUCE_MIN);
+    let inverse_min = 1.0 / (min(abs(direction.x), abs(direction.y)) + reduce);
+    direction = clamp(direction * inverse_min, vec2<f32>(-params.span_max), vec2<f32>(params.span_max)) * texel;
+
+    let a = 0.5 * (sample(in.uv + direction * (1.0 / 3.0 - 0.5)) + sample(in.uv + direction * (2.0 / 3.0 - 0.5)));
+    let b = a * 0.5 + 0.25 * (sample(in.uv - direction * 0.5) + sample(in.uv + direction * 0.5));
+    // the wider blur sampled across another edge, fall back to the narrow one
+    let luma_b = luma(b);
+    if luma_b < luma_min || luma_b > luma_max {
+        return vec4<f32>(a, center.a);
+    }
+    return vec4<f32>(b, center.a);
+}
@@ -0,0 +1,32 @@
+// shared by every post-processing effect, it's prepended to the effect's own source.
+// see the docs on crate::post::CustomEffect for the conventions effects are written against.
+
+// the output of the previous effect, or the scene for the first one
+@group(0) @binding(0)
+var input: texture_2d<f32>;
+// linear filtering, clamped to the edge
+@group(0) @binding(1)
+var input_sampler: sampler;
+// binding 2 holds the effect's params, declared by the effect since their layout is up to the effect
+// effect specific, e.g. the blurred highlights when compositing bloom
+@group(0) @binding(3)
+var aux: texture_2d<f32>;
+
+struct FullscreenOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+@vertex
+fn vertex(@builtin(vertex_index) index: u32) -> FullscreenOutput {
+    // a single triangle that covers the whole target
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    var out: FullscreenOutput;
+    out.position = vec4<f32>(uv * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0), 0.0, 1.0);
+    out.uv = uv;
+    return out;
+}
+
+fn input_texel_size() -> vec2<f32> {
+    return 1.0 / vec2<f32>(textureDimensions(input));
+}
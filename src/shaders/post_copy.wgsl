@@ -0,0 +1,4 @@
+@fragment
+fn fragment(in: FullscreenOutput) -> @location(0) vec4<f32> {
+    return textureSampleLevel(input, input_sampler, in.uv, 0.0);
+}
@@ -0,0 +1,239 @@
+@group(0) @binding(0)
+var<uniform> camera: Camera;
+@group(0) @binding(1)
+var<uniform> viewport: Viewport;
+
+struct Camera {
+    view_projection: mat4x4<f32>,
+    position: vec3<f32>,
+}
+
+struct Viewport {
+    size: vec2<f32>,
+}
+
+const PI: f32 = 3.14159265359;
+const TAU: f32 = 6.28318530718;
+
+struct VertexInput {
+    @location(0) position: vec2<f32>,
+}
+
+struct InstanceInput {
+    @location(1) model_matrix_0: vec4<f32>,
+    @location(2) model_matrix_1: vec4<f32>,
+    @location(3) model_matrix_2: vec4<f32>,
+    @location(4) model_matrix_3: vec4<f32>,
+    @location(5) bounds: vec4<f32>,
+    @location(6) params: vec4<f32>,
+    @location(7) more_params: vec4<f32>,
+    @location(8) fill: vec4<f32>,
+    @location(9) stroke: vec4<f32>,
+    @location(10) stroke_width: f32,
+    @location(11) kind: u32,
+    @location(12) cap: u32,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    // in the shape's local space
+    @location(0) local: vec2<f32>,
+    @location(1) params: vec4<f32>,
+    @location(2) more_params: vec4<f32>,
+    @location(3) fill: vec4<f32>,
+    @location(4) stroke: vec4<f32>,
+    @location(5) stroke_width: f32,
+    @location(6) @interpolate(flat) kind: u32,
+    @location(7) @interpolate(flat) cap: u32,
+}
+
+// how many pixels a local unit along `axis` covers around `center`
+fn pixels_per_unit(center: vec4<f32>, axis: vec4<f32>) -> f32 {
+    let ndc = (axis.xy * center.w - center.xy * axis.w) / (center.w * center.w);
+    return length(ndc * viewport.size * 0.5);
+}
+
+@vertex
+fn vertex(vertex: VertexInput, instance: InstanceInput) -> VertexOutput {
+    let model_matrix = mat4x4<f32>(
+        instance.model_matrix_0,
+        instance.model_matrix_1,
+        instance.model_matrix_2,
+        instance.model_matrix_3,
+    );
+    let mvp = camera.view_projection * model_matrix;
+
+    // pad the quad by a pixel or so, otherwise the anti-aliased edge gets cut off
+    let center = mvp * vec4<f32>((instance.bounds.xy + instance.bounds.zw) * 0.5, 0.0, 1.0);
+    let unit = vec2<f32>(
+        pixels_per_unit(center, mvp * vec4<f32>(1.0, 0.0, 0.0, 0.0)),
+        pixels_per_unit(center, mvp * vec4<f32>(0.0, 1.0, 0.0, 0.0)),
+    );
+    let size = instance.bounds.zw - instance.bounds.xy;
+    let margin = min(1.5 / max(unit, vec2<f32>(0.0001)), max(size, vec2<f32>(1.0)));
+    let local = mix(instance.bounds.xy - margin, instance.bounds.zw + margin, vertex.position);
+
+    var out: VertexOutput;
+    out.clip_position = mvp * vec4<f32>(local, 0.0, 1.0);
+    out.local = local;
+    out.params = instance.params;
+    out.more_params = instance.more_params;
+    out.fill = instance.fill;
+    out.stroke = instance.stroke;
+    out.stroke_width = instance.stroke_width;
+    out.kind = instance.kind;
+    out.cap = instance.cap;
+    return out;
+}
+
+fn sd_box(p: vec2<f32>, half_size: vec2<f32>) -> f32 {
+    let d = abs(p) - half_size;
+    return length(max(d, vec2<f32>(0.0))) + min(max(d.x, d.y), 0.0);
+}
+
+fn sd_segment(p: vec2<f32>, a: vec2<f32>, b: vec2<f32>) -> f32 {
+    let pa = p - a;
+    let ba = b - a;
+    let h = clamp(dot(pa, ba) / max(dot(ba, ba), 0.000001), 0.0, 1.0);
+    return length(pa - ba * h);
+}
+
+// radii are top right, bottom right, top left, bottom left
+fn sd_rounded_box(p: vec2<f32>, half_size: vec2<f32>, radii: vec4<f32>) -> f32 {
+    let side = select(radii.zw, radii.xy, p.x > 0.0);
+    let radius = min(select(side.y, side.x, p.y > 0.0), min(half_size.x, half_size.y));
+    let q = abs(p) - half_size + radius;
+    return min(max(q.x, q.y), 0.0) + length(max(q, vec2<f32>(0.0))) - radius;
+}
+
+// not exact away from the outline, which is all the anti-aliasing and strokes need
+fn sd_ellipse(p: vec2<f32>, radii: vec2<f32>) -> f32 {
+    let k0 = length(p / radii);
+    let k1 = length(p / (radii * radii));
+    return k0 * (k0 - 1.0) / max(k1, 0.000001);
+}
+
+fn sd_line(p: vec2<f32>, a: vec2<f32>, b: vec2<f32>, half_thickness: f32, cap: u32) -> f32 {
+    if cap == 1u {
+        return sd_segment(p, a, b) - half_thickness;
+    }
+    let length_ab = length(b - a);
+    let direction = select(vec2<f32>(1.0, 0.0), (b - a) / length_ab, length_ab > 0.0);
+    // square caps stick out by half the thickness
+    let half_length = length_ab * 0.5 + select(0.0, half_thickness, cap == 2u);
+    let relative = p - (a + b) * 0.5;
+    let q = vec2<f32>(dot(relative, direction), dot(relative, vec2<f32>(-direction.y, direction.x)));
+    return sd_box(q, vec2<f32>(half_length, half_thickness));
+}
+
+fn sd_arc(p: vec2<f32>, radius: f32, half_thickness: f32, start: f32, end: f32, cap: u32) -> f32 {
+    let ring = abs(length(p) - radius) - half_thickness;
+    let sweep = abs(end - start);
+    if sweep >= TAU {
+        return ring;
+    }
+
+    // rotate the arc's middle onto +y so that it's symmetric around the y axis
+    let angle = (start + end) * 0.5 - PI * 0.5;
+    let c = cos(angle);
+    let s = sin(angle);
+    var q = vec2<f32>(c * p.x + s * p.y, -s * p.x + c * p.y);
+    q.x = abs(q.x);
+
+    let half_sweep = sweep * 0.5;
+    if atan2(q.x, q.y) <= half_sweep {
+        return ring;
+    }
+    let end_direction = vec2<f32>(sin(half_sweep), cos(half_sweep));
+    if cap == 1u {
+        return length(q - end_direction * radius) - half_thickness;
+    }
+    // past the end of a butt capped arc, the closest point is on the flat end
+    return sd_segment(q, end_direction * (radius - half_thickness), end_direction * (radius + half_thickness));
+}
+
+// corners are `radius` away from the centre, with the first one on +y
+fn sd_regular_polygon(p: vec2<f32>, radius: f32, sides: f32) -> f32 {
+    let half_angle = PI / sides;
+    let corner = vec2<f32>(cos(half_angle), sin(half_angle));
+    let angle = atan2(p.x, p.y);
+    let wrapped = angle - 2.0 * half_angle * floor(angle / (2.0 * half_angle)) - half_angle;
+    var q = length(p) * vec2<f32>(cos(wrapped), abs(sin(wrapped)));
+    q = q - radius * corner;
+    q.y += clamp(-q.y, 0.0, radius * corner.y);
+    return length(q) * sign(q.x);
+}
+
+fn sd_triangle(p: vec2<f32>, p0: vec2<f32>, p1: vec2<f32>, p2: vec2<f32>) -> f32 {
+    let e0 = p1 - p0;
+    let e1 = p2 - p1;
+    let e2 = p0 - p2;
+    let v0 = p - p0;
+    let v1 = p - p1;
+    let v2 = p - p2;
+    let pq0 = v0 - e0 * clamp(dot(v0, e0) / max(dot(e0, e0), 0.000001), 0.0, 1.0);
+    let pq1 = v1 - e1 * clamp(dot(v1, e1) / max(dot(e1, e1), 0.000001), 0.0, 1.0);
+    let pq2 = v2 - e2 * clamp(dot(v2, e2) / max(dot(e2, e2), 0.000001), 0.0, 1.0);
+    // works for both windings
+    let s = sign(e0.x * e2.y - e0.y * e2.x);
+    let d = min(
+        min(
+            vec2<f32>(dot(pq0, pq0), s * (v0.x * e0.y - v0.y * e0.x)),
+            vec2<f32>(dot(pq1, pq1), s * (v1.x * e1.y - v1.y * e1.x)),
+        ),
+        vec2<f32>(dot(pq2, pq2), s * (v2.x * e2.y - v2.y * e2.x)),
+    );
+    return -sqrt(d.x) * sign(d.y);
+}
+
+fn sdf(in: VertexOutput) -> f32 {
+    let p = in.local;
+    switch in.kind {
+        case 0u: {
+            return length(p - in.params.xy) - in.params.z;
+        }
+        case 1u: {
+            return sd_ellipse(p - in.params.xy, in.params.zw);
+        }
+        case 2u: {
+            return sd_rounded_box(p - in.params.xy, in.params.zw, in.more_params);
+        }
+        case 3u: {
+            return sd_line(p, in.params.xy, in.params.zw, in.more_params.x, in.cap);
+        }
+        case 4u: {
+            return sd_arc(p - in.params.xy, in.params.z, in.params.w, in.more_params.x, in.more_params.y, in.cap);
+        }
+        case 5u: {
+            let c = cos(in.more_params.x);
+            let s = sin(in.more_params.x);
+            let relative = p - in.params.xy;
+            let rotated = vec2<f32>(c * relative.x + s * relative.y, -s * relative.x + c * relative.y);
+            return sd_regular_polygon(rotated, in.params.z, in.params.w);
+        }
+        default: {
+            return sd_triangle(p, in.params.xy, in.params.zw, in.more_params.xy);
+        }
+    }
+}
+
+@fragment
+fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
+    let d = sdf(in);
+    // the distance's rate of change across a pixel, so edges are a pixel wide at any scale
+    let aa = max(fwidth(d), 0.0001);
+
+    var color = vec4<f32>(in.fill.rgb, in.fill.a * clamp(0.5 - d / aa, 0.0, 1.0));
+    if in.stroke_width > 0.0 {
+        // the stroke goes over the fill
+        let coverage = in.stroke.a * clamp(0.5 - (abs(d) - in.stroke_width * 0.5) / aa, 0.0, 1.0);
+        let alpha = coverage + color.a * (1.0 - coverage);
+        let rgb = (in.stroke.rgb * coverage + color.rgb * color.a * (1.0 - coverage)) / max(alpha, 0.0001);
+        color = vec4<f32>(rgb, alpha);
+    }
+
+    if color.a <= 0.0 {
+        discard;
+    }
+    return color;
+}
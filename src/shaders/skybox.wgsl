@@ -0,0 +1,44 @@
+@group(0) @binding(0)
+var environment: texture_cube<f32>;
+@group(0) @binding(1)
+var environment_sampler: sampler;
+
+struct VertexInput {
+    @location(0) position: vec2<f32>,
+}
+
+struct InstanceInput {
+    // inverse of the camera's view projection, without the view's translation
+    @location(1) inverse_view_projection_0: vec4<f32>,
+    @location(2) inverse_view_projection_1: vec4<f32>,
+    @location(3) inverse_view_projection_2: vec4<f32>,
+    @location(4) inverse_view_projection_3: vec4<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    // divided by w in the fragment shader, dividing here wouldn't interpolate linearly
+    @location(0) direction: vec4<f32>,
+}
+
+@vertex
+fn vertex(vertex: VertexInput, instance: InstanceInput) -> VertexOutput {
+    let inverse_view_projection = mat4x4<f32>(
+        instance.inverse_view_projection_0,
+        instance.inverse_view_projection_1,
+        instance.inverse_view_projection_2,
+        instance.inverse_view_projection_3,
+    );
+
+    var out: VertexOutput;
+    // on the far plane so that everything else ends up in front of the sky
+    out.clip_position = vec4<f32>(vertex.position, 1.0, 1.0);
+    out.direction = inverse_view_projection * vec4<f32>(vertex.position, 1.0, 1.0);
+    return out;
+}
+
+@fragment
+fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
+    let direction = normalize(in.direction.xyz / in.direction.w);
+    return textureSample(environment, environment_sampler, direction);
+}
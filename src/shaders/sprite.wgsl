@@ -0,0 +1,61 @@
+@group(0) @binding(0)
+var<uniform> camera: Camera;
+@group(0) @binding(1)
+var atlas: texture_2d_array<f32>;
+@group(0) @binding(2)
+var atlas_sampler: sampler;
+
+struct Camera {
+    view_projection: mat4x4<f32>,
+    position: vec3<f32>,
+}
+
+struct VertexInput {
+    @location(0) position: vec2<f32>,
+}
+
+struct InstanceInput {
+    @location(1) model_matrix_0: vec4<f32>,
+    @location(2) model_matrix_1: vec4<f32>,
+    @location(3) model_matrix_2: vec4<f32>,
+    @location(4) model_matrix_3: vec4<f32>,
+    @location(5) color: vec4<f32>,
+    @location(6) atlas_coords: vec4<f32>,
+    @location(7) page: u32,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+    @location(1) color: vec4<f32>,
+    @location(2) @interpolate(flat) page: u32,
+}
+
+@vertex
+fn vertex(vertex: VertexInput, instance: InstanceInput) -> VertexOutput {
+    let model_matrix = mat4x4<f32>(
+        instance.model_matrix_0,
+        instance.model_matrix_1,
+        instance.model_matrix_2,
+        instance.model_matrix_3,
+    );
+
+    var out: VertexOutput;
+    out.clip_position = camera.view_projection * model_matrix * vec4<f32>(vertex.position, 0.0, 1.0);
+    // textures are stored top row first, the quad's y goes up
+    let uv = vec2<f32>(vertex.position.x, 1.0 - vertex.position.y);
+    out.uv = mix(instance.atlas_coords.xy, instance.atlas_coords.zw, uv);
+    out.color = instance.color;
+    out.page = instance.page;
+    return out;
+}
+
+@fragment
+fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(atlas, atlas_sampler, in.uv, in.page) * in.color;
+    // fully transparent texels would still cover whatever's drawn behind them later on
+    if color.a < 0.01 {
+        discard;
+    }
+    return color;
+}
@@ -4,6 +4,8 @@ var<uniform> camera: Camera;
 var atlas_texture: texture_2d<f32>;
 @group(0) @binding(2)
 var samp: sampler;
+@group(0) @binding(3)
+var color_atlas_texture: texture_2d<f32>;
 
 struct Camera {
     view_projection: mat4x4<f32>,
@@ -20,6 +22,15 @@ struct VertexOutput {
     @location(0) uv: vec2<f32>,
     @location(1) color: vec4<f32>,
     @location(2) atlas_coords: vec4<f32>,
+    @location(3) distance_range: f32,
+    // 0 for an sdf, 1 for an msdf and 2 for a color glyph
+    @location(4) @interpolate(flat) sampling: u32,
+    // weight, outline width, glow width and shadow softness
+    @location(5) effects: vec4<f32>,
+    @location(6) outline_color: vec4<f32>,
+    @location(7) shadow_color: vec4<f32>,
+    @location(8) glow_color: vec4<f32>,
+    @location(9) shadow_offset: vec2<f32>,
 }
 
 struct InstanceInput {
@@ -29,6 +40,13 @@ struct InstanceInput {
     @location(5) model_matrix_3: vec4<f32>,
     @location(6) albedo: vec4<f32>,
     @location(7) atlas_coords: vec4<f32>,
+    @location(8) distance_range: f32,
+    @location(9) sampling: u32,
+    @location(10) effects: vec4<f32>,
+    @location(11) outline_color: vec4<f32>,
+    @location(12) shadow_color: vec4<f32>,
+    @location(13) glow_color: vec4<f32>,
+    @location(14) shadow_offset: vec2<f32>,
 }
 
 @vertex
@@ -45,9 +63,40 @@ fn vertex(vertex: VertexInput, instance: InstanceInput) -> VertexOutput {
     out.uv = vertex.uv;
     out.color = instance.albedo;
     out.atlas_coords = instance.atlas_coords;
+    out.distance_range = instance.distance_range;
+    out.sampling = instance.sampling;
+    out.effects = instance.effects;
+    out.outline_color = instance.outline_color;
+    out.shadow_color = instance.shadow_color;
+    out.glow_color = instance.glow_color;
+    out.shadow_offset = instance.shadow_offset;
     return out;
 }
 
+fn median(r: f32, g: f32, b: f32) -> f32 {
+    return max(min(r, g), min(max(r, g), b));
+}
+
+// signed distance to the glyph's edge in atlas pixels, positive inside
+fn signed_distance(in: VertexOutput, uv: vec2<f32>) -> f32 {
+    let sampled = textureSample(atlas_texture, samp, uv);
+    var distance = sampled.r;
+    if in.sampling == 1u {
+        distance = median(sampled.r, sampled.g, sampled.b);
+    }
+    return (distance - 0.5) * in.distance_range;
+}
+
+// puts a premultiplied colour on top of another
+fn over(top: vec4<f32>, bottom: vec4<f32>) -> vec4<f32> {
+    return top + bottom * (1.0 - top.a);
+}
+
+fn premultiply(color: vec4<f32>, coverage: f32) -> vec4<f32> {
+    let alpha = color.a * coverage;
+    return vec4<f32>(color.rgb * alpha, alpha);
+}
+
 @fragment
 fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
     let uv_start = in.atlas_coords.xy;
@@ -55,10 +104,50 @@ fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
     var scale = uv_end - uv_start;
     let scaled_uv = scale * in.uv + uv_start;
 
+    let weight = in.effects.x;
+    let outline_width = in.effects.y;
+    let glow_width = in.effects.z;
+    let shadow_softness = in.effects.w;
 
-    let sampled = textureSample(atlas_texture, samp, scaled_uv).r;
-    let smoothing = 0.2 / 16.0;
-    let buffer = 0.5;
-    let alpha = smoothstep(buffer - smoothing, buffer + smoothing, sampled);
-    return vec4<f32>(in.color.rgb, alpha);
-}
\ No newline at end of file
+    // how many screen pixels an atlas pixel covers, keeps edges one pixel wide at any text size
+    let atlas_size = vec2<f32>(textureDimensions(atlas_texture));
+    let screen_tex_size = vec2<f32>(1.0) / fwidth(scaled_uv);
+    let px_per_texel = max(0.5 * dot(vec2<f32>(1.0) / atlas_size, screen_tex_size), 1.0 / in.distance_range);
+
+    // everything is measured from the glyph's edge, moved out by the weight
+    let distance = signed_distance(in, scaled_uv) + weight;
+    let fill = clamp(distance * px_per_texel + 0.5, 0.0, 1.0);
+    let outline = clamp((distance + outline_width) * px_per_texel + 0.5, 0.0, 1.0);
+
+    var color = premultiply(in.color, fill);
+    color = over(color, premultiply(in.outline_color, outline));
+
+    if glow_width > 0.0 {
+        let glow = clamp(1.0 + (distance + outline_width) / glow_width, 0.0, 1.0);
+        color = over(color, premultiply(in.glow_color, glow * glow));
+    }
+
+    // sampled even without a shadow, texture samples have to happen in uniform control flow
+    // the atlas' v goes down, and the shadow is sampled from where it's cast from
+    let shadow_uv = clamp(
+        scaled_uv - vec2<f32>(in.shadow_offset.x, -in.shadow_offset.y) / atlas_size,
+        min(uv_start, uv_end),
+        max(uv_start, uv_end),
+    );
+    let shadow_distance = signed_distance(in, shadow_uv) + weight + outline_width;
+    // the softness widens the edge, it's never sharper than a screen pixel
+    let shadow_sharpness = 1.0 / max(shadow_softness, 1.0 / px_per_texel);
+    let shadow = clamp(shadow_distance * shadow_sharpness + 0.5, 0.0, 1.0);
+    color = over(color, premultiply(in.shadow_color, shadow));
+
+    // color glyphs are images, sampled in uniform control flow like the distance field
+    let color_glyph = textureSample(color_atlas_texture, samp, scaled_uv);
+    if in.sampling == 2u {
+        color = vec4<f32>(color_glyph.rgb * color_glyph.a, color_glyph.a) * in.color.a;
+    }
+
+    if color.a <= 0.0 {
+        discard;
+    }
+    return vec4<f32>(color.rgb / color.a, color.a);
+}
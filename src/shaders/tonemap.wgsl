@@ -0,0 +1,31 @@
+struct Params {
+    exposure: f32,
+    // 0 for aces, 1 for reinhard
+    tonemapper: u32,
+}
+
+@group(0) @binding(2)
+var<uniform> params: Params;
+
+// krzysztof narkowicz's fit of the aces filmic curve
+fn aces(x: vec3<f32>) -> vec3<f32> {
+    let mapped = (x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14);
+    return clamp(mapped, vec3<f32>(0.0), vec3<f32>(1.0));
+}
+
+fn reinhard(x: vec3<f32>) -> vec3<f32> {
+    return x / (1.0 + x);
+}
+
+@fragment
+fn fragment(in: FullscreenOutput) -> @location(0) vec4<f32> {
+    let color = textureSampleLevel(input, input_sampler, in.uv, 0.0);
+    let exposed = color.rgb * params.exposure;
+    var mapped: vec3<f32>;
+    if params.tonemapper == 0u {
+        mapped = aces(exposed);
+    } else {
+        mapped = reinhard(exposed);
+    }
+    return vec4<f32>(mapped, color.a);
+}
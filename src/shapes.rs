@@ -1,22 +1,22 @@
-use nalgebra::Matrix4;
+use nalgebra::{Matrix4, Point2, Vector2, Vector4};
 use wgpu::{vertex_attr_array, BufferUsages, ShaderStages};
 
 use crate::{
     bind::{BindEntry, BindEntryType, BindHandle},
-    camera::CameraUniform,
+    camera::{Camera, CameraUniform},
     geometry::Geometry,
     instance::InstanceData,
     material::BasicMaterial,
     pipeline::{Pipeline, PipelineBuilder, PipelineHandle},
     plain::Plain,
-    render::{MeshHandle, Render},
+    render::{Mesh, MeshHandle, PhysicalSize, Render},
     render_object::RenderObject,
 };
 
 #[repr(C)]
 #[derive(Clone, Debug)]
 pub struct ShapeVertex {
-    pos: [f32; 3],
+    pub pos: [f32; 3],
 }
 
 unsafe impl Plain for ShapeVertex {}
@@ -67,7 +67,6 @@ pub fn shape_pipeline(render: &mut Render) -> (Pipeline, BindHandle) {
     }]);
 
     let pipeline_handle = PipelineBuilder::new()
-        .with_format(wgpu::TextureFormat::Bgra8UnormSrgb)
         // .with_cull_mode(Some(wgpu::Face::Back))
         .with_cull_mode(None)
         .with_bind(defaults_bind)
@@ -167,3 +166,456 @@ impl RenderObject for ShapeRenderObject {
         self.mesh_handle
     }
 }
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LineCap {
+    /// Ends exactly at the end points.
+    Butt,
+    /// A half circle past each end point.
+    Round,
+    /// Half the thickness past each end point.
+    Square,
+}
+
+/// A 2d primitive drawn with a signed distance function, so edges stay smooth at any scale.
+///
+/// Coordinates are in the shape's local space, which [SdfShapeRenderer::draw_transformed] maps into the world. +y is up.
+#[derive(Clone, Copy, Debug)]
+pub enum SdfShape {
+    Circle {
+        center: Point2<f32>,
+        radius: f32,
+    },
+    Ellipse {
+        center: Point2<f32>,
+        radii: Vector2<f32>,
+    },
+    RoundedRect {
+        center: Point2<f32>,
+        size: Vector2<f32>,
+        /// Top left, top right, bottom right and bottom left.
+        corner_radii: [f32; 4],
+    },
+    Line {
+        from: Point2<f32>,
+        to: Point2<f32>,
+        thickness: f32,
+        cap: LineCap,
+    },
+    /// A circular arc going counter clockwise from `start_angle` to `end_angle`, in radians from +x.
+    /// Square caps are drawn as butt caps.
+    Arc {
+        center: Point2<f32>,
+        radius: f32,
+        start_angle: f32,
+        end_angle: f32,
+        thickness: f32,
+        cap: LineCap,
+    },
+    /// A polygon with `sides` equal sides whose corners are `radius` away from the center, the first corner points up before rotating.
+    RegularPolygon {
+        center: Point2<f32>,
+        radius: f32,
+        sides: u32,
+        rotation: f32,
+    },
+    Triangle {
+        a: Point2<f32>,
+        b: Point2<f32>,
+        c: Point2<f32>,
+    },
+}
+
+impl SdfShape {
+    /// The shader's id for the shape.
+    fn kind(&self) -> u32 {
+        match self {
+            SdfShape::Circle { .. } => 0,
+            SdfShape::Ellipse { .. } => 1,
+            SdfShape::RoundedRect { .. } => 2,
+            SdfShape::Line { .. } => 3,
+            SdfShape::Arc { .. } => 4,
+            SdfShape::RegularPolygon { .. } => 5,
+            SdfShape::Triangle { .. } => 6,
+        }
+    }
+
+    /// The shape's local bounding box as [min x, min y, max x, max y].
+    pub fn bounds(&self) -> [f32; 4] {
+        let around = |center: &Point2<f32>, x: f32, y: f32| {
+            [center.x - x, center.y - y, center.x + x, center.y + y]
+        };
+        match self {
+            SdfShape::Circle { center, radius } => around(center, *radius, *radius),
+            SdfShape::Ellipse { center, radii } => around(center, radii.x, radii.y),
+            SdfShape::RoundedRect { center, size, .. } => {
+                around(center, size.x / 2.0, size.y / 2.0)
+            }
+            SdfShape::Line {
+                from,
+                to,
+                thickness,
+                ..
+            } => [
+                // covers square caps at any angle
+                from.x.min(to.x) - thickness,
+                from.y.min(to.y) - thickness,
+                from.x.max(to.x) + thickness,
+                from.y.max(to.y) + thickness,
+            ],
+            SdfShape::Arc {
+                center,
+                radius,
+                thickness,
+                ..
+            } => around(center, radius + thickness / 2.0, radius + thickness / 2.0),
+            SdfShape::RegularPolygon { center, radius, .. } => around(center, *radius, *radius),
+            SdfShape::Triangle { a, b, c } => [
+                a.x.min(b.x).min(c.x),
+                a.y.min(b.y).min(c.y),
+                a.x.max(b.x).max(c.x),
+                a.y.max(b.y).max(c.y),
+            ],
+        }
+    }
+
+    /// The shape's parameters as the shader expects them.
+    fn params(&self) -> (Vector4<f32>, Vector4<f32>, LineCap) {
+        match *self {
+            SdfShape::Circle { center, radius } => (
+                Vector4::new(center.x, center.y, radius, 0.0),
+                Vector4::zeros(),
+                LineCap::Butt,
+            ),
+            SdfShape::Ellipse { center, radii } => (
+                Vector4::new(center.x, center.y, radii.x, radii.y),
+                Vector4::zeros(),
+                LineCap::Butt,
+            ),
+            SdfShape::RoundedRect {
+                center,
+                size,
+                corner_radii: [top_left, top_right, bottom_right, bottom_left],
+            } => (
+                Vector4::new(center.x, center.y, size.x / 2.0, size.y / 2.0),
+                Vector4::new(top_right, bottom_right, top_left, bottom_left),
+                LineCap::Butt,
+            ),
+            SdfShape::Line {
+                from,
+                to,
+                thickness,
+                cap,
+            } => (
+                Vector4::new(from.x, from.y, to.x, to.y),
+                Vector4::new(thickness / 2.0, 0.0, 0.0, 0.0),
+                cap,
+            ),
+            SdfShape::Arc {
+                center,
+                radius,
+                start_angle,
+                end_angle,
+                thickness,
+                cap,
+            } => (
+                Vector4::new(center.x, center.y, radius, thickness / 2.0),
+                Vector4::new(start_angle, end_angle, 0.0, 0.0),
+                cap,
+            ),
+            SdfShape::RegularPolygon {
+                center,
+                radius,
+                sides,
+                rotation,
+            } => (
+                Vector4::new(center.x, center.y, radius, sides.max(3) as f32),
+                Vector4::new(rotation, 0.0, 0.0, 0.0),
+                LineCap::Butt,
+            ),
+            SdfShape::Triangle { a, b, c } => (
+                Vector4::new(a.x, a.y, b.x, b.y),
+                Vector4::new(c.x, c.y, 0.0, 0.0),
+                LineCap::Butt,
+            ),
+        }
+    }
+
+    pub fn instance(&self, style: &ShapeStyle, transform: Matrix4<f32>) -> SdfShapeInstance {
+        let [min_x, min_y, max_x, max_y] = self.bounds();
+        // strokes are centred on the outline
+        let stroke = style.stroke_width / 2.0;
+        let (params, more_params, cap) = self.params();
+        SdfShapeInstance {
+            transform,
+            bounds: Vector4::new(
+                min_x - stroke,
+                min_y - stroke,
+                max_x + stroke,
+                max_y + stroke,
+            ),
+            params,
+            more_params,
+            fill: style.fill,
+            stroke: style.stroke,
+            stroke_width: style.stroke_width,
+            kind: self.kind(),
+            cap: match cap {
+                LineCap::Butt => 0,
+                LineCap::Round => 1,
+                LineCap::Square => 2,
+            },
+            _padding: 0,
+        }
+    }
+}
+
+/// How an [SdfShape] is filled and outlined.
+#[derive(Clone, Copy, Debug)]
+pub struct ShapeStyle {
+    pub fill: [f32; 4],
+    pub stroke: [f32; 4],
+    /// The stroke straddles the shape's outline, 0 for no stroke.
+    pub stroke_width: f32,
+}
+
+impl ShapeStyle {
+    pub fn fill(color: [f32; 4]) -> Self {
+        Self {
+            fill: color,
+            stroke: [0.0; 4],
+            stroke_width: 0.0,
+        }
+    }
+
+    /// Just an outline, the inside is left transparent.
+    pub fn stroke(color: [f32; 4], width: f32) -> Self {
+        Self {
+            fill: [0.0; 4],
+            stroke: color,
+            stroke_width: width,
+        }
+    }
+
+    pub fn with_fill(mut self, color: [f32; 4]) -> Self {
+        self.fill = color;
+        self
+    }
+
+    pub fn with_stroke(mut self, color: [f32; 4], width: f32) -> Self {
+        self.stroke = color;
+        self.stroke_width = width;
+        self
+    }
+}
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct SdfShapeInstance {
+    pub transform: Matrix4<f32>,
+    /// The quad drawn for the shape in local space, [min x, min y, max x, max y].
+    pub bounds: Vector4<f32>,
+    pub params: Vector4<f32>,
+    pub more_params: Vector4<f32>,
+    pub fill: [f32; 4],
+    pub stroke: [f32; 4],
+    pub stroke_width: f32,
+    pub kind: u32,
+    pub cap: u32,
+    pub _padding: u32,
+}
+
+unsafe impl Plain for SdfShapeInstance {}
+
+impl InstanceData for SdfShapeInstance {}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct SdfShapeVertex {
+    pub pos: [f32; 2],
+}
+
+unsafe impl Plain for SdfShapeVertex {}
+
+/// A unit quad which the vertex shader stretches over the shape's bounds.
+#[derive(Debug)]
+pub struct SdfShapeGeometry {
+    pub vertices: [SdfShapeVertex; 4],
+    pub indices: [u16; 6],
+}
+
+impl Default for SdfShapeGeometry {
+    fn default() -> Self {
+        Self {
+            vertices: [
+                SdfShapeVertex { pos: [0.0, 0.0] },
+                SdfShapeVertex { pos: [1.0, 0.0] },
+                SdfShapeVertex { pos: [0.0, 1.0] },
+                SdfShapeVertex { pos: [1.0, 1.0] },
+            ],
+            indices: [0, 1, 2, 2, 1, 3],
+        }
+    }
+}
+
+impl Geometry for SdfShapeGeometry {
+    fn contents(&self) -> &[u8] {
+        self.vertices.as_bytes()
+    }
+
+    fn length(&self) -> u32 {
+        self.vertices.len() as u32
+    }
+
+    fn indices(&self) -> Option<&[u8]> {
+        Some(self.indices.as_bytes())
+    }
+}
+
+/// The viewport's size in pixels, used to pad every shape's quad by a pixel for its anti-aliased edge.
+#[repr(C)]
+struct ViewportUniform {
+    size: [f32; 2],
+    _padding: [f32; 2],
+}
+
+unsafe impl Plain for ViewportUniform {}
+
+/// Draws [SdfShape]s with anti-aliased edges, shapes are drawn in the order they're queued.
+///
+/// ```ignore
+/// let mut shapes = SdfShapeRenderer::new(&mut render);
+///
+/// // on resize
+/// shapes.set_camera(&mut render, &camera, size);
+///
+/// // every frame
+/// shapes.draw(
+///     SdfShape::Circle { center: point![50.0, 50.0], radius: 20.0 },
+///     ShapeStyle::fill([1.0, 0.5, 0.0, 1.0]).with_stroke([0.0, 0.0, 0.0, 1.0], 2.0),
+/// );
+/// shapes.flush(&mut render);
+/// render.draw()?;
+/// ```
+pub struct SdfShapeRenderer {
+    bind: BindHandle,
+    pipeline_handle: PipelineHandle,
+    mesh_handle: MeshHandle,
+    instances: Vec<SdfShapeInstance>,
+}
+
+impl SdfShapeRenderer {
+    pub fn new(render: &mut Render) -> Self {
+        let bind = render.build_bind(&mut [
+            // camera
+            BindEntry {
+                visibility: ShaderStages::VERTEX,
+                ty: BindEntryType::BufferUniform {
+                    size: std::mem::size_of::<CameraUniform>() as u64,
+                    usages: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+                },
+                count: None,
+            },
+            // viewport
+            BindEntry {
+                visibility: ShaderStages::VERTEX,
+                ty: BindEntryType::BufferUniform {
+                    size: std::mem::size_of::<ViewportUniform>() as u64,
+                    usages: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+                },
+                count: None,
+            },
+        ]);
+
+        let pipeline = PipelineBuilder::new()
+            .with_cull_mode(None)
+            // the anti-aliased edges are blended, writing depth would cut into shapes drawn afterwards
+            .with_depth_write(false)
+            .with_bind(bind)
+            .with_shader(include_str!("shaders/sdf_shapes.wgsl"))
+            .with_vb::<SdfShapeVertex>(
+                wgpu::VertexStepMode::Vertex,
+                &vertex_attr_array![
+                    // position
+                    0 => Float32x2,
+                ],
+            )
+            .with_vb::<SdfShapeInstance>(
+                wgpu::VertexStepMode::Instance,
+                &vertex_attr_array![
+                    // transform
+                    1 => Float32x4,
+                    2 => Float32x4,
+                    3 => Float32x4,
+                    4 => Float32x4,
+                    // bounds
+                    5 => Float32x4,
+                    // params
+                    6 => Float32x4,
+                    7 => Float32x4,
+                    // fill
+                    8 => Float32x4,
+                    // stroke
+                    9 => Float32x4,
+                    // stroke width
+                    10 => Float32,
+                    // kind
+                    11 => Uint32,
+                    // cap
+                    12 => Uint32,
+                ],
+            )
+            .build(render);
+        let pipeline_handle = render.add_pipeline(pipeline);
+
+        let mesh_handle =
+            render.add_mesh::<SdfShapeGeometry, SdfShapeInstance, BasicMaterial>(Mesh {
+                material: BasicMaterial {},
+                geometry: SdfShapeGeometry::default(),
+            });
+
+        Self {
+            bind,
+            pipeline_handle,
+            mesh_handle,
+            instances: Vec::new(),
+        }
+    }
+
+    /// The bind holding the camera (binding 0) and the viewport size (binding 1).
+    pub fn bind(&self) -> BindHandle {
+        self.bind
+    }
+
+    /// `viewport` is the size of the window in pixels.
+    pub fn set_camera(&self, render: &mut Render, camera: &Camera, viewport: PhysicalSize<u32>) {
+        render.write_buffer(camera.uniform().as_bytes(), self.bind, 0);
+        let viewport = ViewportUniform {
+            size: [viewport.width as f32, viewport.height as f32],
+            _padding: [0.0; 2],
+        };
+        render.write_buffer(viewport.as_bytes(), self.bind, 1);
+    }
+
+    /// Queues a shape for the next [SdfShapeRenderer::flush].
+    pub fn draw(&mut self, shape: SdfShape, style: ShapeStyle) {
+        self.draw_transformed(shape, style, Matrix4::identity());
+    }
+
+    /// Like [SdfShapeRenderer::draw] but moves the shape from its local space with `transform`.
+    pub fn draw_transformed(
+        &mut self,
+        shape: SdfShape,
+        style: ShapeStyle,
+        transform: Matrix4<f32>,
+    ) {
+        self.instances.push(shape.instance(&style, transform));
+    }
+
+    /// Hands the queued shapes to the renderer for the next [Render::draw].
+    pub fn flush(&mut self, render: &mut Render) {
+        for instance in self.instances.drain(..) {
+            render.add_instance(self.mesh_handle, self.pipeline_handle, instance);
+        }
+    }
+}
@@ -0,0 +1,233 @@
+use anyhow::Result;
+use nalgebra::Matrix4;
+use wgpu::{
+    vertex_attr_array, Extent3d, FilterMode, SamplerBindingType, SamplerDescriptor, ShaderStages,
+    TextureSampleType, TextureUsages, TextureViewDimension,
+};
+
+use crate::{
+    bind::{BindEntry, BindEntryType, BindHandle},
+    camera::Camera,
+    geometry::Geometry,
+    instance::InstanceData,
+    material::BasicMaterial,
+    mipmap::is_filterable,
+    pipeline::{CompareFunction, PipelineBuilder, PipelineHandle},
+    plain::Plain,
+    render::{Mesh, MeshHandle, Render},
+    texture::{Cubemap, TextureFormat},
+};
+
+/// The sky is drawn before everything else, anything drawn afterwards covers it.
+pub const SKYBOX_ORDER: i32 = -1000;
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct SkyboxVertex {
+    pub pos: [f32; 2],
+}
+
+unsafe impl Plain for SkyboxVertex {}
+
+/// A single triangle that covers the whole screen.
+#[derive(Debug)]
+pub struct SkyboxGeometry {
+    pub vertices: [SkyboxVertex; 3],
+}
+
+impl Default for SkyboxGeometry {
+    fn default() -> Self {
+        Self {
+            vertices: [
+                SkyboxVertex { pos: [-1.0, -1.0] },
+                SkyboxVertex { pos: [3.0, -1.0] },
+                SkyboxVertex { pos: [-1.0, 3.0] },
+            ],
+        }
+    }
+}
+
+impl Geometry for SkyboxGeometry {
+    fn contents(&self) -> &[u8] {
+        self.vertices.as_bytes()
+    }
+
+    fn length(&self) -> u32 {
+        self.vertices.len() as u32
+    }
+
+    fn indices(&self) -> Option<&[u8]> {
+        None
+    }
+}
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct SkyboxInstance {
+    pub inverse_view_projection: Matrix4<f32>,
+}
+
+unsafe impl Plain for SkyboxInstance {}
+
+impl InstanceData for SkyboxInstance {}
+
+impl SkyboxInstance {
+    /// Only the camera's rotation matters, the sky is infinitely far away.
+    pub fn from_camera(camera: &Camera) -> Self {
+        let mut view = camera.view();
+        view.fixed_view_mut::<3, 1>(0, 3).fill(0.0);
+        let inverse_view_projection = (camera.projection() * view)
+            .try_inverse()
+            .unwrap_or_else(Matrix4::identity);
+        Self {
+            inverse_view_projection,
+        }
+    }
+}
+
+/// Bind entries for a cube texture and a sampler for it, binding 0 and 1 respectively.
+///
+/// Use them in lit pipelines to sample a cubemap as an environment map:
+///
+/// ```wgsl
+/// @group(1) @binding(0)
+/// var environment: texture_cube<f32>;
+/// @group(1) @binding(1)
+/// var environment_sampler: sampler;
+/// ```
+///
+/// Non filterable formats (e.g. [TextureFormat::Rgba32Float]) get a non filtering sampler and no mips.
+pub fn cubemap_entries<'a>(
+    render: &Render,
+    format: TextureFormat,
+    face_size: u32,
+    visibility: ShaderStages,
+) -> [BindEntry<'a>; 2] {
+    let filterable = is_filterable(render.device(), format.wgpu_format());
+    let filter = if filterable {
+        FilterMode::Linear
+    } else {
+        FilterMode::Nearest
+    };
+
+    [
+        BindEntry {
+            visibility,
+            ty: BindEntryType::Texture {
+                sample_type: TextureSampleType::Float { filterable },
+                view_dimension: TextureViewDimension::Cube,
+                sample_count: 1,
+                format: format.wgpu_format(),
+                size: Extent3d {
+                    width: face_size,
+                    height: face_size,
+                    depth_or_array_layers: 6,
+                },
+                usage: TextureUsages::COPY_DST | TextureUsages::TEXTURE_BINDING,
+                // blurrier mips make for cheap rough reflections
+                mip_level_count: if filterable { u32::MAX } else { 1 },
+            },
+            count: None,
+        },
+        BindEntry {
+            visibility,
+            ty: BindEntryType::Sampler {
+                binding_type: if filterable {
+                    SamplerBindingType::Filtering
+                } else {
+                    SamplerBindingType::NonFiltering
+                },
+                descriptor: SamplerDescriptor {
+                    mag_filter: filter,
+                    min_filter: filter,
+                    mipmap_filter: filter,
+                    ..Default::default()
+                },
+            },
+            count: None,
+        },
+    ]
+}
+
+/// Draws a cubemap behind everything else.
+///
+/// ```ignore
+/// let panorama = Texture::load_as("sky.hdr", TextureFormat::Rgba16Float)?;
+/// let cubemap = Cubemap::from_equirectangular(&panorama, 512)?;
+/// let skybox = Skybox::new(&mut render, cubemap.format(), cubemap.face_size());
+/// skybox.set_cubemap(&mut render, &cubemap)?;
+///
+/// // every frame
+/// skybox.draw(&mut render, &camera);
+/// render.draw()?;
+/// ```
+///
+/// The cubemap lives in [Skybox::bind], which lit pipelines can also bind to use the sky as an environment map, see [cubemap_entries].
+pub struct Skybox {
+    bind: BindHandle,
+    pipeline_handle: PipelineHandle,
+    mesh_handle: MeshHandle,
+}
+
+impl Skybox {
+    pub fn new(render: &mut Render, format: TextureFormat, face_size: u32) -> Self {
+        let mut entries = cubemap_entries(render, format, face_size, ShaderStages::FRAGMENT);
+        let bind = render.build_bind(&mut entries);
+
+        let pipeline = PipelineBuilder::new()
+            .with_cull_mode(None)
+            .with_depth_compare(CompareFunction::LessEqual)
+            .with_depth_write(false)
+            .with_order(SKYBOX_ORDER)
+            .with_bind(bind)
+            .with_shader(include_str!("shaders/skybox.wgsl"))
+            .with_vb::<SkyboxVertex>(
+                wgpu::VertexStepMode::Vertex,
+                &vertex_attr_array![
+                    // position
+                    0 => Float32x2,
+                ],
+            )
+            .with_vb::<SkyboxInstance>(
+                wgpu::VertexStepMode::Instance,
+                &vertex_attr_array![
+                    // inverse view projection
+                    1 => Float32x4,
+                    2 => Float32x4,
+                    3 => Float32x4,
+                    4 => Float32x4,
+                ],
+            )
+            .build(render);
+        let pipeline_handle = render.add_pipeline(pipeline);
+
+        let mesh_handle = render.add_mesh::<SkyboxGeometry, SkyboxInstance, BasicMaterial>(Mesh {
+            material: BasicMaterial {},
+            geometry: SkyboxGeometry::default(),
+        });
+
+        Self {
+            bind,
+            pipeline_handle,
+            mesh_handle,
+        }
+    }
+
+    /// The bind holding the cubemap (binding 0) and its sampler (binding 1).
+    pub fn bind(&self) -> BindHandle {
+        self.bind
+    }
+
+    pub fn set_cubemap(&self, render: &mut Render, cubemap: &Cubemap) -> Result<()> {
+        render.write_cubemap(cubemap, self.bind, 0)
+    }
+
+    /// Queues the sky for the next [Render::draw].
+    pub fn draw(&self, render: &mut Render, camera: &Camera) {
+        render.add_instance(
+            self.mesh_handle,
+            self.pipeline_handle,
+            SkyboxInstance::from_camera(camera),
+        );
+    }
+}
@@ -0,0 +1,160 @@
+use crate::sprite::{pipeline::Sprite, sheet::SpriteSheet};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Playback {
+    /// Plays through once and stays on the last frame.
+    Once,
+    Loop,
+    /// Plays forwards, then backwards, then forwards again and so on.
+    PingPong,
+}
+
+/// A sequence of [SpriteSheet] frames.
+#[derive(Clone, Debug)]
+pub struct Animation {
+    /// Indices into the sheet's frames, in playback order.
+    pub frames: Vec<usize>,
+    /// How long each frame is shown for in seconds, one per entry in `frames`.
+    pub durations: Vec<f32>,
+    pub playback: Playback,
+}
+
+impl Animation {
+    /// A looping animation showing every frame for `frame_duration` seconds.
+    pub fn new(frames: impl IntoIterator<Item = usize>, frame_duration: f32) -> Self {
+        let frames = frames.into_iter().collect::<Vec<_>>();
+        let durations = vec![frame_duration; frames.len()];
+        Self {
+            frames,
+            durations,
+            playback: Playback::Loop,
+        }
+    }
+
+    pub fn with_playback(mut self, playback: Playback) -> Self {
+        self.playback = playback;
+        self
+    }
+
+    /// Positions in `frames` over one cycle, ping pong doesn't repeat the first and last frames when turning around.
+    fn sequence(&self) -> impl Iterator<Item = usize> {
+        let len = self.frames.len().min(self.durations.len());
+        let back = match self.playback {
+            Playback::PingPong => 1..len.saturating_sub(1),
+            _ => 0..0,
+        };
+        (0..len).chain(back.rev())
+    }
+
+    /// How long it takes to play through once, or there and back again for ping pong.
+    pub fn cycle_duration(&self) -> f32 {
+        self.sequence().map(|pos| self.durations[pos]).sum()
+    }
+
+    /// The sheet frame shown `time` seconds into the animation, none for an empty animation.
+    pub fn frame_at(&self, time: f32) -> Option<usize> {
+        let cycle = self.cycle_duration();
+        let mut time = match self.playback {
+            Playback::Once => time.min(cycle),
+            _ if cycle > 0.0 => time.rem_euclid(cycle),
+            _ => 0.0,
+        };
+
+        let mut last = None;
+        for pos in self.sequence() {
+            if time < self.durations[pos] {
+                return Some(self.frames[pos]);
+            }
+            time -= self.durations[pos];
+            last = Some(self.frames[pos]);
+        }
+        last
+    }
+
+    pub fn is_finished_at(&self, time: f32) -> bool {
+        self.playback == Playback::Once && time >= self.cycle_duration()
+    }
+}
+
+/// Plays an [Animation], advanced by [AnimationPlayer::update] every frame.
+///
+/// ```ignore
+/// let mut player = AnimationPlayer::new(sheet.animation("run").unwrap().clone());
+///
+/// // every frame
+/// player.update(dt);
+/// if let Some(sprite) = player.sprite(&sheet) {
+///     sprites.draw(sprite.with_position(x, y));
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct AnimationPlayer {
+    animation: Animation,
+    time: f32,
+    /// Multiplies the time passed to [AnimationPlayer::update], defaults to 1.
+    pub speed: f32,
+    paused: bool,
+}
+
+impl AnimationPlayer {
+    pub fn new(animation: Animation) -> Self {
+        Self {
+            animation,
+            time: 0.0,
+            speed: 1.0,
+            paused: false,
+        }
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        if !self.paused {
+            self.time += dt * self.speed;
+        }
+    }
+
+    pub fn animation(&self) -> &Animation {
+        &self.animation
+    }
+
+    /// Switches to another animation and starts it from the beginning.
+    pub fn set_animation(&mut self, animation: Animation) {
+        self.animation = animation;
+        self.restart();
+    }
+
+    pub fn play(&mut self) {
+        self.paused = false;
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn restart(&mut self) {
+        self.time = 0.0;
+    }
+
+    /// Seconds since the animation started, scaled by [AnimationPlayer::speed].
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    /// Always false for looping animations.
+    pub fn is_finished(&self) -> bool {
+        self.animation.is_finished_at(self.time)
+    }
+
+    /// The sheet frame currently shown, none for an empty animation.
+    pub fn frame(&self) -> Option<usize> {
+        self.animation.frame_at(self.time)
+    }
+
+    /// A sprite showing the current frame, see [SpriteSheet::sprite].
+    pub fn sprite(&self, sheet: &SpriteSheet) -> Option<Sprite> {
+        sheet.sprite(self.frame()?)
+    }
+}
@@ -0,0 +1,8 @@
+// 2d textured quads without the boilerplate:
+// - sprites are queued on a SpriteRenderer every frame, sorted by z-order and drawn with a built-in pipeline
+// - every sprite texture lives in the renderer's atlas
+// - a SpriteSheet slices a single texture into frames, which an AnimationPlayer steps through
+
+pub mod animation;
+pub mod pipeline;
+pub mod sheet;
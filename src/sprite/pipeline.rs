@@ -0,0 +1,346 @@
+use anyhow::Result;
+use nalgebra::{Matrix4, Rotation3, Scale3, Translation3, Vector2, Vector3, Vector4};
+use wgpu::{
+    vertex_attr_array, BufferUsages, Extent3d, FilterMode, SamplerBindingType, SamplerDescriptor,
+    ShaderStages, TextureSampleType, TextureUsages, TextureViewDimension,
+};
+
+use crate::{
+    bind::{BindEntry, BindEntryType, BindHandle},
+    camera::{Camera, CameraUniform},
+    geometry::Geometry,
+    instance::InstanceData,
+    material::BasicMaterial,
+    pipeline::{PipelineBuilder, PipelineHandle},
+    plain::Plain,
+    render::{AtlasHandle, Mesh, MeshHandle, Render, TextureHandle},
+    texture::{Texture, TextureFormat},
+};
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct SpriteVertex {
+    pub pos: [f32; 2],
+}
+
+unsafe impl Plain for SpriteVertex {}
+
+/// A unit quad from (0, 0) to (1, 1), sprites are scaled and moved into place by their transform.
+#[derive(Debug)]
+pub struct SpriteGeometry {
+    pub vertices: [SpriteVertex; 4],
+    pub indices: [u16; 6],
+}
+
+impl Default for SpriteGeometry {
+    fn default() -> Self {
+        Self {
+            vertices: [
+                SpriteVertex { pos: [0.0, 0.0] },
+                SpriteVertex { pos: [1.0, 0.0] },
+                SpriteVertex { pos: [0.0, 1.0] },
+                SpriteVertex { pos: [1.0, 1.0] },
+            ],
+            indices: [0, 1, 2, 2, 1, 3],
+        }
+    }
+}
+
+impl Geometry for SpriteGeometry {
+    fn contents(&self) -> &[u8] {
+        self.vertices.as_bytes()
+    }
+
+    fn length(&self) -> u32 {
+        self.vertices.len() as u32
+    }
+
+    fn indices(&self) -> Option<&[u8]> {
+        Some(self.indices.as_bytes())
+    }
+}
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct SpriteInstance {
+    pub transform: Matrix4<f32>,
+    pub color: [f32; 4],
+    /// Where the sprite is on its atlas page, [x, y, x, y] for the corners at the quad's (0, 1) and (1, 0).
+    pub atlas_coords: Vector4<f32>,
+    pub page: u32,
+    pub _padding: [u32; 3],
+}
+
+unsafe impl Plain for SpriteInstance {}
+
+impl InstanceData for SpriteInstance {}
+
+/// A textured quad.
+///
+/// The sprite's local space goes from (0, 0) at the bottom left of the texture to (1, 1) at the top right.
+/// The anchor is a point in that space which the sprite is positioned at and rotated around, e.g. (0.5, 0.5) for the centre.
+#[derive(Clone, Debug)]
+pub struct Sprite {
+    pub texture: TextureHandle,
+    /// The part of the texture to draw as [x, y, x, y] fractions of the texture, top left then bottom right.
+    pub region: [f32; 4],
+    pub position: Vector2<f32>,
+    pub size: Vector2<f32>,
+    pub color: [f32; 4],
+    pub anchor: Vector2<f32>,
+    pub flip_x: bool,
+    pub flip_y: bool,
+    /// Counter clockwise, in radians.
+    pub rotation: f32,
+    /// Sprites with a higher z-order are drawn on top, sprites sharing one are drawn in the order they were queued.
+    pub z_order: i32,
+}
+
+impl Sprite {
+    /// A `width` x `height` sprite showing the whole texture, anchored at its centre.
+    pub fn new(texture: TextureHandle, width: f32, height: f32) -> Self {
+        Self {
+            texture,
+            region: [0.0, 0.0, 1.0, 1.0],
+            position: Vector2::zeros(),
+            size: Vector2::new(width, height),
+            color: [1.0, 1.0, 1.0, 1.0],
+            anchor: Vector2::new(0.5, 0.5),
+            flip_x: false,
+            flip_y: false,
+            rotation: 0.0,
+            z_order: 0,
+        }
+    }
+
+    pub fn with_region(mut self, region: [f32; 4]) -> Self {
+        self.region = region;
+        self
+    }
+
+    pub fn with_position(mut self, x: f32, y: f32) -> Self {
+        self.position = Vector2::new(x, y);
+        self
+    }
+
+    pub fn with_size(mut self, width: f32, height: f32) -> Self {
+        self.size = Vector2::new(width, height);
+        self
+    }
+
+    /// Multiplied with the texture's colour.
+    pub fn with_color(mut self, color: [f32; 4]) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn with_anchor(mut self, x: f32, y: f32) -> Self {
+        self.anchor = Vector2::new(x, y);
+        self
+    }
+
+    pub fn with_flip(mut self, flip_x: bool, flip_y: bool) -> Self {
+        self.flip_x = flip_x;
+        self.flip_y = flip_y;
+        self
+    }
+
+    pub fn with_rotation(mut self, rotation: f32) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    pub fn with_z_order(mut self, z_order: i32) -> Self {
+        self.z_order = z_order;
+        self
+    }
+
+    pub fn transform(&self) -> Matrix4<f32> {
+        Translation3::new(self.position.x, self.position.y, 0.0).to_homogeneous()
+            * Rotation3::from_axis_angle(&Vector3::z_axis(), self.rotation).to_homogeneous()
+            * Scale3::new(self.size.x, self.size.y, 1.0).to_homogeneous()
+            * Translation3::new(-self.anchor.x, -self.anchor.y, 0.0).to_homogeneous()
+    }
+
+    pub fn instance(&self, render: &Render, atlas_handle: AtlasHandle) -> Result<SpriteInstance> {
+        let [x0, y0, x1, y1] = render.get_atlas_coords_for_texture(self.texture, atlas_handle)?;
+        let page = render.get_atlas_page_for_texture(self.texture, atlas_handle)?;
+
+        // the region is relative to the texture, the instance wants it relative to the atlas page
+        let (w, h) = (x1 - x0, y1 - y0);
+        let [rx0, ry0, rx1, ry1] = self.region;
+        let (mut left, mut right) = (x0 + rx0 * w, x0 + rx1 * w);
+        let (mut top, mut bottom) = (y0 + ry0 * h, y0 + ry1 * h);
+        if self.flip_x {
+            std::mem::swap(&mut left, &mut right);
+        }
+        if self.flip_y {
+            std::mem::swap(&mut top, &mut bottom);
+        }
+
+        Ok(SpriteInstance {
+            transform: self.transform(),
+            color: self.color,
+            atlas_coords: Vector4::new(left, top, right, bottom),
+            page,
+            _padding: [0; 3],
+        })
+    }
+}
+
+/// Draws [Sprite]s with a built-in pipeline, sprite textures are added to the renderer's atlas with [SpriteRenderer::add_texture].
+///
+/// ```ignore
+/// let mut sprites = SpriteRenderer::new(&mut render, TextureFormat::Rgba8UnormSrgb, FilterMode::Nearest);
+/// let texture = render.load_texture("player.png", TextureFormat::Rgba8UnormSrgb)?;
+/// let player = sprites.add_texture(&mut render, texture)?;
+///
+/// // every frame
+/// sprites.set_camera(&mut render, &camera);
+/// sprites.draw(Sprite::new(player, 32.0, 32.0).with_position(100.0, 50.0));
+/// sprites.flush(&mut render);
+/// render.draw()?;
+/// ```
+pub struct SpriteRenderer {
+    bind: BindHandle,
+    atlas_handle: AtlasHandle,
+    pipeline_handle: PipelineHandle,
+    mesh_handle: MeshHandle,
+    sprites: Vec<Sprite>,
+}
+
+impl SpriteRenderer {
+    /// `format` is the format of every sprite texture, use [TextureFormat::Rgba8UnormSrgb] for regular images.
+    /// Use [FilterMode::Nearest] for pixel art.
+    pub fn new(render: &mut Render, format: TextureFormat, filter: FilterMode) -> Self {
+        let bind = render.build_bind(&mut [
+            // camera
+            BindEntry {
+                visibility: ShaderStages::VERTEX,
+                ty: BindEntryType::BufferUniform {
+                    size: std::mem::size_of::<CameraUniform>() as u64,
+                    usages: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+                },
+                count: None,
+            },
+            // texture atlas, a layer per page
+            BindEntry {
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindEntryType::Texture {
+                    sample_type: TextureSampleType::Float { filterable: true },
+                    view_dimension: TextureViewDimension::D2Array,
+                    sample_count: 1,
+                    format: format.wgpu_format(),
+                    size: Extent3d {
+                        width: 1,
+                        height: 1,
+                        depth_or_array_layers: 1,
+                    },
+                    usage: TextureUsages::COPY_DST | TextureUsages::TEXTURE_BINDING,
+                    mip_level_count: 1,
+                },
+                count: None,
+            },
+            // sampler
+            BindEntry {
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindEntryType::Sampler {
+                    binding_type: SamplerBindingType::Filtering,
+                    descriptor: SamplerDescriptor {
+                        mag_filter: filter,
+                        min_filter: filter,
+                        ..Default::default()
+                    },
+                },
+                count: None,
+            },
+        ]);
+
+        // the binding was made for this format just above
+        let atlas_handle = render.register_atlas(bind, 1, format).unwrap();
+
+        let pipeline = PipelineBuilder::new()
+            .with_cull_mode(None)
+            // sprites are sorted by z-order instead
+            .with_depth_write(false)
+            .with_bind(bind)
+            .with_shader(include_str!("../shaders/sprite.wgsl"))
+            .with_vb::<SpriteVertex>(
+                wgpu::VertexStepMode::Vertex,
+                &vertex_attr_array![
+                    // position
+                    0 => Float32x2,
+                ],
+            )
+            .with_vb::<SpriteInstance>(
+                wgpu::VertexStepMode::Instance,
+                &vertex_attr_array![
+                    // transform
+                    1 => Float32x4,
+                    2 => Float32x4,
+                    3 => Float32x4,
+                    4 => Float32x4,
+                    // color
+                    5 => Float32x4,
+                    // atlas coords
+                    6 => Float32x4,
+                    // page
+                    7 => Uint32,
+                ],
+            )
+            .build(render);
+        let pipeline_handle = render.add_pipeline(pipeline);
+
+        let mesh_handle = render.add_mesh::<SpriteGeometry, SpriteInstance, BasicMaterial>(Mesh {
+            material: BasicMaterial {},
+            geometry: SpriteGeometry::default(),
+        });
+
+        Self {
+            bind,
+            atlas_handle,
+            pipeline_handle,
+            mesh_handle,
+            sprites: Vec::new(),
+        }
+    }
+
+    /// The bind holding the camera (binding 0), the atlas (binding 1) and its sampler (binding 2).
+    pub fn bind(&self) -> BindHandle {
+        self.bind
+    }
+
+    pub fn atlas_handle(&self) -> AtlasHandle {
+        self.atlas_handle
+    }
+
+    /// Adds a texture to the sprite atlas, it has to be in the format the renderer was created with.
+    pub fn add_texture(&self, render: &mut Render, texture: Texture) -> Result<TextureHandle> {
+        render.add_texture(texture, self.atlas_handle)
+    }
+
+    pub fn set_camera(&self, render: &mut Render, camera: &Camera) {
+        render.write_buffer(camera.uniform().as_bytes(), self.bind, 0);
+    }
+
+    /// Queues a sprite for the next [SpriteRenderer::flush].
+    pub fn draw(&mut self, sprite: Sprite) {
+        self.sprites.push(sprite);
+    }
+
+    /// Sorts the queued sprites by z-order and hands them to the renderer for the next [Render::draw].
+    /// Sprites whose texture isn't in the sprite atlas are skipped with a warning.
+    pub fn flush(&mut self, render: &mut Render) {
+        // stable, so sprites sharing a z-order stay in the order they were queued
+        self.sprites.sort_by_key(|sprite| sprite.z_order);
+
+        for sprite in self.sprites.drain(..) {
+            match sprite.instance(render, self.atlas_handle) {
+                Ok(instance) => {
+                    render.add_instance(self.mesh_handle, self.pipeline_handle, instance)
+                }
+                Err(err) => log::warn!("Skipping sprite: {}", err),
+            }
+        }
+    }
+}
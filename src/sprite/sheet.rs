@@ -0,0 +1,272 @@
+use std::{collections::HashMap, path::Path};
+
+use anyhow::{anyhow, Result};
+use serde_json::Value;
+
+use crate::{
+    render::{Render, TextureHandle},
+    sprite::{
+        animation::{Animation, Playback},
+        pipeline::Sprite,
+    },
+};
+
+/// A rect of a [SpriteSheet]'s texture, in pixels from the top left.
+#[derive(Clone, Debug)]
+pub struct Frame {
+    pub x: u32,
+    pub y: u32,
+    pub w: u32,
+    pub h: u32,
+    /// In seconds, Aseprite exports come with a duration per frame.
+    pub duration: Option<f32>,
+}
+
+/// A texture sliced into frames, e.g. the frames of an animation or the tiles of a tileset.
+pub struct SpriteSheet {
+    texture: TextureHandle,
+    width: u32,
+    height: u32,
+    frames: Vec<Frame>,
+    names: HashMap<String, usize>,
+    animations: HashMap<String, Animation>,
+}
+
+impl SpriteSheet {
+    /// Slices the texture into `columns` x `rows` equally sized frames, numbered left to right then top to bottom.
+    pub fn from_grid(
+        render: &Render,
+        texture: TextureHandle,
+        columns: u32,
+        rows: u32,
+    ) -> Result<Self> {
+        let (width, height) = texture_size(render, texture)?;
+        if columns == 0 || rows == 0 || columns > width || rows > height {
+            return Err(anyhow!(
+                "Can't slice a {}x{} texture into {}x{} frames",
+                width,
+                height,
+                columns,
+                rows
+            ));
+        }
+
+        let (w, h) = (width / columns, height / rows);
+        let frames = (0..rows)
+            .flat_map(|row| {
+                (0..columns).map(move |column| Frame {
+                    x: column * w,
+                    y: row * h,
+                    w,
+                    h,
+                    duration: None,
+                })
+            })
+            .collect();
+
+        Ok(Self {
+            texture,
+            width,
+            height,
+            frames,
+            names: HashMap::new(),
+            animations: HashMap::new(),
+        })
+    }
+
+    /// Reads the frames from a TexturePacker or Aseprite JSON export, with frames either as a hash or an array.
+    ///
+    /// Aseprite frame tags become animations, see [SpriteSheet::animation].
+    /// Trimmed frames are drawn at their trimmed size, rotated frames aren't supported.
+    pub fn from_json(render: &Render, texture: TextureHandle, json: &str) -> Result<Self> {
+        let (width, height) = texture_size(render, texture)?;
+        let root: Value = serde_json::from_str(json)?;
+
+        let entries: Vec<(Option<String>, &Value)> = match root.get("frames") {
+            Some(Value::Object(frames)) => frames
+                .iter()
+                .map(|(name, frame)| (Some(name.clone()), frame))
+                .collect(),
+            Some(Value::Array(frames)) => frames
+                .iter()
+                .map(|frame| {
+                    let name = frame.get("filename").and_then(Value::as_str);
+                    (name.map(String::from), frame)
+                })
+                .collect(),
+            _ => return Err(anyhow!("Sprite sheet JSON has no frames")),
+        };
+
+        let mut frames = Vec::with_capacity(entries.len());
+        let mut names = HashMap::new();
+        for (idx, (name, entry)) in entries.into_iter().enumerate() {
+            let label = name.clone().unwrap_or_else(|| idx.to_string());
+            if entry
+                .get("rotated")
+                .and_then(Value::as_bool)
+                .unwrap_or(false)
+            {
+                return Err(anyhow!(
+                    "Frame {} is rotated, rotated frames aren't supported",
+                    label
+                ));
+            }
+
+            let rect = entry
+                .get("frame")
+                .ok_or(anyhow!("Frame {} has no rect", label))?;
+            let field = |key: &str| {
+                rect.get(key)
+                    .and_then(Value::as_u64)
+                    .map(|value| value as u32)
+                    .ok_or(anyhow!("Frame {} is missing {}", label, key))
+            };
+            let frame = Frame {
+                x: field("x")?,
+                y: field("y")?,
+                w: field("w")?,
+                h: field("h")?,
+                duration: entry
+                    .get("duration")
+                    .and_then(Value::as_f64)
+                    .map(|ms| ms as f32 / 1000.0),
+            };
+            if frame.x + frame.w > width || frame.y + frame.h > height {
+                return Err(anyhow!(
+                    "Frame {} doesn't fit into the {}x{} texture",
+                    label,
+                    width,
+                    height
+                ));
+            }
+
+            frames.push(frame);
+            if let Some(name) = name {
+                names.insert(name, idx);
+            }
+        }
+
+        let mut sheet = Self {
+            texture,
+            width,
+            height,
+            frames,
+            names,
+            animations: HashMap::new(),
+        };
+
+        let tags = root
+            .get("meta")
+            .and_then(|meta| meta.get("frameTags"))
+            .and_then(Value::as_array);
+        for tag in tags.into_iter().flatten() {
+            let name = tag
+                .get("name")
+                .and_then(Value::as_str)
+                .ok_or(anyhow!("Frame tag has no name"))?;
+            let bound = |key: &str| {
+                tag.get(key)
+                    .and_then(Value::as_u64)
+                    .map(|value| value as usize)
+                    .filter(|value| *value < sheet.frames.len())
+                    .ok_or(anyhow!("Frame tag {} has no valid {} frame", name, key))
+            };
+            let (from, to) = (bound("from")?, bound("to")?);
+
+            let direction = tag
+                .get("direction")
+                .and_then(Value::as_str)
+                .unwrap_or("forward");
+            let mut frames = (from..=to).collect::<Vec<_>>();
+            if direction.contains("reverse") {
+                frames.reverse();
+            }
+            // the fallback only matters for hand written files, aseprite always sets a duration
+            let animation = sheet.animation_from(frames, 0.1);
+            let animation = if direction.starts_with("pingpong") {
+                animation.with_playback(Playback::PingPong)
+            } else {
+                animation
+            };
+            sheet.animations.insert(name.into(), animation);
+        }
+
+        Ok(sheet)
+    }
+
+    /// Reads a JSON export from disk, see [SpriteSheet::from_json].
+    pub fn load_json<P: AsRef<Path>>(
+        render: &Render,
+        texture: TextureHandle,
+        path: P,
+    ) -> Result<Self> {
+        let json = std::fs::read_to_string(path)?;
+        Self::from_json(render, texture, &json)
+    }
+
+    pub fn texture(&self) -> TextureHandle {
+        self.texture
+    }
+
+    pub fn frames(&self) -> &[Frame] {
+        &self.frames
+    }
+
+    pub fn frame(&self, idx: usize) -> Option<&Frame> {
+        self.frames.get(idx)
+    }
+
+    /// Looks up a frame by the name it has in a JSON export.
+    pub fn frame_index(&self, name: &str) -> Option<usize> {
+        self.names.get(name).copied()
+    }
+
+    /// A sprite showing a single frame at the frame's size in pixels.
+    pub fn sprite(&self, idx: usize) -> Option<Sprite> {
+        let frame = self.frames.get(idx)?;
+        let (width, height) = (self.width as f32, self.height as f32);
+        let region = [
+            frame.x as f32 / width,
+            frame.y as f32 / height,
+            (frame.x + frame.w) as f32 / width,
+            (frame.y + frame.h) as f32 / height,
+        ];
+        Some(Sprite::new(self.texture, frame.w as f32, frame.h as f32).with_region(region))
+    }
+
+    pub fn animation(&self, name: &str) -> Option<&Animation> {
+        self.animations.get(name)
+    }
+
+    pub fn add_animation(&mut self, name: &str, animation: Animation) {
+        self.animations.insert(name.into(), animation);
+    }
+
+    /// A looping animation over `frames`, using each frame's own duration or `frame_duration` when it doesn't have one.
+    pub fn animation_from(
+        &self,
+        frames: impl IntoIterator<Item = usize>,
+        frame_duration: f32,
+    ) -> Animation {
+        let frames = frames.into_iter().collect::<Vec<_>>();
+        let durations = frames
+            .iter()
+            .map(|idx| {
+                self.frames
+                    .get(*idx)
+                    .and_then(|frame| frame.duration)
+                    .unwrap_or(frame_duration)
+            })
+            .collect();
+        Animation {
+            frames,
+            durations,
+            playback: Playback::Loop,
+        }
+    }
+}
+
+fn texture_size(render: &Render, texture: TextureHandle) -> Result<(u32, u32)> {
+    let texture = render.get_texture(texture)?;
+    Ok((texture.width, texture.height))
+}
@@ -0,0 +1,371 @@
+// color glyphs are drawn as images from an rgba atlas instead of from a distance field
+// they come from bitmap strikes (CBDT/sbix), from COLR layers (outlines filled with palette colors)
+// or from a set of emoji pngs for fonts without any emoji at all
+// everything gets rasterized at the manager's px, so color glyphs don't stay sharp when magnified like sdf glyphs do
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Result};
+use fdsm::{
+    bezier::scanline::FillRule, generate::generate_sdf, render::correct_sign_sdf, shape::Shape,
+    transform::Transform,
+};
+use image::{imageops::FilterType, GrayImage, ImageFormat, RgbaImage};
+use nalgebra::{Affine2, Matrix3};
+use ttf_parser::{colr::Painter, Face, GlyphId, RasterImageFormat, RgbaColor};
+
+use crate::texture::TextureFormat;
+
+use super::{font_bitmap_manager::GlyphMetrics, shaping::ShapedGlyph};
+
+/// The format of the atlas color glyphs go into.
+pub const COLOR_GLYPH_FORMAT: TextureFormat = TextureFormat::Rgba8UnormSrgb;
+
+/// Emoji images for sequences of characters, e.g. a directory of twemoji or noto emoji pngs.
+#[derive(Debug, Default)]
+pub struct EmojiImages {
+    // keyed by the sequence without variation selectors
+    sequences: HashMap<String, u16>,
+    paths: Vec<PathBuf>,
+}
+
+impl EmojiImages {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads every png in `dir` named after the code points it's for, in hex.
+    /// Both twemoji (`1f469-200d-1f4bb.png`) and noto (`emoji_u1f469_200d_1f4bb.png`) names work.
+    pub fn load_dir<P: AsRef<Path>>(dir: P) -> Result<Self> {
+        let mut images = Self::new();
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("png") {
+                continue;
+            }
+            let Some(stem) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                continue;
+            };
+            let sequence: Option<String> = stem
+                .trim_start_matches("emoji_u")
+                .split(['-', '_'])
+                .map(|code| u32::from_str_radix(code, 16).ok().and_then(char::from_u32))
+                .collect();
+            // other pngs in the directory are skipped
+            if let Some(sequence) = sequence {
+                images.add(&sequence, path);
+            }
+        }
+        Ok(images)
+    }
+
+    /// Adds an image for a sequence of characters, e.g. `"👩‍💻"`.
+    pub fn add<P: Into<PathBuf>>(&mut self, sequence: &str, path: P) {
+        let idx = self.paths.len() as u16;
+        self.paths.push(path.into());
+        self.sequences.insert(normalise(sequence), idx);
+    }
+
+    /// The image for a sequence of characters, variation selectors don't matter.
+    pub fn get(&self, sequence: &str) -> Option<u16> {
+        self.sequences.get(&normalise(sequence)).copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.paths.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.paths.is_empty()
+    }
+
+    /// Loads an image and fits it into a `px` sized em square, centered on `center` (relative to the baseline).
+    pub(crate) fn rasterize(
+        &self,
+        image: u16,
+        px: f32,
+        center: f32,
+    ) -> Result<(GlyphMetrics, Vec<u8>)> {
+        let path = self
+            .paths
+            .get(image as usize)
+            .ok_or(anyhow!("No emoji image {}", image))?;
+        let image = image::open(path)?.into_rgba8();
+        let fit = px / image.width().max(image.height()) as f32;
+        let width = ((image.width() as f32 * fit).round() as u32).max(1);
+        let height = ((image.height() as f32 * fit).round() as u32).max(1);
+        let image = image::imageops::resize(&image, width, height, FilterType::Triangle);
+
+        let metrics = GlyphMetrics {
+            advance: px,
+            xmin: (px - width as f32) / 2.0,
+            ymin: (center - height as f32 / 2.0).round(),
+            width,
+            height,
+            padding: 0,
+            color: true,
+        };
+        Ok((metrics, image.into_raw()))
+    }
+}
+
+fn normalise(sequence: &str) -> String {
+    sequence.chars().filter(|c| *c != '\u{fe0f}').collect()
+}
+
+/// The glyph as an rgba image at `px` pixels per em, rows going down.
+/// None for glyphs that aren't in color, those are drawn from their outline.
+pub(crate) fn rasterize_color(
+    face: &Face,
+    glyph_id: GlyphId,
+    px: f32,
+) -> Result<Option<(GlyphMetrics, Vec<u8>)>> {
+    let scale = px / face.units_per_em() as f32;
+    let advance = face.glyph_hor_advance(glyph_id).unwrap_or(0) as f32 * scale;
+
+    if let Some(image) = face.glyph_raster_image(glyph_id, px.round() as u16) {
+        return rasterize_bitmap(image, px, advance);
+    }
+    if face.is_color_glyph(glyph_id) {
+        return rasterize_layers(face, glyph_id, scale, advance).map(Some);
+    }
+    Ok(None)
+}
+
+/// A bitmap strike's image (CBDT or sbix), scaled from the strike's size to `px`.
+fn rasterize_bitmap(
+    image: ttf_parser::RasterGlyphImage,
+    px: f32,
+    advance: f32,
+) -> Result<Option<(GlyphMetrics, Vec<u8>)>> {
+    let decoded = match image.format {
+        RasterImageFormat::PNG => {
+            image::load_from_memory_with_format(image.data, ImageFormat::Png)?.into_rgba8()
+        }
+        RasterImageFormat::BitmapPremulBgra32 => {
+            let pixels = image
+                .data
+                .chunks_exact(4)
+                .flat_map(|bgra| {
+                    let alpha = bgra[3];
+                    let unpremultiply = |c: u8| {
+                        if alpha == 0 {
+                            0
+                        } else {
+                            (c as u32 * 255 / alpha as u32).min(255) as u8
+                        }
+                    };
+                    [
+                        unpremultiply(bgra[2]),
+                        unpremultiply(bgra[1]),
+                        unpremultiply(bgra[0]),
+                        alpha,
+                    ]
+                })
+                .collect();
+            RgbaImage::from_raw(image.width as u32, image.height as u32, pixels)
+                .ok_or(anyhow!("Bitmap glyph data doesn't match its size"))?
+        }
+        // monochrome and grayscale strikes aren't in color
+        _ => return Ok(None),
+    };
+
+    let scale = px / image.pixels_per_em as f32;
+    let width = ((decoded.width() as f32 * scale).round() as u32).max(1);
+    let height = ((decoded.height() as f32 * scale).round() as u32).max(1);
+    let resized = image::imageops::resize(&decoded, width, height, FilterType::Triangle);
+
+    let metrics = GlyphMetrics {
+        advance,
+        xmin: (image.x as f32 * scale).round(),
+        ymin: (image.y as f32 * scale).round(),
+        width,
+        height,
+        padding: 0,
+        color: true,
+    };
+    Ok(Some((metrics, resized.into_raw())))
+}
+
+/// Collects the layers of a COLR glyph, the foreground color is left for the caller to pick.
+#[derive(Default)]
+struct Layers {
+    current: Option<GlyphId>,
+    layers: Vec<(GlyphId, Option<RgbaColor>)>,
+}
+
+impl Painter for Layers {
+    fn outline(&mut self, glyph_id: GlyphId) {
+        self.current = Some(glyph_id);
+    }
+
+    fn paint_foreground(&mut self) {
+        if let Some(glyph_id) = self.current.take() {
+            self.layers.push((glyph_id, None));
+        }
+    }
+
+    fn paint_color(&mut self, color: RgbaColor) {
+        if let Some(glyph_id) = self.current.take() {
+            self.layers.push((glyph_id, Some(color)));
+        }
+    }
+}
+
+/// A COLR glyph's layers filled with their palette colors (the first palette) and stacked bottom to top.
+/// Layers in the foreground color are black, the text's color can't reach into the image.
+fn rasterize_layers(
+    face: &Face,
+    glyph_id: GlyphId,
+    scale: f32,
+    advance: f32,
+) -> Result<(GlyphMetrics, Vec<u8>)> {
+    let mut layers = Layers::default();
+    face.paint_color_glyph(glyph_id, 0, &mut layers)
+        .ok_or(anyhow!("Couldn't paint color glyph {}", glyph_id.0))?;
+
+    let bounds = layers
+        .layers
+        .iter()
+        .filter_map(|(layer, _)| face.glyph_bounding_box(*layer))
+        .reduce(|a, b| ttf_parser::Rect {
+            x_min: a.x_min.min(b.x_min),
+            y_min: a.y_min.min(b.y_min),
+            x_max: a.x_max.max(b.x_max),
+            y_max: a.y_max.max(b.y_max),
+        });
+    let Some(bounds) = bounds else {
+        return Ok((
+            GlyphMetrics {
+                advance,
+                color: true,
+                ..Default::default()
+            },
+            Vec::new(),
+        ));
+    };
+
+    // a pixel of room around the layers for their anti-aliased edges
+    let left = (bounds.x_min as f32 * scale).floor() as i32 - 1;
+    let bottom = (bounds.y_min as f32 * scale).floor() as i32 - 1;
+    let right = (bounds.x_max as f32 * scale).ceil() as i32 + 1;
+    let top = (bounds.y_max as f32 * scale).ceil() as i32 + 1;
+    let width = (right - left) as u32;
+    let height = (top - bottom) as u32;
+
+    // straight alpha, rows going up like fdsm's
+    let mut pixels = vec![[0.0f32; 4]; (width * height) as usize];
+    for (layer, color) in layers.layers {
+        let color = color.map_or([0.0, 0.0, 0.0, 1.0], |color| {
+            [color.red, color.green, color.blue, color.alpha].map(|c| c as f32 / 255.0)
+        });
+
+        let mut shape = Shape::load_from_face(face, layer);
+        shape.transform(&Affine2::from_matrix_unchecked(Matrix3::new(
+            scale as f64,
+            0.0,
+            -left as f64,
+            0.0,
+            scale as f64,
+            -bottom as f64,
+            0.0,
+            0.0,
+            1.0,
+        )));
+        // a distance field with a range of one pixel is the layer's anti-aliased coverage
+        let prepared = shape.prepare();
+        let mut coverage = GrayImage::new(width, height);
+        generate_sdf(&prepared, 1.0, &mut coverage);
+        correct_sign_sdf(&mut coverage, &prepared, FillRule::Nonzero);
+
+        for (pixel, covered) in pixels.iter_mut().zip(coverage.as_raw()) {
+            let alpha = color[3] * *covered as f32 / 255.0;
+            let out_alpha = alpha + pixel[3] * (1.0 - alpha);
+            if out_alpha > 0.0 {
+                for channel in 0..3 {
+                    pixel[channel] = (color[channel] * alpha
+                        + pixel[channel] * pixel[3] * (1.0 - alpha))
+                        / out_alpha;
+                }
+            }
+            pixel[3] = out_alpha;
+        }
+    }
+
+    let row = width as usize;
+    let data = pixels
+        .chunks_exact(row)
+        .rev()
+        .flatten()
+        .flat_map(|pixel| pixel.map(|c| (c * 255.0).round() as u8))
+        .collect();
+
+    let metrics = GlyphMetrics {
+        advance,
+        xmin: left as f32,
+        ymin: bottom as f32,
+        width,
+        height,
+        padding: 0,
+        color: true,
+    };
+    Ok((metrics, data))
+}
+
+/// Replaces clusters with missing glyphs (0) in them with emoji images, as glyphs of face `face` whose id is the image.
+/// Emoji are an em wide.
+pub(crate) fn replace_missing_with_emoji(
+    glyphs: Vec<ShapedGlyph>,
+    text: &str,
+    images: &EmojiImages,
+    face: usize,
+    size: f32,
+) -> Vec<ShapedGlyph> {
+    let mut clusters: Vec<usize> = glyphs.iter().map(|glyph| glyph.cluster).collect();
+    clusters.sort_unstable();
+    clusters.dedup();
+    let cluster_end = |cluster: usize| {
+        clusters
+            .iter()
+            .copied()
+            .find(|other| *other > cluster)
+            .unwrap_or(text.len())
+    };
+
+    // a cluster's glyphs are next to each other, whichever the direction
+    let mut result = Vec::with_capacity(glyphs.len());
+    let mut idx = 0;
+    while idx < glyphs.len() {
+        let cluster = glyphs[idx].cluster;
+        let end = glyphs[idx..]
+            .iter()
+            .position(|glyph| glyph.cluster != cluster)
+            .map_or(glyphs.len(), |len| idx + len);
+        let group = &glyphs[idx..end];
+        idx = end;
+
+        // variation selectors etc. can still get (invisible) glyphs of their own
+        let image = group
+            .iter()
+            .any(|glyph| glyph.glyph_id == 0)
+            .then(|| images.get(&text[cluster..cluster_end(cluster)]))
+            .flatten();
+        match image {
+            Some(image) => result.push(ShapedGlyph {
+                face,
+                glyph_id: image,
+                cluster,
+                x_advance: size,
+                y_advance: 0.0,
+                x_offset: 0.0,
+                y_offset: 0.0,
+            }),
+            None => result.extend_from_slice(group),
+        }
+    }
+    result
+}
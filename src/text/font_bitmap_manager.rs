@@ -1,100 +1,519 @@
-use std::{
-    collections::HashMap,
-    fs::File,
-    io::{BufReader, Read},
-};
+use std::{cell::RefCell, collections::HashMap, fmt::Debug, ops::RangeInclusive};
 
 use anyhow::{anyhow, Result};
-use fontdue::{Font, FontSettings, Metrics};
+use fdsm::{
+    bezier::scanline::FillRule,
+    generate::{generate_msdf, generate_sdf},
+    render::{correct_sign_msdf, correct_sign_sdf},
+    shape::Shape,
+    transform::Transform,
+};
+use image::{GrayImage, RgbImage};
+use nalgebra::{Affine2, Matrix3};
+use ttf_parser::GlyphId;
 
 use crate::{
+    atlas::AtlasFull,
     render::{AtlasHandle, Render, TextureHandle},
-    texture::Texture,
+    texture::{Texture, TextureFormat},
+};
+
+use super::{
+    color_glyphs::{rasterize_color, replace_missing_with_emoji, EmojiImages, COLOR_GLYPH_FORMAT},
+    font_collection::{FontCollection, FontFace, FontStyle},
+    layout::{DecorationLine, FontMetrics, TextShaper},
+    shaping::{self, ShapedGlyph, ShapingFace},
 };
 
+/// Printable ascii, space to tilde.
+pub const ASCII: RangeInclusive<char> = ' '..='~';
+/// The printable half of latin-1, accented letters, currency signs etc.
+pub const LATIN_1: RangeInclusive<char> = '\u{a0}'..='\u{ff}';
+
+/// What gets stored in the atlas for every glyph.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum DistanceField {
+    /// A single channel signed distance field, corners get rounded off when magnified.
+    #[default]
+    Sdf,
+    /// A multi-channel signed distance field, keeps corners sharp. Needs an [TextureFormat::Rgba8Unorm] atlas.
+    Msdf,
+}
+
+impl DistanceField {
+    /// The format of the atlas the glyphs go into.
+    pub fn format(&self) -> TextureFormat {
+        match self {
+            DistanceField::Sdf => TextureFormat::R8Unorm,
+            DistanceField::Msdf => TextureFormat::Rgba8Unorm,
+        }
+    }
+}
+
+/// Where a glyph's bitmap goes relative to the pen position, in pixels at the manager's px.
+/// Divide by [FontBitmapManager::px] and multiply by the text size to get the size on screen.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct GlyphMetrics {
+    /// How far to move the pen after the glyph.
+    pub advance: f32,
+    /// Left edge of the bitmap, padding included.
+    pub xmin: f32,
+    /// Bottom edge of the bitmap relative to the baseline, padding included.
+    pub ymin: f32,
+    /// Bitmap width, padding included. Zero for glyphs with nothing to draw, e.g. a space.
+    pub width: u32,
+    /// Bitmap height, padding included.
+    pub height: u32,
+    /// Pixels of distance field around the outline on every side.
+    pub padding: u32,
+    /// Whether the glyph is an image in the color atlas rather than a distance field, see [FontBitmapManager::with_color_atlas].
+    pub color: bool,
+}
+
+impl GlyphMetrics {
+    pub fn is_empty(&self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Glyph {
+    // glyphs without an outline don't take up any space in the atlas
+    texture: Option<TextureHandle>,
+    metrics: GlyphMetrics,
+    last_used: u64,
+}
+
+#[derive(Debug, Default)]
+struct GlyphCache {
+    // keyed by (face, glyph id) rather than char, shaping can turn the same char into different glyphs
+    glyphs: HashMap<(usize, u16), Glyph>,
+    // bumped on every use, the glyph with the lowest last_used is the least recently used
+    clock: u64,
+}
+
+impl GlyphCache {
+    fn touch(&mut self, face: usize, glyph_id: u16) -> Option<Glyph> {
+        self.clock += 1;
+        let clock = self.clock;
+        self.glyphs.get_mut(&(face, glyph_id)).map(|glyph| {
+            glyph.last_used = clock;
+            *glyph
+        })
+    }
+}
+
 // one of these needs to exist for each font used, at each px, each font weight, etc.
 // that's fine - this is just a lightweight mapping of glyph -> texturehandle
-// the bigger concern is keeping so many textures loaded on the renderer
-// but if that becomes an issue, we can solve it
-#[derive(Clone, Debug)]
+// glyphs are rasterized into the atlas the first time they're needed
+// once the atlas is full the least recently used glyphs make room for new ones
 pub struct FontBitmapManager {
-    map: HashMap<char, (TextureHandle, Metrics)>,
+    // the primary face followed by its fallbacks
+    // faces borrow from the font data, so they get parsed again whenever they're needed
+    faces: Vec<FontFace>,
+    // shaping happens for every line of every text, so the faces are only parsed for it once
+    shaping_faces: Vec<ShapingFace>,
+    // text render objects share the manager, so the cache has to be able to change behind an Rc
+    cache: RefCell<GlyphCache>,
     pub atlas_handle: AtlasHandle,
+    /// Where color glyphs go, they're drawn from their outline without one.
+    pub color_atlas_handle: Option<AtlasHandle>,
+    // images for emoji none of the faces have, they're glyphs of a face after the last one
+    emoji: Option<EmojiImages>,
+    /// The size glyphs are rasterized at, in pixels per em.
     pub px: f32,
+    /// How far the distance field reaches past the outline, in pixels at [FontBitmapManager::px].
+    pub spread: f32,
+    pub distance_field: DistanceField,
+}
+
+impl Debug for FontBitmapManager {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FontBitmapManager")
+            .field("faces", &self.faces)
+            .field("glyphs", &self.cache.borrow().glyphs.len())
+            .field("atlas_handle", &self.atlas_handle)
+            .field("color_atlas_handle", &self.color_atlas_handle)
+            .field("emoji", &self.emoji.as_ref().map(EmojiImages::len))
+            .field("px", &self.px)
+            .field("spread", &self.spread)
+            .field("distance_field", &self.distance_field)
+            .finish()
+    }
 }
 
 impl FontBitmapManager {
-    pub fn new(
-        render: &mut Render,
-        font_path: &str,
+    /// Loads the font, glyphs are rasterized when they're first used or with [FontBitmapManager::preload].
+    ///
+    /// Glyphs are rasterized at `px` pixels per em, text drawn at other sizes scales the same glyphs.
+    ///
+    /// Uses the first face of a font collection (.ttc), see [FontBitmapManager::from_collection] to pick a face and fall back to other fonts.
+    pub fn new(font_path: &str, px: f32, atlas_handle: AtlasHandle) -> Result<Self> {
+        let mut collection = FontCollection::new();
+        let faces = collection.load(font_path)?;
+        Self::from_faces(faces.into_iter().take(1).collect(), px, atlas_handle)
+    }
+
+    /// Uses the face of `family` closest to `weight` and `style`, glyphs it doesn't have come from the collection's fallbacks.
+    pub fn from_collection(
+        collection: &FontCollection,
+        family: &str,
+        weight: u16,
+        style: FontStyle,
         px: f32,
         atlas_handle: AtlasHandle,
     ) -> Result<Self> {
-        let file = File::open(font_path)?;
-        let mut reader = BufReader::new(file);
-        let mut buf = Vec::new();
-        let _ = reader.read_to_end(&mut buf)?;
-        let font = Font::from_bytes(buf, FontSettings::default()).map_err(|err| anyhow!(err))?;
-
-        let map = 
-        // font
-            // .chars()
-            [('h', 0), ('e', 0), ('l', 0), ('o', 0), ('w', 0), ('r', 0), ('d', 0), (' ', 0)]
-            .iter()
-            .map(|(c, _)| {
-                let (metrics, bitmap) = font.rasterize(*c, px);
-                // println!("{metrics:?}, {c}");
-
-                let sdf_bitmap = msdf::sdf(
-                    &msdf::bitmap::Bitmap {
-                        data: bitmap,
-                        dimensions: (metrics.width as u32, metrics.height as u32),
-                    },
-                    (64, 64),
-                    20,
-                );
+        Self::from_faces(
+            collection.fallback_chain(family, weight, style)?,
+            px,
+            atlas_handle,
+        )
+    }
 
-                let texture = Texture {
-                    data: sdf_bitmap
-                        .into_iter()
-                        // .flat_map(|val| val.to_le_bytes())
-                        .map(|val| (val * 255.0).floor() as u8)
-                        .collect(),
-                    width: 64,
-                    height: 64,
-                    format: crate::texture::TextureFormat::R8Unorm,
-                };
-                let texture_handle = render.add_texture(texture, atlas_handle)?;
-                anyhow::Ok((*c, (texture_handle, metrics)))
-            })
-            .collect::<Result<HashMap<char, (TextureHandle, Metrics)>>>()?;
+    /// Uses the first face, falling back to the next one whenever a face is missing a glyph.
+    pub fn from_faces(faces: Vec<FontFace>, px: f32, atlas_handle: AtlasHandle) -> Result<Self> {
+        if faces.is_empty() {
+            return Err(anyhow!("A font bitmap manager needs at least one face"));
+        }
+
+        let shaping_faces = faces
+            .iter()
+            .map(|face| ShapingFace::parse(face.data.clone(), face.index))
+            .collect::<Result<_>>()?;
 
         Ok(Self {
-            map,
+            faces,
+            shaping_faces,
+            cache: RefCell::new(GlyphCache::default()),
             atlas_handle,
+            color_atlas_handle: None,
+            emoji: None,
             px,
+            spread: px / 8.0,
+            distance_field: DistanceField::Sdf,
         })
     }
 
-    pub fn get_metric(&self, character: char) -> Result<Metrics> {
-        self.map
-            .get(&character)
+    /// How far the distance field reaches past the outline, in pixels at [FontBitmapManager::px]. Defaults to an eighth of px.
+    /// Glyphs are padded by this much, a bigger spread leaves more room for outlines and glows but takes up more of the atlas.
+    pub fn with_spread(mut self, spread: f32) -> Self {
+        self.spread = spread.max(1.0);
+        self
+    }
+
+    /// Defaults to [DistanceField::Sdf], the atlas has to be in [DistanceField::format].
+    pub fn with_distance_field(mut self, distance_field: DistanceField) -> Self {
+        self.distance_field = distance_field;
+        self
+    }
+
+    /// An atlas in [COLOR_GLYPH_FORMAT] for color glyphs (emoji etc.) from COLR, CBDT and sbix fonts, and for emoji images.
+    /// Register it at [crate::text::pipeline::COLOR_ATLAS_BINDING] of the text pipeline's bind.
+    pub fn with_color_atlas(mut self, color_atlas_handle: AtlasHandle) -> Self {
+        self.color_atlas_handle = Some(color_atlas_handle);
+        self
+    }
+
+    /// Images for emoji that none of the faces have, only used with a color atlas.
+    pub fn with_emoji_images(mut self, emoji: EmojiImages) -> Self {
+        self.emoji = Some(emoji);
+        self
+    }
+
+    // emoji images are glyphs of a face after the fallbacks
+    fn emoji_face(&self) -> Option<(usize, &EmojiImages)> {
+        self.color_atlas_handle?;
+        self.emoji.as_ref().map(|emoji| (self.faces.len(), emoji))
+    }
+
+    /// The primary face followed by its fallbacks.
+    pub fn faces(&self) -> &[FontFace] {
+        &self.faces
+    }
+
+    /// The first face with a glyph for `character` and the glyph, without any shaping.
+    /// The primary face's missing glyph (0) if none of the faces have one.
+    pub fn lookup_glyph(&self, character: char) -> Result<(usize, u16)> {
+        for (idx, face) in self.faces.iter().enumerate() {
+            if let Some(glyph_id) = face.parse()?.glyph_index(character) {
+                return Ok((idx, glyph_id.0));
+            }
+        }
+        if let Some((face, emoji)) = self.emoji_face() {
+            if let Some(image) = emoji.get(character.encode_utf8(&mut [0; 4])) {
+                return Ok((face, image));
+            }
+        }
+        Ok((0, 0))
+    }
+
+    /// Rasterizes glyphs ahead of time, e.g. `manager.preload(render, ASCII.chain(LATIN_1))` or `manager.preload(render, "äöü".chars())`.
+    ///
+    /// Only the characters' default glyphs are loaded, ligatures and contextual forms (e.g. in arabic) are loaded when they're first shaped.
+    pub fn preload(
+        &self,
+        render: &mut Render,
+        characters: impl IntoIterator<Item = char>,
+    ) -> Result<()> {
+        for character in characters {
+            let (face, glyph_id) = self.lookup_glyph(character)?;
+            self.load_glyph(render, face, glyph_id)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the glyph's metrics, rasterizing it into the atlas if it isn't there yet.
+    ///
+    /// When the atlas is full the least recently used glyphs are evicted until the new glyph fits.
+    /// `face` is the glyph's face in [FontBitmapManager::faces], see [ShapedGlyph::face].
+    pub fn load_glyph(
+        &self,
+        render: &mut Render,
+        face: usize,
+        glyph_id: u16,
+    ) -> Result<GlyphMetrics> {
+        if let Some(glyph) = self.cache.borrow_mut().touch(face, glyph_id) {
+            return Ok(glyph.metrics);
+        }
+
+        let (metrics, data) = self.rasterize(face, glyph_id)?;
+        let (atlas_handle, format) = match self.color_atlas_handle {
+            Some(color_atlas_handle) if metrics.color => (color_atlas_handle, COLOR_GLYPH_FORMAT),
+            _ => (self.atlas_handle, self.distance_field.format()),
+        };
+        let texture = if metrics.is_empty() {
+            None
+        } else {
+            Some(loop {
+                let texture = Texture {
+                    data: data.clone(),
+                    width: metrics.width,
+                    height: metrics.height,
+                    format,
+                };
+                match render.add_texture(texture, atlas_handle) {
+                    Ok(texture) => break texture,
+                    Err(err) if err.is::<AtlasFull>() => {
+                        if !self.evict(render, metrics.color)? {
+                            return Err(anyhow!(
+                                "Couldn't fit glyph {} of face {} into the glyph atlas: {}",
+                                glyph_id,
+                                face,
+                                err
+                            ));
+                        }
+                    }
+                    Err(err) => return Err(err),
+                }
+            })
+        };
+
+        let mut cache = self.cache.borrow_mut();
+        cache.clock += 1;
+        let last_used = cache.clock;
+        cache.glyphs.insert(
+            (face, glyph_id),
+            Glyph {
+                texture,
+                metrics,
+                last_used,
+            },
+        );
+        Ok(metrics)
+    }
+
+    /// Removes the least recently used glyph from the atlas (the color atlas if `color`), returns false if there aren't any glyphs left.
+    fn evict(&self, render: &mut Render, color: bool) -> Result<bool> {
+        let mut cache = self.cache.borrow_mut();
+        let Some(key) = cache
+            .glyphs
+            .iter()
+            .filter(|(_, glyph)| glyph.texture.is_some() && glyph.metrics.color == color)
+            .min_by_key(|(_, glyph)| glyph.last_used)
+            .map(|(key, _)| *key)
+        else {
+            return Ok(false);
+        };
+
+        let glyph = cache.glyphs.remove(&key).unwrap();
+        if let Some(texture) = glyph.texture {
+            render.remove_texture(texture)?;
+        }
+        log::debug!(
+            "Evicted glyph {} of face {} from the glyph atlas",
+            key.1,
+            key.0
+        );
+        Ok(true)
+    }
+
+    /// The glyph's metrics and distance field (or image for color glyphs), the data is empty if there's nothing to draw.
+    fn rasterize(&self, face: usize, glyph_id: u16) -> Result<(GlyphMetrics, Vec<u8>)> {
+        if let Some((emoji_face, emoji)) = self.emoji_face() {
+            if face == emoji_face {
+                let metrics = self.metrics(self.px);
+                return emoji.rasterize(
+                    glyph_id,
+                    self.px,
+                    (metrics.ascent + metrics.descent) / 2.0,
+                );
+            }
+        }
+
+        let face = self
+            .faces
+            .get(face)
+            .ok_or(anyhow!("No face {} in the font bitmap manager", face))?
+            .parse()?;
+        let glyph_id = GlyphId(glyph_id);
+        if self.color_atlas_handle.is_some() {
+            if let Some(color) = rasterize_color(&face, glyph_id, self.px)? {
+                return Ok(color);
+            }
+        }
+
+        let scale = self.px as f64 / face.units_per_em() as f64;
+        let advance = face.glyph_hor_advance(glyph_id).unwrap_or(0) as f64 * scale;
+
+        // whitespace etc. has nothing to draw, only an advance
+        let Some(bounds) = face.glyph_bounding_box(glyph_id) else {
+            return Ok((
+                GlyphMetrics {
+                    advance: advance as f32,
+                    ..Default::default()
+                },
+                Vec::new(),
+            ));
+        };
+
+        // the box is snapped to whole pixels so that the quad lines up with the texels,
+        // the outline keeps its sub-pixel position inside of it
+        let padding = self.spread.ceil() as i32;
+        let left = (bounds.x_min as f64 * scale).floor() as i32 - padding;
+        let bottom = (bounds.y_min as f64 * scale).floor() as i32 - padding;
+        let right = (bounds.x_max as f64 * scale).ceil() as i32 + padding;
+        let top = (bounds.y_max as f64 * scale).ceil() as i32 + padding;
+        let width = (right - left) as u32;
+        let height = (top - bottom) as u32;
+
+        let mut shape = Shape::load_from_face(&face, glyph_id);
+        shape.transform(&Affine2::from_matrix_unchecked(Matrix3::new(
+            scale,
+            0.0,
+            -left as f64,
+            0.0,
+            scale,
+            -bottom as f64,
+            0.0,
+            0.0,
+            1.0,
+        )));
+
+        // fdsm's range covers both sides of the edge
+        let range = self.spread as f64 * 2.0;
+        let data: Vec<u8> = match self.distance_field {
+            DistanceField::Sdf => {
+                let prepared = shape.prepare();
+                let mut image = GrayImage::new(width, height);
+                generate_sdf(&prepared, range, &mut image);
+                correct_sign_sdf(&mut image, &prepared, FillRule::Nonzero);
+                image.into_raw()
+            }
+            DistanceField::Msdf => {
+                let prepared = Shape::edge_coloring_simple(shape, 0.03, 0).prepare();
+                let mut image = RgbImage::new(width, height);
+                generate_msdf(&prepared, range, &mut image);
+                correct_sign_msdf(&mut image, &prepared, FillRule::Nonzero);
+                image
+                    .pixels()
+                    .flat_map(|pixel| [pixel[0], pixel[1], pixel[2], 255])
+                    .collect()
+            }
+        };
+
+        // fdsm's y points up, texture rows go down
+        let row = (width * self.distance_field.format().pixel_size()) as usize;
+        let data = data.chunks_exact(row).rev().flatten().copied().collect();
+
+        let metrics = GlyphMetrics {
+            advance: advance as f32,
+            xmin: left as f32,
+            ymin: bottom as f32,
+            width,
+            height,
+            padding: padding as u32,
+            color: false,
+        };
+        Ok((metrics, data))
+    }
+
+    /// Whether the glyph is currently in the atlas.
+    pub fn is_loaded(&self, face: usize, glyph_id: u16) -> bool {
+        self.cache.borrow().glyphs.contains_key(&(face, glyph_id))
+    }
+
+    /// The metrics of a glyph that's in the atlas, see [FontBitmapManager::load_glyph] for glyphs that might not be.
+    pub fn get_metric(&self, face: usize, glyph_id: u16) -> Result<GlyphMetrics> {
+        self.cache
+            .borrow()
+            .glyphs
+            .get(&(face, glyph_id))
             .ok_or(anyhow!(
-                "Couldn't find metric for character '{}'",
-                character
+                "Couldn't find metric for glyph {} of face {}",
+                glyph_id,
+                face
             ))
-            .copied()
-            .map(|inner| inner.1)
+            .map(|glyph| glyph.metrics)
     }
 
-    pub fn get_texture(&self, character: char) -> Result<TextureHandle> {
-        self.map
-            .get(&character)
+    /// The atlas a glyph that's been loaded is in, the color atlas for color glyphs.
+    pub fn get_atlas(&self, face: usize, glyph_id: u16) -> Result<AtlasHandle> {
+        let metrics = self.get_metric(face, glyph_id)?;
+        match self.color_atlas_handle {
+            Some(color_atlas_handle) if metrics.color => Ok(color_atlas_handle),
+            _ => Ok(self.atlas_handle),
+        }
+    }
+
+    /// The texture of a glyph that's in the atlas, counts as a use of the glyph.
+    /// Glyphs with nothing to draw don't have a texture, see [GlyphMetrics::is_empty].
+    pub fn get_texture(&self, face: usize, glyph_id: u16) -> Result<TextureHandle> {
+        self.cache
+            .borrow_mut()
+            .touch(face, glyph_id)
+            .and_then(|glyph| glyph.texture)
             .ok_or(anyhow!(
-                "Couldn't find texture for character '{}'",
-                character
+                "Couldn't find texture for glyph {} of face {}",
+                glyph_id,
+                face
             ))
-            .copied()
-            .map(|inner| inner.0)
+    }
+}
+
+impl TextShaper for FontBitmapManager {
+    fn shape(&self, text: &str, size: f32) -> Result<Vec<ShapedGlyph>> {
+        let glyphs = shaping::shape(&self.shaping_faces, size, text);
+        Ok(match self.emoji_face() {
+            Some((face, emoji)) => replace_missing_with_emoji(glyphs, text, emoji, face, size),
+            None => glyphs,
+        })
+    }
+
+    fn metrics(&self, size: f32) -> FontMetrics {
+        let face = self.shaping_faces[0].borrow_dependent();
+        let scale = size / face.units_per_em() as f32;
+        FontMetrics {
+            ascent: face.ascender() as f32 * scale,
+            descent: face.descender() as f32 * scale,
+            line_gap: face.line_gap() as f32 * scale,
+            underline: face.underline_metrics().map(|line| DecorationLine {
+                position: line.position as f32 * scale,
+                thickness: line.thickness as f32 * scale,
+            }),
+            strikeout: face.strikeout_metrics().map(|line| DecorationLine {
+                position: line.position as f32 * scale,
+                thickness: line.thickness as f32 * scale,
+            }),
+        }
     }
 }
@@ -0,0 +1,219 @@
+// fonts are looked up by family, weight and style like css does it
+// a collection holds every face of every font file that was loaded, .ttc files contain several faces
+// when a face doesn't have a glyph the next face in the fallback chain gets a go, e.g. latin -> cjk -> emoji
+
+use std::{fs::File, io::Read, rc::Rc};
+
+use anyhow::{anyhow, Result};
+use ttf_parser::{name_id, Face};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum FontStyle {
+    #[default]
+    Normal,
+    Italic,
+    /// A slanted normal face, italics are used for it if there isn't one and the other way around.
+    Oblique,
+}
+
+impl From<ttf_parser::Style> for FontStyle {
+    fn from(style: ttf_parser::Style) -> Self {
+        match style {
+            ttf_parser::Style::Normal => FontStyle::Normal,
+            ttf_parser::Style::Italic => FontStyle::Italic,
+            ttf_parser::Style::Oblique => FontStyle::Oblique,
+        }
+    }
+}
+
+/// Common weights, any value from 1 to 1000 works.
+pub mod weight {
+    pub const THIN: u16 = 100;
+    pub const LIGHT: u16 = 300;
+    pub const NORMAL: u16 = 400;
+    pub const MEDIUM: u16 = 500;
+    pub const BOLD: u16 = 700;
+    pub const BLACK: u16 = 900;
+}
+
+/// A single face from a font file, cheap to clone since the file's data is shared.
+#[derive(Clone)]
+pub struct FontFace {
+    pub data: Rc<[u8]>,
+    /// The face's index in a font collection (.ttc), 0 for plain font files.
+    pub index: u32,
+    pub family: String,
+    pub weight: u16,
+    pub style: FontStyle,
+}
+
+impl std::fmt::Debug for FontFace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FontFace")
+            .field("index", &self.index)
+            .field("family", &self.family)
+            .field("weight", &self.weight)
+            .field("style", &self.style)
+            .finish()
+    }
+}
+
+impl FontFace {
+    /// Reads the face's family, weight and style from its tables.
+    pub fn new(data: Rc<[u8]>, index: u32) -> Result<Self> {
+        let face = Face::parse(&data, index)?;
+        let family = family_name(&face).ok_or(anyhow!("Font face {} has no family name", index))?;
+        let weight = face.weight().to_number();
+        let style = face.style().into();
+        Ok(Self {
+            data,
+            index,
+            family,
+            weight,
+            style,
+        })
+    }
+
+    pub fn parse(&self) -> Result<Face<'_>> {
+        Ok(Face::parse(&self.data, self.index)?)
+    }
+
+    /// Whether the face has a glyph for `character`.
+    pub fn has_glyph(&self, character: char) -> bool {
+        self.parse()
+            .is_ok_and(|face| face.glyph_index(character).is_some())
+    }
+}
+
+/// The typographic family groups all weights under one name, older fonts only have the plain family ("Roboto Bold" etc).
+fn family_name(face: &Face) -> Option<String> {
+    [name_id::TYPOGRAPHIC_FAMILY, name_id::FAMILY]
+        .into_iter()
+        .find_map(|id| {
+            face.names()
+                .into_iter()
+                .filter(|name| name.name_id == id && name.is_unicode())
+                .find_map(|name| name.to_string())
+        })
+}
+
+/// All the faces of the fonts that were loaded, see [FontCollection::resolve].
+#[derive(Debug, Default)]
+pub struct FontCollection {
+    faces: Vec<FontFace>,
+    fallbacks: Vec<String>,
+}
+
+impl FontCollection {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Families to try, in order, when a face is missing a glyph, e.g. `vec!["Noto Sans CJK JP".into(), "Noto Color Emoji".into()]`.
+    pub fn with_fallbacks(mut self, fallbacks: Vec<String>) -> Self {
+        self.fallbacks = fallbacks;
+        self
+    }
+
+    /// Loads every face in a font file (.ttf, .otf or a .ttc collection).
+    pub fn load(&mut self, path: &str) -> Result<Vec<FontFace>> {
+        let mut file = File::open(path)?;
+        let mut data = Vec::new();
+        file.read_to_end(&mut data)?;
+        self.add_font_data(data)
+    }
+
+    /// Adds every face in a font file that's already in memory.
+    pub fn add_font_data(&mut self, data: Vec<u8>) -> Result<Vec<FontFace>> {
+        let data: Rc<[u8]> = data.into();
+        let count = ttf_parser::fonts_in_collection(&data).unwrap_or(1);
+        let faces = (0..count)
+            .map(|index| FontFace::new(data.clone(), index))
+            .collect::<Result<Vec<_>>>()?;
+        self.faces.extend(faces.iter().cloned());
+        Ok(faces)
+    }
+
+    pub fn faces(&self) -> &[FontFace] {
+        &self.faces
+    }
+
+    /// The names of the families that were loaded, without duplicates.
+    pub fn families(&self) -> Vec<&str> {
+        let mut families: Vec<&str> = Vec::new();
+        for face in &self.faces {
+            if !families.contains(&face.family.as_str()) {
+                families.push(&face.family);
+            }
+        }
+        families
+    }
+
+    /// The face of `family` closest to `weight` and `style`, family names are case insensitive.
+    ///
+    /// Matches the way css picks faces: the style is matched first (italic and oblique stand in for each other, then normal),
+    /// then the closest weight, preferring lighter faces for weights below 400, heavier faces above 500 and 400 to 500 in between.
+    pub fn resolve(&self, family: &str, weight: u16, style: FontStyle) -> Option<&FontFace> {
+        self.faces
+            .iter()
+            .filter(|face| face.family.eq_ignore_ascii_case(family))
+            .min_by_key(|face| {
+                (
+                    style_distance(style, face.style),
+                    weight_distance(weight, face.weight),
+                )
+            })
+    }
+
+    /// The resolved face followed by the fallback families' closest faces, missing fallbacks are skipped.
+    pub fn fallback_chain(
+        &self,
+        family: &str,
+        weight: u16,
+        style: FontStyle,
+    ) -> Result<Vec<FontFace>> {
+        let primary = self
+            .resolve(family, weight, style)
+            .ok_or(anyhow!("No font loaded for family '{}'", family))?;
+
+        let mut chain = vec![primary.clone()];
+        for fallback in &self.fallbacks {
+            let Some(face) = self.resolve(fallback, weight, style) else {
+                log::warn!("Fallback font family '{}' isn't loaded", fallback);
+                continue;
+            };
+            let duplicate = chain
+                .iter()
+                .any(|other| Rc::ptr_eq(&other.data, &face.data) && other.index == face.index);
+            if !duplicate {
+                chain.push(face.clone());
+            }
+        }
+        Ok(chain)
+    }
+}
+
+fn style_distance(wanted: FontStyle, style: FontStyle) -> u8 {
+    use FontStyle::*;
+    match (wanted, style) {
+        _ if wanted == style => 0,
+        (Italic, Oblique) | (Oblique, Italic) => 1,
+        (_, Normal) => 2,
+        _ => 3,
+    }
+}
+
+/// Lower is better, the first element picks the side of the wanted weight to search, the second the closest weight on it.
+pub(crate) fn weight_distance(wanted: u16, weight: u16) -> (u8, u16) {
+    let lighter = weight <= wanted;
+    let heavier = weight >= wanted;
+    match wanted {
+        400..=500 if heavier && weight <= 500 => (0, weight - wanted),
+        400..=500 if lighter => (1, wanted - weight),
+        400..=500 => (2, weight - wanted),
+        ..=399 if lighter => (0, wanted - weight),
+        ..=399 => (1, weight - wanted),
+        _ if heavier => (0, weight - wanted),
+        _ => (1, wanted - weight),
+    }
+}
@@ -0,0 +1,760 @@
+// lays text out over multiple lines: breaks it into lines, aligns them and positions every glyph
+// the whole text is shaped once to find out how wide each part of it is, lines are broken on those widths
+// and then every line is shaped again on its own so that right to left runs get ordered within the line
+// rich text is made of spans with their own font and size, lines get as tall as their tallest span
+// coordinates are in pixels with y up, (0, 0) is the top left corner of the layout box and lines go down from there
+
+use std::ops::Range;
+
+use anyhow::{anyhow, Result};
+use unicode_bidi::BidiInfo;
+use unicode_linebreak::{linebreaks, BreakOpportunity};
+
+use super::shaping::ShapedGlyph;
+
+/// Added to lines that got cut short, three dots are used instead if the font doesn't have it.
+pub const ELLIPSIS: &str = "…";
+const ELLIPSIS_FALLBACK: &str = "...";
+
+/// Vertical metrics of a font at some size, in pixels with y up.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FontMetrics {
+    /// How far the font reaches above the baseline.
+    pub ascent: f32,
+    /// How far the font reaches below the baseline, negative.
+    pub descent: f32,
+    /// Extra space the font wants between lines.
+    pub line_gap: f32,
+    /// Where the font wants underlines, if it says.
+    pub underline: Option<DecorationLine>,
+    /// Where the font wants strikethroughs, if it says.
+    pub strikeout: Option<DecorationLine>,
+}
+
+/// A line drawn along the text, in pixels with y up.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DecorationLine {
+    /// The top of the line, relative to the baseline.
+    pub position: f32,
+    pub thickness: f32,
+}
+
+impl FontMetrics {
+    /// The distance between two baselines the font asks for.
+    pub fn line_height(&self) -> f32 {
+        self.ascent - self.descent + self.line_gap
+    }
+}
+
+/// Turns text into glyphs for [layout], e.g. a [crate::text::font_bitmap_manager::FontBitmapManager].
+pub trait TextShaper {
+    /// Glyphs in visual order, positioned in pixels at `size` pixels per em.
+    fn shape(&self, text: &str, size: f32) -> Result<Vec<ShapedGlyph>>;
+
+    /// The font's vertical metrics at `size` pixels per em.
+    fn metrics(&self, size: f32) -> FontMetrics;
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Wrap {
+    /// Lines only break at newlines.
+    None,
+    /// Lines break between words, words too long for a line of their own are broken between characters.
+    #[default]
+    Word,
+    /// Lines break between any two characters.
+    Character,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Align {
+    #[default]
+    Left,
+    Center,
+    Right,
+    /// Stretches the spaces of wrapped lines to fill the max width, the last line of a paragraph stays left aligned.
+    Justify,
+}
+
+/// Where the lines go within [LayoutOptions::with_max_height].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum VerticalAlign {
+    #[default]
+    Top,
+    Middle,
+    Bottom,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LineHeight {
+    /// A multiple of the font's line height.
+    Relative(f32),
+    /// In pixels.
+    Absolute(f32),
+}
+
+impl Default for LineHeight {
+    fn default() -> Self {
+        LineHeight::Relative(1.0)
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LayoutOptions {
+    pub max_width: Option<f32>,
+    pub max_height: Option<f32>,
+    pub max_lines: Option<usize>,
+    pub wrap: Wrap,
+    pub align: Align,
+    pub vertical_align: VerticalAlign,
+    pub line_height: LineHeight,
+    pub ellipsis: bool,
+}
+
+impl LayoutOptions {
+    /// A single line for every paragraph, as wide as it needs to be.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Lines wrap at this width, see [LayoutOptions::with_wrap]. Also the width lines are aligned in.
+    pub fn with_max_width(mut self, max_width: f32) -> Self {
+        self.max_width = Some(max_width);
+        self
+    }
+
+    /// Lines that don't fit are dropped, all of them if not even the first one fits. Also the height lines are vertically aligned in.
+    pub fn with_max_height(mut self, max_height: f32) -> Self {
+        self.max_height = Some(max_height);
+        self
+    }
+
+    pub fn with_max_lines(mut self, max_lines: usize) -> Self {
+        self.max_lines = Some(max_lines);
+        self
+    }
+
+    /// Defaults to [Wrap::Word], only matters with a max width.
+    pub fn with_wrap(mut self, wrap: Wrap) -> Self {
+        self.wrap = wrap;
+        self
+    }
+
+    pub fn with_align(mut self, align: Align) -> Self {
+        self.align = align;
+        self
+    }
+
+    /// Only matters with a max height.
+    pub fn with_vertical_align(mut self, vertical_align: VerticalAlign) -> Self {
+        self.vertical_align = vertical_align;
+        self
+    }
+
+    /// Defaults to the font's line height.
+    pub fn with_line_height(mut self, line_height: LineHeight) -> Self {
+        self.line_height = line_height;
+        self
+    }
+
+    /// Ends the last line with [ELLIPSIS] when lines had to be dropped, and cuts lines that are too wide (e.g. with [Wrap::None]) short with one.
+    pub fn with_ellipsis(mut self, ellipsis: bool) -> Self {
+        self.ellipsis = ellipsis;
+        self
+    }
+}
+
+/// A part of the text with its own font and size, see [layout_spans].
+#[derive(Clone)]
+pub struct LayoutSpan<'a> {
+    /// Byte range of the span in the text.
+    pub range: Range<usize>,
+    pub shaper: &'a dyn TextShaper,
+    /// In pixels per em.
+    pub size: f32,
+}
+
+/// A glyph placed by [layout].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LayoutGlyph {
+    /// The font in the fallback chain the glyph is from, see [ShapedGlyph::face].
+    pub face: usize,
+    pub glyph_id: u16,
+    /// Byte offset into the text of the first character the glyph was made from, or into [TextLayout::ellipsis] for the ellipsis' glyphs.
+    pub cluster: usize,
+    /// The span the glyph is from, 0 when there's only one. An ellipsis is from the span its line was cut in.
+    pub span: usize,
+    /// Whether the glyph is part of an ellipsis rather than the text.
+    pub ellipsis: bool,
+    /// The glyph's origin, on its line's baseline.
+    pub x: f32,
+    pub y: f32,
+    /// The line the glyph is on.
+    pub line: usize,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct LayoutLine {
+    /// The part of the text on the line, without the whitespace or newline it ends with.
+    pub range: Range<usize>,
+    /// Where the line starts.
+    pub x: f32,
+    pub baseline: f32,
+    /// The top and bottom edge of the line, line height included.
+    pub top: f32,
+    pub bottom: f32,
+    pub width: f32,
+    /// The line's glyphs in [TextLayout::glyphs].
+    pub glyphs: Range<usize>,
+}
+
+/// Glyphs next to each other on a line that are from the same span, e.g. to underline them.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LayoutRun {
+    pub span: usize,
+    pub line: usize,
+    /// Where the first glyph's pen position is.
+    pub x: f32,
+    /// The glyphs' advances added up, stretched spaces included.
+    pub width: f32,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct TextBounds {
+    pub min: [f32; 2],
+    pub max: [f32; 2],
+}
+
+impl TextBounds {
+    pub fn width(&self) -> f32 {
+        self.max[0] - self.min[0]
+    }
+
+    pub fn height(&self) -> f32 {
+        self.max[1] - self.min[1]
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextLayout {
+    pub glyphs: Vec<LayoutGlyph>,
+    pub lines: Vec<LayoutLine>,
+    /// In visual order, line by line.
+    pub runs: Vec<LayoutRun>,
+    /// From the top of the first line to the bottom of the last one, and from the left-most line start to the right-most line end.
+    pub bounds: TextBounds,
+    /// Whether lines were dropped or cut short to fit.
+    pub truncated: bool,
+    /// The text of the ellipsis glyphs, see [LayoutGlyph::cluster].
+    pub ellipsis: &'static str,
+}
+
+/// Lays out `text` at `size` pixels per em.
+pub fn layout(
+    shaper: &impl TextShaper,
+    text: &str,
+    size: f32,
+    options: &LayoutOptions,
+) -> Result<TextLayout> {
+    let span = LayoutSpan {
+        range: 0..text.len(),
+        shaper,
+        size,
+    };
+    layout_spans(text, &[span], options)
+}
+
+/// Lays out `text` with every span in its own font and size, the spans have to cover the text in order.
+///
+/// Lines are as tall as the tallest span on them. Spans are shaped on their own, so there's no kerning or ligatures across them.
+pub fn layout_spans(
+    text: &str,
+    spans: &[LayoutSpan],
+    options: &LayoutOptions,
+) -> Result<TextLayout> {
+    if spans.is_empty() {
+        return Err(anyhow!("Text needs at least one span to be laid out"));
+    }
+    let mut end = 0;
+    for span in spans {
+        if span.range.start != end || span.range.end < span.range.start {
+            return Err(anyhow!(
+                "Span {:?} doesn't continue where the previous one ended ({})",
+                span.range,
+                end
+            ));
+        }
+        end = span.range.end;
+    }
+    if end != text.len() {
+        return Err(anyhow!(
+            "Spans end at {} but the text is {} bytes long",
+            end,
+            text.len()
+        ));
+    }
+
+    let metrics: Vec<FontMetrics> = spans
+        .iter()
+        .map(|span| span.shaper.metrics(span.size))
+        .collect();
+    // unbounded ui constraints are infinite, that's the same as no limit
+    let max_width = options.max_width.filter(|max_width| max_width.is_finite());
+    let max_height = options
+        .max_height
+        .filter(|max_height| max_height.is_finite());
+
+    let mut shaped = Vec::new();
+    for span in spans.iter().filter(|span| !span.range.is_empty()) {
+        shaped.extend(
+            span.shaper
+                .shape(&text[span.range.clone()], span.size)?
+                .into_iter()
+                .map(|glyph| ShapedGlyph {
+                    cluster: glyph.cluster + span.range.start,
+                    ..glyph
+                }),
+        );
+    }
+    let widths = Widths::new(&shaped, text.len());
+    let mut lines = break_lines(text, &widths, max_width, options.wrap);
+
+    // the font metrics of a line are the most extreme ones of the spans on it
+    let line_metrics = |line: &Line| {
+        let on_line: Vec<usize> = if line.range.is_empty() {
+            vec![span_at(spans, line.range.start)]
+        } else {
+            (0..spans.len())
+                .filter(|idx| {
+                    let range = &spans[*idx].range;
+                    range.start < line.range.end && range.end > line.range.start
+                })
+                .collect()
+        };
+        let line_metrics = on_line
+            .iter()
+            .map(|idx| metrics[*idx])
+            .reduce(|a, b| FontMetrics {
+                ascent: a.ascent.max(b.ascent),
+                descent: a.descent.min(b.descent),
+                line_gap: a.line_gap.max(b.line_gap),
+                ..a
+            })
+            .unwrap_or_default();
+        let line_height = match options.line_height {
+            LineHeight::Relative(factor) => line_metrics.line_height() * factor,
+            LineHeight::Absolute(line_height) => line_height,
+        };
+        (line_metrics, line_height)
+    };
+
+    let mut max_lines = options.max_lines.unwrap_or(usize::MAX);
+    if let Some(max_height) = max_height {
+        let mut height = 0.0;
+        let fitting = lines
+            .iter()
+            .take_while(|line| {
+                height += line_metrics(line).1;
+                height <= max_height
+            })
+            .count();
+        max_lines = max_lines.min(fitting);
+    }
+    let mut truncated = lines.len() > max_lines;
+    lines.truncate(max_lines);
+
+    // the ellipsis is in the font of the span the line is cut in
+    let ellipsis_span = |line: &Line| {
+        let last = text[line.range.clone()]
+            .char_indices()
+            .last()
+            .map_or(line.range.start, |(idx, _)| line.range.start + idx);
+        span_at(spans, last)
+    };
+    let last = lines.len().saturating_sub(1);
+    let needs_ellipsis: Vec<bool> = lines
+        .iter()
+        .enumerate()
+        .map(|(idx, line)| {
+            let too_wide = max_width.is_some_and(|max| widths.width(line.range.clone()) > max);
+            options.ellipsis && (too_wide || (truncated && idx == last))
+        })
+        .collect();
+    // every span the ellipsis is used in has to have a glyph for it, otherwise all of them get the fallback
+    let mut ellipsis = ELLIPSIS;
+    for (line, _) in lines
+        .iter()
+        .zip(&needs_ellipsis)
+        .filter(|(_, needs)| **needs)
+    {
+        let span = &spans[ellipsis_span(line)];
+        let glyphs = span.shaper.shape(ELLIPSIS, span.size)?;
+        if glyphs.iter().any(|glyph| glyph.glyph_id == 0) {
+            ellipsis = ELLIPSIS_FALLBACK;
+        }
+    }
+    let shape_ellipsis = |span: usize| {
+        let span = &spans[span];
+        span.shaper.shape(ellipsis, span.size)
+    };
+
+    let mut ellipsis_glyphs = Vec::new();
+    for (line, needs) in lines.iter_mut().zip(&needs_ellipsis) {
+        if !needs {
+            ellipsis_glyphs.push(None);
+            continue;
+        }
+        let span = ellipsis_span(line);
+        let glyphs = shape_ellipsis(span)?;
+        let ellipsis_width: f32 = glyphs.iter().map(|glyph| glyph.x_advance).sum();
+        // cut the line back until the ellipsis fits after it
+        let fits = |end: usize| {
+            let range = trim_end(text, line.range.start..end);
+            max_width.is_none_or(|max| widths.width(range) + ellipsis_width <= max)
+        };
+        let end = widths
+            .boundaries(line.range.clone())
+            .rev()
+            .find(|end| fits(*end))
+            .unwrap_or(line.range.start);
+        line.range = trim_end(text, line.range.start..end);
+        line.ellipsis = true;
+        truncated = true;
+        ellipsis_glyphs.push(Some((span, glyphs)));
+    }
+
+    let mut laid_out = Vec::new();
+    for (line, ellipsis) in lines.iter().zip(ellipsis_glyphs) {
+        let mut line_glyphs = shape_line(text, spans, line.range.clone())?
+            .into_iter()
+            .map(|(glyph, span)| (glyph, span, false))
+            .collect::<Vec<_>>();
+        if let Some((span, glyphs)) = ellipsis {
+            let ellipsis = glyphs.into_iter().map(|glyph| (glyph, span, true));
+            // the ellipsis goes where the line ends, which is on the left for right to left text
+            if is_rtl(&text[line.range.clone()]) {
+                line_glyphs.splice(0..0, ellipsis);
+            } else {
+                line_glyphs.extend(ellipsis);
+            }
+        }
+        laid_out.push(line_glyphs);
+    }
+
+    let widths_of_lines: Vec<f32> = laid_out
+        .iter()
+        .map(|line| line.iter().map(|(glyph, _, _)| glyph.x_advance).sum())
+        .collect();
+    let heights_of_lines: Vec<(FontMetrics, f32)> = lines.iter().map(line_metrics).collect();
+    let box_width = max_width.unwrap_or(widths_of_lines.iter().copied().fold(0.0, f32::max));
+    let content_height: f32 = heights_of_lines.iter().map(|(_, height)| height).sum();
+    let box_height = max_height.unwrap_or(content_height);
+    let top = match options.vertical_align {
+        VerticalAlign::Top => 0.0,
+        VerticalAlign::Middle => (box_height - content_height) / 2.0,
+        VerticalAlign::Bottom => box_height - content_height,
+    };
+
+    let mut glyphs = Vec::new();
+    let mut runs: Vec<LayoutRun> = Vec::new();
+    let mut layout_lines = Vec::new();
+    let mut line_top = -top;
+    for (idx, (line, line_glyphs)) in lines.iter().zip(laid_out).enumerate() {
+        let (metrics, line_height) = heights_of_lines[idx];
+        let mut width = widths_of_lines[idx];
+        let mut extra = 0.0;
+        let spaces = line_glyphs
+            .iter()
+            .filter(|(glyph, _, ellipsis)| !ellipsis && is_space(text, glyph.cluster))
+            .count();
+        if options.align == Align::Justify
+            && max_width.is_some()
+            && !line.hard
+            && !line.ellipsis
+            && spaces > 0
+        {
+            extra = (box_width - width) / spaces as f32;
+            width = box_width;
+        }
+
+        let x = match options.align {
+            Align::Left | Align::Justify => 0.0,
+            Align::Center => (box_width - width) / 2.0,
+            Align::Right => box_width - width,
+        };
+        // the ascent and descent are centered in the line, the rest is split above and below
+        let half_leading = (line_height - (metrics.ascent - metrics.descent)) / 2.0;
+        let baseline = line_top - half_leading - metrics.ascent;
+
+        let start = glyphs.len();
+        let mut pen = [x, baseline];
+        for (glyph, span, ellipsis) in line_glyphs {
+            glyphs.push(LayoutGlyph {
+                face: glyph.face,
+                glyph_id: glyph.glyph_id,
+                cluster: glyph.cluster,
+                span,
+                ellipsis,
+                x: pen[0] + glyph.x_offset,
+                y: pen[1] + glyph.y_offset,
+                line: idx,
+            });
+            let mut advance = glyph.x_advance;
+            if !ellipsis && is_space(text, glyph.cluster) {
+                advance += extra;
+            }
+            match runs.last_mut() {
+                Some(run) if run.line == idx && run.span == span => run.width += advance,
+                _ => runs.push(LayoutRun {
+                    span,
+                    line: idx,
+                    x: pen[0],
+                    width: advance,
+                }),
+            }
+            pen[0] += advance;
+            pen[1] += glyph.y_advance;
+        }
+
+        layout_lines.push(LayoutLine {
+            range: line.range.clone(),
+            x,
+            baseline,
+            top: line_top,
+            bottom: line_top - line_height,
+            width,
+            glyphs: start..glyphs.len(),
+        });
+        line_top -= line_height;
+    }
+
+    let left = layout_lines
+        .iter()
+        .map(|line| line.x)
+        .reduce(f32::min)
+        .unwrap_or(0.0);
+    let right = layout_lines
+        .iter()
+        .map(|line| line.x + line.width)
+        .reduce(f32::max)
+        .unwrap_or(0.0);
+    let bounds = TextBounds {
+        min: [left, -(top + content_height)],
+        max: [right, -top],
+    };
+
+    Ok(TextLayout {
+        glyphs,
+        lines: layout_lines,
+        runs,
+        bounds,
+        truncated,
+        ellipsis,
+    })
+}
+
+/// The size [layout] would take up, without positioning any glyphs.
+pub fn measure(
+    shaper: &impl TextShaper,
+    text: &str,
+    size: f32,
+    options: &LayoutOptions,
+) -> Result<TextBounds> {
+    Ok(layout(shaper, text, size, options)?.bounds)
+}
+
+/// Shapes a line one span at a time, in visual order. Returns every glyph along with its span.
+fn shape_line(
+    text: &str,
+    spans: &[LayoutSpan],
+    range: Range<usize>,
+) -> Result<Vec<(ShapedGlyph, usize)>> {
+    let mut glyphs = Vec::new();
+    if range.is_empty() {
+        return Ok(glyphs);
+    }
+
+    // right to left runs are cut into spans back to front, the shaper reverses the glyphs within each span
+    let line = &text[range.clone()];
+    let bidi = BidiInfo::new(line, None);
+    for paragraph in &bidi.paragraphs {
+        let (levels, runs) = bidi.visual_runs(paragraph, paragraph.range.clone());
+        for run in runs {
+            let mut pieces: Vec<(usize, Range<usize>)> = spans
+                .iter()
+                .enumerate()
+                .map(|(idx, span)| {
+                    let start = span.range.start.max(range.start + run.start);
+                    let end = span.range.end.min(range.start + run.end);
+                    (idx, start..end)
+                })
+                .filter(|(_, piece)| piece.start < piece.end)
+                .collect();
+            if levels[run.start].is_rtl() {
+                pieces.reverse();
+            }
+
+            for (idx, piece) in pieces {
+                let span = &spans[idx];
+                glyphs.extend(
+                    span.shaper
+                        .shape(&text[piece.clone()], span.size)?
+                        .into_iter()
+                        .map(|glyph| {
+                            let glyph = ShapedGlyph {
+                                cluster: glyph.cluster + piece.start,
+                                ..glyph
+                            };
+                            (glyph, idx)
+                        }),
+                );
+            }
+        }
+    }
+    Ok(glyphs)
+}
+
+/// The span the character at `idx` is in, the last one past the end of the text.
+fn span_at(spans: &[LayoutSpan], idx: usize) -> usize {
+    spans
+        .iter()
+        .position(|span| span.range.contains(&idx))
+        .unwrap_or(spans.len() - 1)
+}
+
+struct Line {
+    range: Range<usize>,
+    // ended by a newline (or the end of the text) rather than wrapping
+    hard: bool,
+    ellipsis: bool,
+}
+
+/// How wide any part of the text is, from the advances of its glyphs.
+struct Widths {
+    // prefix[i] is the width of text[..i]
+    prefix: Vec<f32>,
+    // the byte offsets glyphs start at, lines can be broken at any of them
+    clusters: Vec<usize>,
+}
+
+impl Widths {
+    fn new(glyphs: &[ShapedGlyph], len: usize) -> Self {
+        let mut advances = vec![0.0; len];
+        for glyph in glyphs {
+            advances[glyph.cluster] += glyph.x_advance;
+        }
+        let mut prefix = vec![0.0];
+        prefix.extend(advances.iter().scan(0.0, |width, advance| {
+            *width += advance;
+            Some(*width)
+        }));
+
+        let mut clusters: Vec<usize> = glyphs.iter().map(|glyph| glyph.cluster).collect();
+        clusters.push(len);
+        clusters.sort_unstable();
+        clusters.dedup();
+        Self { prefix, clusters }
+    }
+
+    fn width(&self, range: Range<usize>) -> f32 {
+        self.prefix[range.end] - self.prefix[range.start]
+    }
+
+    /// Places inside of `range` (including its end, excluding its start) where the text can be split without splitting a glyph.
+    fn boundaries(&self, range: Range<usize>) -> impl DoubleEndedIterator<Item = usize> + '_ {
+        self.clusters
+            .iter()
+            .copied()
+            .filter(move |cluster| *cluster > range.start && *cluster <= range.end)
+    }
+}
+
+fn break_lines(text: &str, widths: &Widths, max_width: Option<f32>, wrap: Wrap) -> Vec<Line> {
+    let mut breaks: Vec<(usize, BreakOpportunity)> = linebreaks(text)
+        .filter(|(_, opportunity)| {
+            wrap != Wrap::None || *opportunity == BreakOpportunity::Mandatory
+        })
+        .collect();
+    if wrap == Wrap::Character {
+        breaks.extend(
+            widths
+                .boundaries(0..text.len())
+                .map(|boundary| (boundary, BreakOpportunity::Allowed)),
+        );
+        // mandatory breaks first so they're the ones that are kept
+        breaks
+            .sort_by_key(|(idx, opportunity)| (*idx, *opportunity != BreakOpportunity::Mandatory));
+        breaks.dedup_by_key(|(idx, _)| *idx);
+    }
+
+    let fits = |range: Range<usize>| {
+        max_width.is_none_or(|max| widths.width(trim_end(text, range)) <= max)
+    };
+
+    let mut lines = Vec::new();
+    let mut start = 0;
+    // the last place the current line could have been broken at
+    let mut last_break = None;
+    for (idx, opportunity) in breaks {
+        if wrap != Wrap::None && !fits(start..idx) {
+            if let Some(end) = last_break.take() {
+                lines.push(Line {
+                    range: trim_end(text, start..end),
+                    hard: false,
+                    ellipsis: false,
+                });
+                start = end;
+            }
+            // a word that doesn't fit on a line of its own is broken between characters,
+            // with at least one character on every line
+            while !fits(start..idx) {
+                let first = widths.boundaries(start..idx).next().unwrap_or(idx);
+                let end = widths
+                    .boundaries(start..idx)
+                    .take_while(|end| fits(start..*end))
+                    .last()
+                    .unwrap_or(first);
+                if end >= idx {
+                    break;
+                }
+                lines.push(Line {
+                    range: start..end,
+                    hard: false,
+                    ellipsis: false,
+                });
+                start = end;
+            }
+        }
+
+        if opportunity == BreakOpportunity::Mandatory {
+            lines.push(Line {
+                range: trim_end(text, start..idx),
+                hard: true,
+                ellipsis: false,
+            });
+            start = idx;
+            last_break = None;
+        } else {
+            last_break = Some(idx);
+        }
+    }
+    lines
+}
+
+/// Drops the whitespace (and newline) a range ends with.
+fn trim_end(text: &str, range: Range<usize>) -> Range<usize> {
+    range.start..range.start + text[range].trim_end().len()
+}
+
+fn is_space(text: &str, idx: usize) -> bool {
+    text.get(idx..)
+        .and_then(|rest| rest.chars().next())
+        .is_some_and(char::is_whitespace)
+}
+
+fn is_rtl(text: &str) -> bool {
+    BidiInfo::new(text, None)
+        .paragraphs
+        .first()
+        .is_some_and(|paragraph| paragraph.level.is_rtl())
+}
@@ -2,8 +2,19 @@
 // keep it simple:
 // - we will use a library to convert font glyphs (bezier) into bitmaps
 // - stitch those bitmaps into an atlas
-// - draw textured rects for each letter
+// - pick fonts by family, weight and style with fallbacks for missing glyphs, see font_collection.rs
+// - shape the text into positioned glyphs, see shaping.rs
+// - break it into lines and align them, see layout.rs
+// - style parts of the text differently with spans, see rich_text.rs
+// - draw textured rects for each letter, color glyphs (emoji) come from their own atlas, see color_glyphs.rs
+// - billboard text in the 3d world so it faces the camera, see world_text.rs
 
+pub mod color_glyphs;
 pub mod font_bitmap_manager;
+pub mod font_collection;
+pub mod layout;
 pub mod pipeline;
+pub mod rich_text;
+pub mod shaping;
 pub mod text_builder;
+pub mod world_text;
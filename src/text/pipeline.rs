@@ -17,7 +17,10 @@ use crate::{
     render_object::RenderObject,
 };
 
-use super::font_bitmap_manager::FontBitmapManager;
+use super::{
+    color_glyphs::COLOR_GLYPH_FORMAT,
+    font_bitmap_manager::{DistanceField, FontBitmapManager},
+};
 
 #[repr(C)]
 #[derive(Clone, Debug)]
@@ -34,10 +37,101 @@ pub struct TextInstance {
     pub transform: Matrix4<f32>,
     pub albedo: [f32; 4],
     pub atlas_coords: Vector4<f32>,
+    /// The distance covered by the distance field from 0 to 1, in atlas pixels. Twice the manager's spread.
+    pub distance_range: f32,
+    /// How the glyph is read from the atlas, see [GlyphSampling].
+    pub sampling: u32,
+    // see TextEffects, the four floats are passed to the shader as a single vec4
+    pub weight: f32,
+    pub outline_width: f32,
+    pub glow_width: f32,
+    pub shadow_softness: f32,
+    pub outline_color: [f32; 4],
+    pub shadow_color: [f32; 4],
+    pub glow_color: [f32; 4],
+    pub shadow_offset: [f32; 2],
 }
 
 unsafe impl Plain for TextInstance {}
 
+/// How the text shader draws a glyph, passed along with every glyph so that a run of text can mix them.
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GlyphSampling {
+    /// From a single channel distance field in the glyph atlas.
+    Sdf = 0,
+    /// From the median of a multi-channel distance field in the glyph atlas.
+    Msdf = 1,
+    /// As is from the color atlas, only the text color's alpha applies and effects are skipped.
+    Color = 2,
+}
+
+impl From<DistanceField> for GlyphSampling {
+    fn from(distance_field: DistanceField) -> Self {
+        match distance_field {
+            DistanceField::Sdf => GlyphSampling::Sdf,
+            DistanceField::Msdf => GlyphSampling::Msdf,
+        }
+    }
+}
+
+/// The binding of the text pipeline's bind to register the glyph atlas at.
+pub const GLYPH_ATLAS_BINDING: u32 = 1;
+/// The binding of the text pipeline's bind to register the color atlas at, see [FontBitmapManager::with_color_atlas].
+pub const COLOR_ATLAS_BINDING: u32 = 3;
+
+/// Outlines, shadows etc. drawn from a glyph's distance field.
+///
+/// Distances are in pixels at the font manager's px, so effects scale along with the text.
+/// The distance field only reaches as far as the manager's spread (see [FontBitmapManager::with_spread]),
+/// anything further out than that (weight, outline, glow and shadow offset added up) gets cut off.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct TextEffects {
+    /// Grows glyphs by this much, negative to thin them.
+    pub weight: f32,
+    pub outline_width: f32,
+    pub outline_color: [f32; 4],
+    /// With y up.
+    pub shadow_offset: [f32; 2],
+    /// How far the shadow's edge is blurred.
+    pub shadow_softness: f32,
+    pub shadow_color: [f32; 4],
+    /// How far the glow fades out from the glyph's (outlined) edge.
+    pub glow_width: f32,
+    pub glow_color: [f32; 4],
+}
+
+impl TextEffects {
+    /// No effects.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_weight(mut self, weight: f32) -> Self {
+        self.weight = weight;
+        self
+    }
+
+    pub fn with_outline(mut self, width: f32, color: [f32; 4]) -> Self {
+        self.outline_width = width;
+        self.outline_color = color;
+        self
+    }
+
+    pub fn with_shadow(mut self, offset: [f32; 2], softness: f32, color: [f32; 4]) -> Self {
+        self.shadow_offset = offset;
+        self.shadow_softness = softness;
+        self.shadow_color = color;
+        self
+    }
+
+    pub fn with_glow(mut self, width: f32, color: [f32; 4]) -> Self {
+        self.glow_width = width;
+        self.glow_color = color;
+        self
+    }
+}
+
 impl InstanceData for TextInstance {
     fn data(&self) -> &[u8] {
         self.as_bytes()
@@ -98,8 +192,10 @@ pub struct TextRenderObject {
     pub albedo: [f32; 4],
     pub pipeline_handle: PipelineHandle,
     pub mesh_handle: MeshHandle,
-    pub character: char,
+    pub face: usize,
+    pub glyph_id: u16,
     pub manager: Rc<FontBitmapManager>,
+    pub effects: TextEffects,
 }
 
 impl RenderObject for TextRenderObject {
@@ -114,14 +210,41 @@ impl RenderObject for TextRenderObject {
         // do we want the texture handles to be registered to the renderer?
         // probably yes - existing code relies on any atlas textures being stored on the renderer
         // so the new 'font' struct will need to co-operate with the renderer
-        let texture_handle = self.manager.get_texture(self.character).unwrap();
-        let atlas_coords = render
-            .get_atlas_coords_for_texture(texture_handle, self.manager.atlas_handle)
-            .unwrap();
+        // the glyph can be gone if it was evicted from the atlas after the text was built
+        let atlas_coords = self
+            .manager
+            .get_texture(self.face, self.glyph_id)
+            .and_then(|texture_handle| {
+                let atlas_handle = self.manager.get_atlas(self.face, self.glyph_id)?;
+                render.get_atlas_coords_for_texture(texture_handle, atlas_handle)
+            })
+            .unwrap_or_else(|err| {
+                log::warn!("Can't draw glyph {}: {}", self.glyph_id, err);
+                [0.0; 4]
+            });
+        let color = self
+            .manager
+            .get_metric(self.face, self.glyph_id)
+            .is_ok_and(|metrics| metrics.color);
+        let sampling = if color {
+            GlyphSampling::Color
+        } else {
+            self.manager.distance_field.into()
+        };
         TextInstance {
             transform: self.transform,
             albedo: self.albedo,
             atlas_coords: atlas_coords.into(),
+            distance_range: self.manager.spread * 2.0,
+            sampling: sampling as u32,
+            weight: self.effects.weight,
+            outline_width: self.effects.outline_width,
+            glow_width: self.effects.glow_width,
+            shadow_softness: self.effects.shadow_softness,
+            outline_color: self.effects.outline_color,
+            shadow_color: self.effects.shadow_color,
+            glow_color: self.effects.glow_color,
+            shadow_offset: self.effects.shadow_offset,
         }
     }
 
@@ -134,7 +257,32 @@ impl RenderObject for TextRenderObject {
     }
 }
 
+/// A pipeline for text from an [DistanceField::Sdf] glyph atlas.
 pub fn text_pipeline(render: &mut Render) -> (Pipeline, BindHandle) {
+    text_pipeline_for(render, DistanceField::Sdf)
+}
+
+/// A pipeline for text from a glyph atlas of the given kind, register the atlas with [DistanceField::format].
+///
+/// The bind also has room for a color atlas at [COLOR_ATLAS_BINDING], for fonts with color glyphs.
+pub fn text_pipeline_for(
+    render: &mut Render,
+    distance_field: DistanceField,
+) -> (Pipeline, BindHandle) {
+    text_pipeline_with(
+        render,
+        distance_field,
+        // .with_cull_mode(Some(wgpu::Face::Back))
+        PipelineBuilder::new().with_cull_mode(None),
+    )
+}
+
+// the text bind and vertex layout on top of `pipeline_builder`'s depth, culling and order
+pub(crate) fn text_pipeline_with(
+    render: &mut Render,
+    distance_field: DistanceField,
+    pipeline_builder: PipelineBuilder,
+) -> (Pipeline, BindHandle) {
     let defaults_bind = render.build_bind(&mut [
         // camera
         BindEntry {
@@ -152,13 +300,14 @@ pub fn text_pipeline(render: &mut Render) -> (Pipeline, BindHandle) {
                 sample_type: wgpu::TextureSampleType::Float { filterable: true },
                 view_dimension: wgpu::TextureViewDimension::D2,
                 sample_count: 1,
-                format: wgpu::TextureFormat::R8Unorm,
+                format: distance_field.format().wgpu_format(),
                 size: Extent3d {
                     width: 1,
                     height: 1,
                     depth_or_array_layers: 1,
                 },
                 usage: TextureUsages::COPY_DST | TextureUsages::TEXTURE_BINDING,
+                mip_level_count: 1,
             },
             count: None,
         },
@@ -176,12 +325,27 @@ pub fn text_pipeline(render: &mut Render) -> (Pipeline, BindHandle) {
             },
             count: None,
         },
+        // color atlas
+        BindEntry {
+            visibility: ShaderStages::FRAGMENT,
+            ty: BindEntryType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                sample_count: 1,
+                format: COLOR_GLYPH_FORMAT.wgpu_format(),
+                size: Extent3d {
+                    width: 1,
+                    height: 1,
+                    depth_or_array_layers: 1,
+                },
+                usage: TextureUsages::COPY_DST | TextureUsages::TEXTURE_BINDING,
+                mip_level_count: 1,
+            },
+            count: None,
+        },
     ]);
 
-    let pipeline_handle = PipelineBuilder::new()
-        .with_format(wgpu::TextureFormat::Bgra8UnormSrgb)
-        // .with_cull_mode(Some(wgpu::Face::Back))
-        .with_cull_mode(None)
+    let pipeline_handle = pipeline_builder
         .with_bind(defaults_bind)
         .with_shader(include_str!("../shaders/text.wgsl"))
         .with_vb::<TextVertex>(
@@ -205,6 +369,20 @@ pub fn text_pipeline(render: &mut Render) -> (Pipeline, BindHandle) {
                 6 => Float32x4,
                 // atlas coords
                 7 => Float32x4,
+                // distance range
+                8 => Float32,
+                // sampling
+                9 => Uint32,
+                // weight, outline width, glow width and shadow softness
+                10 => Float32x4,
+                // outline color
+                11 => Float32x4,
+                // shadow color
+                12 => Float32x4,
+                // glow color
+                13 => Float32x4,
+                // shadow offset
+                14 => Float32x2,
             ],
         )
         .build(render);
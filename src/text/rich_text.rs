@@ -0,0 +1,164 @@
+// rich text is a list of spans, each with its own style on top of the text's base style
+// the spans are laid out together (see layout::layout_spans), decorations are rects worked out from the layout
+// so that the text builder and the ui can draw them with whatever they draw rects with
+
+use std::ops::Range;
+
+use super::layout::{DecorationLine, FontMetrics, TextBounds, TextLayout};
+
+/// What a span changes about the text's base style, anything left unset is taken from it.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SpanStyle {
+    pub color: Option<[f32; 4]>,
+    /// In the same units as the text's size.
+    pub size: Option<f32>,
+    /// See [crate::text::font_collection::weight].
+    pub weight: Option<u16>,
+    pub underline: bool,
+    pub strikethrough: bool,
+    /// Drawn behind the span's lines.
+    pub background: Option<[f32; 4]>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextSpan {
+    pub text: String,
+    pub style: SpanStyle,
+}
+
+impl TextSpan {
+    pub fn new(text: &str) -> Self {
+        Self {
+            text: text.to_string(),
+            style: SpanStyle::default(),
+        }
+    }
+
+    pub fn with_style(mut self, style: SpanStyle) -> Self {
+        self.style = style;
+        self
+    }
+
+    pub fn with_color(mut self, color: [f32; 4]) -> Self {
+        self.style.color = Some(color);
+        self
+    }
+
+    pub fn with_size(mut self, size: f32) -> Self {
+        self.style.size = Some(size);
+        self
+    }
+
+    pub fn with_weight(mut self, weight: u16) -> Self {
+        self.style.weight = Some(weight);
+        self
+    }
+
+    pub fn with_underline(mut self, underline: bool) -> Self {
+        self.style.underline = underline;
+        self
+    }
+
+    pub fn with_strikethrough(mut self, strikethrough: bool) -> Self {
+        self.style.strikethrough = strikethrough;
+        self
+    }
+
+    pub fn with_background(mut self, background: [f32; 4]) -> Self {
+        self.style.background = Some(background);
+        self
+    }
+}
+
+/// The spans' text put together, along with where each span is in it.
+pub fn join_spans(spans: &[TextSpan]) -> (String, Vec<Range<usize>>) {
+    let mut text = String::new();
+    let mut ranges = Vec::new();
+    for span in spans {
+        let start = text.len();
+        text.push_str(&span.text);
+        ranges.push(start..text.len());
+    }
+    (text, ranges)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DecorationKind {
+    Background,
+    Underline,
+    Strikethrough,
+}
+
+/// A rect to draw along with the text, in the layout's coordinates (pixels with y up).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Decoration {
+    pub kind: DecorationKind,
+    pub span: usize,
+    pub color: [f32; 4],
+    pub rect: TextBounds,
+}
+
+/// The backgrounds, underlines and strikethroughs of the spans in `layout`.
+/// `metrics` are the spans' font metrics at their size, spans without a color of their own use `color`.
+///
+/// Backgrounds come first, they're meant to be drawn before the text and the lines after it.
+pub fn decorations(
+    layout: &TextLayout,
+    spans: &[TextSpan],
+    metrics: &[FontMetrics],
+    color: [f32; 4],
+) -> Vec<Decoration> {
+    let mut backgrounds = Vec::new();
+    let mut lines = Vec::new();
+    for run in &layout.runs {
+        let (Some(span), Some(metrics)) = (spans.get(run.span), metrics.get(run.span)) else {
+            continue;
+        };
+        let line = &layout.lines[run.line];
+        let x = [run.x, run.x + run.width];
+
+        if let Some(background) = span.style.background {
+            backgrounds.push(Decoration {
+                kind: DecorationKind::Background,
+                span: run.span,
+                color: background,
+                rect: TextBounds {
+                    min: [x[0], line.bottom],
+                    max: [x[1], line.top],
+                },
+            });
+        }
+
+        let color = span.style.color.unwrap_or(color);
+        let mut push_line = |kind, decoration: DecorationLine| {
+            let top = line.baseline + decoration.position;
+            lines.push(Decoration {
+                kind,
+                span: run.span,
+                color,
+                rect: TextBounds {
+                    min: [x[0], top - decoration.thickness],
+                    max: [x[1], top],
+                },
+            });
+        };
+        // fonts that don't say where their lines go get them just below the baseline and around half the x height
+        let thickness = ((metrics.ascent - metrics.descent) / 14.0).max(1.0);
+        if span.style.underline {
+            let underline = metrics.underline.unwrap_or(DecorationLine {
+                position: metrics.descent / 2.0,
+                thickness,
+            });
+            push_line(DecorationKind::Underline, underline);
+        }
+        if span.style.strikethrough {
+            let strikeout = metrics.strikeout.unwrap_or(DecorationLine {
+                position: metrics.ascent * 0.3 + thickness / 2.0,
+                thickness,
+            });
+            push_line(DecorationKind::Strikethrough, strikeout);
+        }
+    }
+    backgrounds.extend(lines);
+    backgrounds
+}
@@ -0,0 +1,189 @@
+// shaping turns a string into positioned glyphs, the font decides on kerning, ligatures,
+// where combining marks go and which glyph forms scripts like arabic and devanagari use
+// the text is split into runs of a single direction (unicode bidi) and script first,
+// every run is shaped on its own and the runs are put back together in visual order
+// parts of a run the font has no glyphs for are shaped again with the next font in the fallback chain
+
+use std::{ops::Range, rc::Rc};
+
+use anyhow::{anyhow, Result};
+use rustybuzz::{Direction, Face, UnicodeBuffer};
+use self_cell::self_cell;
+use unicode_bidi::BidiInfo;
+use unicode_script::{Script, UnicodeScript};
+
+/// A glyph placed by [shape]. Positions are in pixels at the px the text was shaped at, with y up.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ShapedGlyph {
+    /// The font in the fallback chain the glyph is from, 0 for the primary font.
+    pub face: usize,
+    pub glyph_id: u16,
+    /// Byte offset into the text of the first character the glyph was made from.
+    /// Ligatures are made from several characters, combining marks share the cluster of their base character.
+    pub cluster: usize,
+    /// How far to move the pen after the glyph.
+    pub x_advance: f32,
+    pub y_advance: f32,
+    /// Where the glyph is drawn relative to the pen, e.g. to place a mark above its base.
+    pub x_offset: f32,
+    pub y_offset: f32,
+}
+
+self_cell!(
+    /// A font parsed for shaping, along with the data it borrows from.
+    /// Parsing takes a while, so it's done once rather than every time some text is shaped.
+    pub struct ShapingFace {
+        owner: Rc<[u8]>,
+
+        #[covariant]
+        dependent: Face,
+    }
+);
+
+impl ShapingFace {
+    /// Parses face `index` of the font in `data`, 0 for plain font files.
+    pub fn parse(data: Rc<[u8]>, index: u32) -> Result<Self> {
+        Self::try_new(data, |data| {
+            Face::from_slice(data, index).ok_or(anyhow!("Couldn't parse font face {}", index))
+        })
+    }
+}
+
+/// Shapes `text` at `px` pixels per em with the first face in `faces`, falling back to the next face for anything it doesn't have.
+///
+/// The glyphs come back in visual order, left to right, so right to left text is already reversed.
+pub fn shape(faces: &[ShapingFace], px: f32, text: &str) -> Vec<ShapedGlyph> {
+    let mut glyphs = Vec::new();
+    if faces.is_empty() {
+        return glyphs;
+    }
+
+    let bidi = BidiInfo::new(text, None);
+    for paragraph in &bidi.paragraphs {
+        let (levels, runs) = bidi.visual_runs(paragraph, paragraph.range.clone());
+        for run in runs {
+            let rtl = levels[run.start].is_rtl();
+            let mut script_runs = script_runs(text, run);
+            if rtl {
+                script_runs.reverse();
+            }
+
+            for range in script_runs {
+                glyphs.extend(shape_run(faces, 0, px, text, range, rtl));
+            }
+        }
+    }
+    glyphs
+}
+
+/// Shapes a run of a single direction and script with `faces[face]`, reshaping the glyphs it's missing with the faces after it.
+fn shape_run(
+    faces: &[ShapingFace],
+    face: usize,
+    px: f32,
+    text: &str,
+    range: Range<usize>,
+    rtl: bool,
+) -> Vec<ShapedGlyph> {
+    let font = faces[face].borrow_dependent();
+    let scale = px / font.units_per_em() as f32;
+
+    let mut buffer = UnicodeBuffer::new();
+    buffer.set_pre_context(&text[..range.start]);
+    buffer.push_str(&text[range.clone()]);
+    buffer.set_post_context(&text[range.end..]);
+    buffer.set_direction(if rtl {
+        Direction::RightToLeft
+    } else {
+        Direction::LeftToRight
+    });
+    // the script and language are guessed from the run
+    let shaped = rustybuzz::shape(font, &[], buffer);
+
+    let glyphs: Vec<ShapedGlyph> = shaped
+        .glyph_infos()
+        .iter()
+        .zip(shaped.glyph_positions())
+        .map(|(info, position)| ShapedGlyph {
+            face,
+            glyph_id: info.glyph_id as u16,
+            cluster: range.start + info.cluster as usize,
+            x_advance: position.x_advance as f32 * scale,
+            y_advance: position.y_advance as f32 * scale,
+            x_offset: position.x_offset as f32 * scale,
+            y_offset: position.y_offset as f32 * scale,
+        })
+        .collect();
+
+    // the last face's missing glyphs (0) are drawn as they are
+    if face + 1 >= faces.len() || glyphs.iter().all(|glyph| glyph.glyph_id != 0) {
+        return glyphs;
+    }
+
+    let mut clusters: Vec<usize> = glyphs.iter().map(|glyph| glyph.cluster).collect();
+    clusters.sort_unstable();
+    clusters.dedup();
+    // where the cluster starting at `cluster` ends
+    let cluster_end = |cluster: usize| {
+        clusters
+            .iter()
+            .copied()
+            .find(|other| *other > cluster)
+            .unwrap_or(range.end)
+    };
+
+    // a cluster with any missing glyph is reshaped as a whole, e.g. a base with a mark the font doesn't have
+    let missing_clusters: Vec<usize> = glyphs
+        .iter()
+        .filter(|glyph| glyph.glyph_id == 0)
+        .map(|glyph| glyph.cluster)
+        .collect();
+    let is_missing = |glyph: &ShapedGlyph| missing_clusters.contains(&glyph.cluster);
+
+    // missing glyphs next to each other come from neighbouring clusters, whichever the direction
+    let mut result = Vec::new();
+    let mut idx = 0;
+    while idx < glyphs.len() {
+        if !is_missing(&glyphs[idx]) {
+            result.push(glyphs[idx]);
+            idx += 1;
+            continue;
+        }
+        let start = idx;
+        while idx < glyphs.len() && is_missing(&glyphs[idx]) {
+            idx += 1;
+        }
+        let missing = &glyphs[start..idx];
+        let first = missing.iter().map(|glyph| glyph.cluster).min().unwrap();
+        let last = missing.iter().map(|glyph| glyph.cluster).max().unwrap();
+        result.extend(shape_run(
+            faces,
+            face + 1,
+            px,
+            text,
+            first..cluster_end(last),
+            rtl,
+        ));
+    }
+    result
+}
+
+/// Splits `range` wherever the script changes. Punctuation, spaces, emoji etc. are shared between scripts and stay with the run they're in.
+pub fn script_runs(text: &str, range: Range<usize>) -> Vec<Range<usize>> {
+    let mut runs = Vec::new();
+    let mut start = range.start;
+    let mut current = None;
+    for (idx, character) in text[range.clone()].char_indices() {
+        let script = character.script();
+        if matches!(script, Script::Common | Script::Inherited | Script::Unknown) {
+            continue;
+        }
+        if current.is_some_and(|current| current != script) {
+            runs.push(start..range.start + idx);
+            start = range.start + idx;
+        }
+        current = Some(script);
+    }
+    runs.push(start..range.end);
+    runs
+}
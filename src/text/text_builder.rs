@@ -4,20 +4,35 @@ use anyhow::Result;
 use nalgebra::{Matrix4, Scale3, Translation3};
 
 use crate::{
+    camera::Camera,
     pipeline::PipelineHandle,
     render::{MeshHandle, Render},
+    shapes::ShapeRenderObject,
 };
 
-use super::{font_bitmap_manager::FontBitmapManager, pipeline::TextRenderObject};
+use super::{
+    font_bitmap_manager::FontBitmapManager,
+    font_collection::weight_distance,
+    layout::{layout_spans, LayoutOptions, LayoutSpan, TextBounds, TextLayout, TextShaper},
+    pipeline::{TextEffects, TextRenderObject},
+    rich_text::{decorations, join_spans, TextSpan},
+    world_text::Billboard,
+};
 
+/// Builds render objects for a piece of text, laid out with [LayoutOptions].
+/// The transform's origin is the top left corner of the layout box, with y up.
 pub struct TextBuilder {
-    text: String,
+    spans: Vec<TextSpan>,
     font_manager: Rc<FontBitmapManager>,
+    // other weights of the font, for spans with a weight
+    fonts: Vec<Rc<FontBitmapManager>>,
     albedo: [f32; 4],
     transform: Matrix4<f32>,
     pipeline_handle: PipelineHandle,
     mesh_handle: MeshHandle,
     scale: f32,
+    layout: LayoutOptions,
+    effects: TextEffects,
 }
 
 impl TextBuilder {
@@ -31,28 +46,163 @@ impl TextBuilder {
         scale: f32,
     ) -> Self {
         Self {
-            text: text.to_string(),
+            spans: vec![TextSpan::new(text)],
             font_manager,
+            fonts: Vec::new(),
             albedo,
             transform,
             pipeline_handle,
             mesh_handle,
             scale,
+            layout: LayoutOptions::new(),
+            effects: TextEffects::new(),
+        }
+    }
+
+    /// Wrapping, alignment etc. in the same units as `scale`. Defaults to [LayoutOptions::new], a single line per paragraph.
+    pub fn with_layout(mut self, layout: LayoutOptions) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    /// Outline, shadow etc. for every glyph. Defaults to [TextEffects::new], none.
+    pub fn with_effects(mut self, effects: TextEffects) -> Self {
+        self.effects = effects;
+        self
+    }
+
+    /// Replaces the text with spans styled on top of the builder's albedo, scale and font.
+    pub fn with_spans(mut self, spans: Vec<TextSpan>) -> Self {
+        self.spans = spans;
+        self
+    }
+
+    /// Another weight of the font for spans with a weight, they get the font closest to it.
+    /// It has to use the same atlas as the main font since all glyphs are drawn with the same pipeline.
+    pub fn with_font(mut self, font_manager: Rc<FontBitmapManager>) -> Self {
+        self.fonts.push(font_manager);
+        self
+    }
+
+    pub fn with_transform(mut self, transform: Matrix4<f32>) -> Self {
+        self.transform = transform;
+        self
+    }
+
+    /// Places the text in the world facing `camera`, replacing the transform.
+    /// It measures the text, so set the spans and layout first, and call it again whenever the camera moves.
+    pub fn with_billboard(self, billboard: &Billboard, camera: &Camera) -> Result<Self> {
+        let transform = billboard.transform(camera, &self.measure()?);
+        Ok(self.with_transform(transform))
+    }
+
+    fn font_manager_for(&self, span: &TextSpan) -> &Rc<FontBitmapManager> {
+        let Some(weight) = span.style.weight else {
+            return &self.font_manager;
+        };
+        std::iter::once(&self.font_manager)
+            .chain(&self.fonts)
+            .min_by_key(|font| weight_distance(weight, font.faces()[0].weight))
+            .unwrap()
+    }
+
+    /// Where every glyph goes, without building any render objects. Glyphs' spans are the builder's spans.
+    pub fn layout(&self) -> Result<TextLayout> {
+        let (text, ranges) = join_spans(&self.spans);
+        let mut spans: Vec<LayoutSpan> = self
+            .spans
+            .iter()
+            .zip(ranges)
+            .map(|(span, range)| LayoutSpan {
+                range,
+                shaper: &**self.font_manager_for(span),
+                size: span.style.size.unwrap_or(self.scale),
+            })
+            .collect();
+        // no spans is the same as no text
+        if spans.is_empty() {
+            spans.push(LayoutSpan {
+                range: 0..0,
+                shaper: &*self.font_manager,
+                size: self.scale,
+            });
         }
+        layout_spans(&text, &spans, &self.layout)
+    }
+
+    /// The area the text takes up, see [TextLayout::bounds].
+    pub fn measure(&self) -> Result<TextBounds> {
+        Ok(self.layout()?.bounds)
     }
 
     pub fn build(&self, render: &mut Render) -> Result<Vec<TextRenderObject>> {
+        self.build_glyphs(render, &self.layout()?)
+    }
+
+    /// Builds the text along with quads for its spans' backgrounds, underlines and strikethroughs.
+    /// The quads are drawn with [crate::shapes::shape_pipeline] and a [crate::shapes::quad_geometry] mesh,
+    /// draw them before the text so that backgrounds end up behind it.
+    pub fn build_with_decorations(
+        &self,
+        render: &mut Render,
+        shape_pipeline_handle: PipelineHandle,
+        quad_mesh_handle: MeshHandle,
+    ) -> Result<(Vec<TextRenderObject>, Vec<ShapeRenderObject>)> {
+        let layout = self.layout()?;
+        let glyphs = self.build_glyphs(render, &layout)?;
+
+        let metrics: Vec<_> = self
+            .spans
+            .iter()
+            .map(|span| {
+                self.font_manager_for(span)
+                    .metrics(span.style.size.unwrap_or(self.scale))
+            })
+            .collect();
+        let decorations = decorations(&layout, &self.spans, &metrics, self.albedo)
+            .into_iter()
+            .map(|decoration| {
+                let rect = decoration.rect;
+                // the quad is centered on the origin
+                let center = [
+                    (rect.min[0] + rect.max[0]) / 2.0,
+                    (rect.min[1] + rect.max[1]) / 2.0,
+                ];
+                let transform = self.transform
+                    * Translation3::new(center[0], center[1], 0.0).to_homogeneous()
+                    * Scale3::new(rect.width(), rect.height(), 1.0).to_homogeneous();
+                ShapeRenderObject {
+                    transform,
+                    albedo: decoration.color,
+                    pipeline_handle: shape_pipeline_handle,
+                    mesh_handle: quad_mesh_handle,
+                }
+            })
+            .collect();
+        Ok((glyphs, decorations))
+    }
+
+    fn build_glyphs(
+        &self,
+        render: &mut Render,
+        layout: &TextLayout,
+    ) -> Result<Vec<TextRenderObject>> {
         let mut render_objs = Vec::new();
-        let mut x = 0.0;
-        let mut y = 0.0;
 
-        let scale = self.scale / self.font_manager.px;
+        for glyph in &layout.glyphs {
+            let span = &self.spans[glyph.span];
+            let font_manager = self.font_manager_for(span);
+            // metrics are in pixels at the manager's px, the same glyphs are scaled to any text size
+            let scale = span.style.size.unwrap_or(self.scale) / font_manager.px;
 
-        for character in self.text.chars() {
-            let metrics = self.font_manager.get_metric(character)?;
+            let metrics = font_manager.load_glyph(render, glyph.face, glyph.glyph_id)?;
+            if metrics.is_empty() {
+                continue;
+            }
 
-            let xpos = x + metrics.xmin as f32 * scale;
-            let ypos = y + metrics.ymin as f32 * scale;
+            // the quad covers the whole bitmap, padding included, so texels map onto it 1:1
+            let xpos = glyph.x + metrics.xmin * scale;
+            let ypos = glyph.y + metrics.ymin * scale;
             let w = metrics.width as f32 * scale;
             let h = metrics.height as f32 * scale;
 
@@ -62,16 +212,16 @@ impl TextBuilder {
 
             let render_obj = TextRenderObject {
                 transform: transform,
-                albedo: self.albedo,
+                albedo: span.style.color.unwrap_or(self.albedo),
                 pipeline_handle: self.pipeline_handle,
                 mesh_handle: self.mesh_handle,
-                character,
-                manager: self.font_manager.clone(),
+                face: glyph.face,
+                glyph_id: glyph.glyph_id,
+                manager: font_manager.clone(),
+                effects: self.effects,
             };
 
             render_objs.push(render_obj);
-
-            x += metrics.advance_width * scale;
         }
         Ok(render_objs)
     }
@@ -0,0 +1,119 @@
+// text placed in the 3d world, e.g. name tags and labels over models
+// the text is laid out as usual and then billboarded: rotated to face the camera, scaled and moved so that
+// its anchor sits on a point in the world. the transform depends on the camera so it's rebuilt whenever it moves
+
+use nalgebra::{Matrix4, Point3, Rotation3, Scale3, Translation3, Vector3};
+
+use crate::{
+    bind::BindHandle,
+    camera::Camera,
+    pipeline::{Pipeline, PipelineBuilder},
+    render::Render,
+};
+
+use super::{font_bitmap_manager::DistanceField, layout::TextBounds, pipeline::text_pipeline_with};
+
+/// World text is drawn after the scene so that it blends over it, and before debug drawing.
+pub const WORLD_TEXT_ORDER: i32 = 500;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TextSize {
+    /// World units per unit of text size, the text gets smaller the further away it is.
+    World(f32),
+    /// The text size is in pixels on screen however far away the text is, for a viewport this many pixels tall.
+    Screen { viewport_height: f32 },
+}
+
+/// Where text goes in the world, see [crate::text::text_builder::TextBuilder::with_billboard].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Billboard {
+    pub position: Point3<f32>,
+    pub size: TextSize,
+    /// The point of the text's bounds that sits on `position`, from [0, 0] at the bottom left to [1, 1] at the top right.
+    pub anchor: [f32; 2],
+    /// Only turn around the y axis to face the camera so that the text stays upright.
+    pub upright: bool,
+}
+
+impl Billboard {
+    /// Text centered above `position` at one world unit per unit of text size.
+    pub fn new(position: Point3<f32>) -> Self {
+        Self {
+            position,
+            size: TextSize::World(1.0),
+            anchor: [0.5, 0.0],
+            upright: false,
+        }
+    }
+
+    pub fn with_size(mut self, size: TextSize) -> Self {
+        self.size = size;
+        self
+    }
+
+    pub fn with_anchor(mut self, anchor: [f32; 2]) -> Self {
+        self.anchor = anchor;
+        self
+    }
+
+    pub fn with_upright(mut self, upright: bool) -> Self {
+        self.upright = upright;
+        self
+    }
+
+    /// The transform for text with the given bounds, facing `camera`.
+    pub fn transform(&self, camera: &Camera, bounds: &TextBounds) -> Matrix4<f32> {
+        let scale = match self.size {
+            TextSize::World(scale) => scale,
+            TextSize::Screen { viewport_height } => {
+                camera.world_units_per_pixel(&self.position, viewport_height)
+            }
+        };
+        let anchor = [
+            bounds.min[0] + bounds.width() * self.anchor[0],
+            bounds.min[1] + bounds.height() * self.anchor[1],
+        ];
+
+        Translation3::from(self.position.coords).to_homogeneous()
+            * self.facing(camera)
+            * Scale3::new(scale, scale, scale).to_homogeneous()
+            * Translation3::new(-anchor[0], -anchor[1], 0.0).to_homogeneous()
+    }
+
+    // text is laid out in the xy plane looking down +z, turn +z towards the camera
+    fn facing(&self, camera: &Camera) -> Matrix4<f32> {
+        let back = camera.eye - camera.target;
+        if self.upright {
+            let back = Vector3::new(back.x, 0.0, back.z);
+            // looking straight down there's no way to stay upright, fall back to facing the screen
+            if back.norm_squared() > f32::EPSILON {
+                return Rotation3::face_towards(&back, &Vector3::y()).to_homogeneous();
+            }
+        }
+        // the inverse of the view's rotation lines the text up with the screen
+        let mut facing = camera.view().transpose();
+        facing.fixed_view_mut::<3, 1>(0, 3).fill(0.0);
+        facing.fixed_view_mut::<1, 3>(3, 0).fill(0.0);
+        facing
+    }
+}
+
+/// A text pipeline for text in the world, register the atlas as with [crate::text::pipeline::text_pipeline_for].
+///
+/// With `depth_test` the text is hidden behind things in front of it, otherwise it's drawn over everything.
+/// Either way it doesn't write depth, so overlapping labels don't cut holes into each other.
+pub fn world_text_pipeline(
+    render: &mut Render,
+    distance_field: DistanceField,
+    depth_test: bool,
+) -> (Pipeline, BindHandle) {
+    text_pipeline_with(
+        render,
+        distance_field,
+        PipelineBuilder::new()
+            .with_cull_mode(None)
+            .with_depth_test(depth_test)
+            .with_depth_write(false)
+            .with_order(WORLD_TEXT_ORDER),
+    )
+}
@@ -1,27 +1,92 @@
 use std::{collections::HashMap, iter::repeat, path::Path};
 
+use anyhow::{anyhow, Result};
 use generational_arena::Arena;
+use half::f16;
+use image::DynamicImage;
 
 use crate::{
     atlas::{Atlas, RectHandle},
     render::TextureHandle,
 };
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TextureFormat {
     Rgba8Unorm,
+    /// Rgba8 with srgb encoded colors, e.g. albedo textures. Sampling decodes them to linear.
+    Rgba8UnormSrgb,
     R8Unorm,
+    /// Two channels, e.g. the x and y of a tangent space normal map.
+    Rg8Unorm,
     Rgba16,
+    /// Half float HDR colors.
+    Rgba16Float,
+    /// Full float HDR colors, what .hdr and .exr images load as.
+    Rgba32Float,
+    /// Block compressed (BC, ETC2 or ASTC) data, see [Texture::from_compressed_path].
+    /// Only usable when the device supports the format, see [crate::render::Render::supports_texture_format].
+    Compressed(wgpu::TextureFormat),
 }
 
 impl TextureFormat {
+    /// Bytes per pixel, or per block for compressed formats.
     pub fn pixel_size(&self) -> u32 {
         match self {
-            TextureFormat::Rgba8Unorm => 4,
+            TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb => 4,
             TextureFormat::R8Unorm => 1,
-            TextureFormat::Rgba16 => 8,
+            TextureFormat::Rg8Unorm => 2,
+            TextureFormat::Rgba16 | TextureFormat::Rgba16Float => 8,
+            TextureFormat::Rgba32Float => 16,
+            TextureFormat::Compressed(format) => format.block_copy_size(None).unwrap_or(0),
         }
     }
+
+    pub fn wgpu_format(&self) -> wgpu::TextureFormat {
+        match self {
+            TextureFormat::Rgba8Unorm => wgpu::TextureFormat::Rgba8Unorm,
+            TextureFormat::Rgba8UnormSrgb => wgpu::TextureFormat::Rgba8UnormSrgb,
+            TextureFormat::R8Unorm => wgpu::TextureFormat::R8Unorm,
+            TextureFormat::Rg8Unorm => wgpu::TextureFormat::Rg8Unorm,
+            TextureFormat::Rgba16 => wgpu::TextureFormat::Rgba16Unorm,
+            TextureFormat::Rgba16Float => wgpu::TextureFormat::Rgba16Float,
+            TextureFormat::Rgba32Float => wgpu::TextureFormat::Rgba32Float,
+            TextureFormat::Compressed(format) => *format,
+        }
+    }
+
+    pub fn is_compressed(&self) -> bool {
+        matches!(self, TextureFormat::Compressed(_))
+    }
+
+    pub fn is_srgb(&self) -> bool {
+        self.wgpu_format().is_srgb()
+    }
+
+    /// The width and height of a block, 1x1 for uncompressed formats.
+    pub fn block_dimensions(&self) -> (u32, u32) {
+        self.wgpu_format().block_dimensions()
+    }
+
+    /// The number of bytes in a tightly packed row of a `width` wide texture.
+    pub fn bytes_per_row(&self, width: u32) -> u32 {
+        width.div_ceil(self.block_dimensions().0) * self.pixel_size()
+    }
+
+    /// The number of rows in a `height` tall texture, rows of blocks for compressed formats.
+    pub fn rows(&self, height: u32) -> u32 {
+        height.div_ceil(self.block_dimensions().1)
+    }
+
+    /// The number of bytes in a tightly packed `width` x `height` texture.
+    pub fn data_size(&self, width: u32, height: u32) -> usize {
+        self.bytes_per_row(width) as usize * self.rows(height) as usize
+    }
+
+    /// Whether data in this format can be copied as is into a texture of `format`.
+    /// Formats only differing by their srgb-ness are compatible.
+    pub fn is_compatible_with(&self, format: wgpu::TextureFormat) -> bool {
+        self.wgpu_format().remove_srgb_suffix() == format.remove_srgb_suffix()
+    }
 }
 
 pub struct Texture {
@@ -40,19 +105,197 @@ impl Texture {
         self.height
     }
 
+    /// Like [Texture::load] but panics when the texture can't be loaded.
     pub fn from_path<P: AsRef<Path>>(path: P) -> Self {
-        let img = image::open(path).unwrap();
-        let buff = img.to_rgba8();
-        Self {
-            width: buff.width(),
-            height: buff.height(),
-            data: buff.to_vec(),
-            format: TextureFormat::Rgba8Unorm,
+        Self::load(path).unwrap()
+    }
+
+    /// Loads an image as [TextureFormat::Rgba8Unorm], or as [TextureFormat::Rgba32Float] for HDR images (.hdr, .exr).
+    /// KTX2 and DDS files are loaded as is, see [Texture::from_compressed_bytes].
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let bytes = read(path)?;
+        Self::from_bytes(&bytes)
+            .map_err(|err| anyhow!("Failed to load {}: {}", path.display(), err))
+    }
+
+    /// Loads an image and converts it to `format`.
+    /// KTX2 and DDS files are loaded as is, so `format` has to match the file's format.
+    pub fn load_as<P: AsRef<Path>>(path: P, format: TextureFormat) -> Result<Self> {
+        let path = path.as_ref();
+        let bytes = read(path)?;
+        Self::from_bytes_as(&bytes, format)
+            .map_err(|err| anyhow!("Failed to load {}: {}", path.display(), err))
+    }
+
+    /// Decodes an encoded image (png, jpeg, hdr, ...) or a KTX2/DDS file, picking the format like [Texture::load].
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if is_compressed_container(bytes) {
+            return Self::from_compressed_bytes(bytes);
+        }
+        let img = image::load_from_memory(bytes)?;
+        Ok(Self::from_image(&img))
+    }
+
+    /// Decodes an encoded image and converts it to `format`, see [Texture::load_as].
+    pub fn from_bytes_as(bytes: &[u8], format: TextureFormat) -> Result<Self> {
+        if is_compressed_container(bytes) {
+            let texture = Self::from_compressed_bytes(bytes)?;
+            if texture.format != format {
+                return Err(anyhow!(
+                    "Expected a {:?} texture but the file contains {:?}",
+                    format,
+                    texture.format
+                ));
+            }
+            return Ok(texture);
+        }
+        let img = image::load_from_memory(bytes)?;
+        Self::from_image_as(&img, format)
+    }
+
+    /// Converts an image to [TextureFormat::Rgba8Unorm], or to [TextureFormat::Rgba32Float] for float images.
+    pub fn from_image(img: &DynamicImage) -> Self {
+        let format = match img {
+            DynamicImage::ImageRgb32F(_) | DynamicImage::ImageRgba32F(_) => {
+                TextureFormat::Rgba32Float
+            }
+            _ => TextureFormat::Rgba8Unorm,
+        };
+        // only compressed formats can fail
+        Self::from_image_as(img, format).unwrap()
+    }
+
+    /// A magenta and black checkerboard for textures that failed to load, see [crate::render::Render::with_missing_texture_fallback].
+    pub fn missing(format: TextureFormat) -> Result<Self> {
+        const SIZE: u32 = 64;
+        const SQUARE: u32 = 8;
+        let img = image::RgbaImage::from_fn(SIZE, SIZE, |x, y| {
+            if (x / SQUARE + y / SQUARE).is_multiple_of(2) {
+                image::Rgba([255, 0, 255, 255])
+            } else {
+                image::Rgba([0, 0, 0, 255])
+            }
+        });
+        Self::from_image_as(&DynamicImage::ImageRgba8(img), format)
+    }
+
+    /// Converts an image to `format`, compressed formats can't be converted to.
+    pub fn from_image_as(img: &DynamicImage, format: TextureFormat) -> Result<Self> {
+        let data = match format {
+            TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb => img.to_rgba8().into_raw(),
+            TextureFormat::R8Unorm => img.to_luma8().into_raw(),
+            TextureFormat::Rg8Unorm => img
+                .to_rgba8()
+                .pixels()
+                .flat_map(|pixel| [pixel[0], pixel[1]])
+                .collect(),
+            TextureFormat::Rgba16 => img
+                .to_rgba16()
+                .into_raw()
+                .into_iter()
+                .flat_map(u16::to_ne_bytes)
+                .collect(),
+            TextureFormat::Rgba16Float => img
+                .to_rgba32f()
+                .into_raw()
+                .into_iter()
+                .flat_map(|v| f16::from_f32(v).to_ne_bytes())
+                .collect(),
+            TextureFormat::Rgba32Float => img
+                .to_rgba32f()
+                .into_raw()
+                .into_iter()
+                .flat_map(f32::to_ne_bytes)
+                .collect(),
+            TextureFormat::Compressed(format) => {
+                return Err(anyhow!(
+                    "Images can't be converted to compressed formats ({:?}), load a KTX2 or DDS file instead",
+                    format
+                ))
+            }
+        };
+
+        Ok(Self {
+            width: img.width(),
+            height: img.height(),
+            data,
+            format,
+        })
+    }
+
+    /// Loads the first mip level of a pre-compressed KTX2 or DDS file.
+    pub fn from_compressed_path<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let bytes = read(path)?;
+        Self::from_compressed_bytes(&bytes)
+            .map_err(|err| anyhow!("Failed to load {}: {}", path.display(), err))
+    }
+
+    /// Reads the first mip level of a pre-compressed KTX2 or DDS file, detected from the file's magic bytes.
+    pub fn from_compressed_bytes(bytes: &[u8]) -> Result<Self> {
+        let (format, width, height, mut data) = if bytes.starts_with(&KTX2_MAGIC) {
+            let reader = ktx2::Reader::new(bytes).map_err(|err| anyhow!("{}", err))?;
+            let header = reader.header();
+            if let Some(scheme) = header.supercompression_scheme {
+                return Err(anyhow!(
+                    "Supercompressed KTX2 files ({:?}) aren't supported",
+                    scheme
+                ));
+            }
+            let format = header
+                .format
+                .and_then(ktx2_format)
+                .ok_or(anyhow!("Unsupported KTX2 format {:?}", header.format))?;
+            let level = reader
+                .levels()
+                .next()
+                .ok_or(anyhow!("KTX2 file has no mip levels"))?;
+            (
+                format,
+                header.pixel_width,
+                header.pixel_height,
+                level.data.to_vec(),
+            )
+        } else if bytes.starts_with(b"DDS ") {
+            let dds = ddsfile::Dds::read(bytes).map_err(|err| anyhow!("{}", err))?;
+            let format = dds_format(&dds).ok_or(anyhow!(
+                "Unsupported DDS format {:?}",
+                dds.get_dxgi_format()
+                    .map(|format| format!("{:?}", format))
+                    .or(dds.get_d3d_format().map(|format| format!("{:?}", format)))
+            ))?;
+            let data = dds.get_data(0).map_err(|err| anyhow!("{}", err))?;
+            (format, dds.get_width(), dds.get_height(), data.to_vec())
+        } else {
+            return Err(anyhow!("Not a KTX2 or DDS file"));
+        };
+
+        let format = TextureFormat::Compressed(format);
+        let size = format.data_size(width, height);
+        if data.len() < size {
+            return Err(anyhow!(
+                "Expected at least {} bytes of {:?} data but got {}",
+                size,
+                format,
+                data.len()
+            ));
         }
+        // only keep the first mip level
+        data.truncate(size);
+
+        Ok(Self {
+            width,
+            height,
+            data,
+            format,
+        })
     }
 
+    /// Stitches together the textures placed on one page of an atlas.
     pub fn from_atlas(
         atlas: &Atlas,
+        page: u32,
         rect_to_tex: &HashMap<RectHandle, TextureHandle>,
         textures: &Arena<Texture>,
     ) -> Self {
@@ -66,28 +309,38 @@ impl Texture {
             .collect();
         for (rect_handle, texture_handle) in rect_to_tex {
             let rect = atlas.get_rect(*rect_handle).unwrap();
-            let offset: usize = (pixel_size * (rect.x + rect.y * atlas.width))
-                .try_into()
-                .unwrap();
+            if rect.page != page {
+                continue;
+            }
             let texture = textures.get(texture_handle.0).unwrap();
-            let tex_width = texture.width as usize;
+            if texture.width == 0 || texture.height == 0 {
+                continue;
+            }
+
+            let pixel_size = pixel_size as usize;
+            let padding = atlas.padding() as usize;
+            let row_len = texture.width as usize * pixel_size;
 
             // we cannot naively paste the texture data into a 1d array
-            // we need to do it row by row
-            // we'll always be at the same x offset but our y offset can change
-            // and we need to iterate over the texture's rows
-            for row in 0..texture.height as usize {
-                let row_data = texture
-                    .data
-                    .get(
-                        (row * tex_width * pixel_size as usize)
-                            ..((row + 1) * tex_width * pixel_size as usize),
-                    )
-                    .unwrap()
-                    .to_vec();
-                let row_offset = row * atlas.width as usize * pixel_size as usize + offset;
-                // println!("{:?}", row_offset..row_offset + row_data.len());
-                data.splice(row_offset..row_offset + row_data.len(), row_data);
+            // we need to do it row by row, starting at the padding above the texture
+            // the padding gets the texture's edge pixels so that filtering (and mipmapping) at the edges doesn't pick up neighbouring rects
+            for y in 0..texture.height as usize + padding * 2 {
+                let src_row = y.saturating_sub(padding).min(texture.height as usize - 1);
+                let src = &texture.data[src_row * row_len..(src_row + 1) * row_len];
+
+                let dst_y = rect.y as usize - padding + y;
+                let mut dst =
+                    (dst_y * atlas.width as usize + rect.x as usize - padding) * pixel_size;
+                for _ in 0..padding {
+                    data[dst..dst + pixel_size].copy_from_slice(&src[..pixel_size]);
+                    dst += pixel_size;
+                }
+                data[dst..dst + row_len].copy_from_slice(src);
+                dst += row_len;
+                for _ in 0..padding {
+                    data[dst..dst + pixel_size].copy_from_slice(&src[row_len - pixel_size..]);
+                    dst += pixel_size;
+                }
             }
         }
 
@@ -99,3 +352,284 @@ impl Texture {
         }
     }
 }
+
+impl Texture {
+    /// Reads a pixel as rgba floats, channels missing from the format are filled in with 0 (and 1 for alpha).
+    fn read_pixel(&self, x: u32, y: u32) -> [f32; 4] {
+        let size = self.format.pixel_size() as usize;
+        let offset = (y * self.width + x) as usize * size;
+        let bytes = &self.data[offset..offset + size];
+        let unorm8 = |idx: usize| bytes[idx] as f32 / 255.0;
+        let channel = |idx: usize, width: usize| &bytes[idx * width..(idx + 1) * width];
+        match self.format {
+            TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb => {
+                [unorm8(0), unorm8(1), unorm8(2), unorm8(3)]
+            }
+            TextureFormat::R8Unorm => [unorm8(0), 0.0, 0.0, 1.0],
+            TextureFormat::Rg8Unorm => [unorm8(0), unorm8(1), 0.0, 1.0],
+            TextureFormat::Rgba16 => std::array::from_fn(|idx| {
+                u16::from_ne_bytes(channel(idx, 2).try_into().unwrap()) as f32 / 65535.0
+            }),
+            TextureFormat::Rgba16Float => std::array::from_fn(|idx| {
+                f16::from_ne_bytes(channel(idx, 2).try_into().unwrap()).to_f32()
+            }),
+            TextureFormat::Rgba32Float => {
+                std::array::from_fn(|idx| f32::from_ne_bytes(channel(idx, 4).try_into().unwrap()))
+            }
+            TextureFormat::Compressed(_) => unreachable!("compressed textures have no pixels"),
+        }
+    }
+
+    /// Bilinearly samples the texture at normalised coordinates, wrapping horizontally and clamping vertically.
+    fn sample_wrapped(&self, u: f32, v: f32) -> [f32; 4] {
+        let x = u * self.width as f32 - 0.5;
+        let y = (v * self.height as f32 - 0.5).clamp(0.0, (self.height - 1) as f32);
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+        let wrap = |x: f32| x.rem_euclid(self.width as f32) as u32;
+        let (x0, x1) = (wrap(x0), wrap(x0 + 1.0));
+        let (y0, y1) = (y0 as u32, (y0 as u32 + 1).min(self.height - 1));
+
+        let (a, b, c, d) = (
+            self.read_pixel(x0, y0),
+            self.read_pixel(x1, y0),
+            self.read_pixel(x0, y1),
+            self.read_pixel(x1, y1),
+        );
+        std::array::from_fn(|idx| {
+            let top = a[idx] + (b[idx] - a[idx]) * fx;
+            let bottom = c[idx] + (d[idx] - c[idx]) * fx;
+            top + (bottom - top) * fy
+        })
+    }
+}
+
+fn push_pixel(format: TextureFormat, data: &mut Vec<u8>, pixel: [f32; 4]) {
+    let unorm8 = |v: f32| (v.clamp(0.0, 1.0) * 255.0).round() as u8;
+    match format {
+        TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb => data.extend(pixel.map(unorm8)),
+        TextureFormat::R8Unorm => data.push(unorm8(pixel[0])),
+        TextureFormat::Rg8Unorm => data.extend([unorm8(pixel[0]), unorm8(pixel[1])]),
+        TextureFormat::Rgba16 => {
+            for v in pixel {
+                data.extend(((v.clamp(0.0, 1.0) * 65535.0).round() as u16).to_ne_bytes());
+            }
+        }
+        TextureFormat::Rgba16Float => {
+            for v in pixel {
+                data.extend(f16::from_f32(v).to_ne_bytes());
+            }
+        }
+        TextureFormat::Rgba32Float => {
+            for v in pixel {
+                data.extend(v.to_ne_bytes());
+            }
+        }
+        TextureFormat::Compressed(_) => unreachable!("compressed textures have no pixels"),
+    }
+}
+
+/// The six square faces of a cube texture, in wgpu's layer order: +X, -X, +Y, -Y, +Z, -Z.
+/// Upload with [crate::render::Render::write_cubemap], see [crate::skybox::Skybox].
+pub struct Cubemap {
+    pub faces: [Texture; 6],
+}
+
+impl Cubemap {
+    pub fn from_faces(faces: [Texture; 6]) -> Result<Self> {
+        let (size, format) = (faces[0].width, faces[0].format);
+        for face in &faces {
+            if face.width != size || face.height != size {
+                return Err(anyhow!(
+                    "Cubemap faces must be square and the same size, expected {}x{} but got {}x{}",
+                    size,
+                    size,
+                    face.width,
+                    face.height
+                ));
+            }
+            if face.format != format {
+                return Err(anyhow!(
+                    "Cubemap faces must share a format, expected {:?} but got {:?}",
+                    format,
+                    face.format
+                ));
+            }
+        }
+        Ok(Self { faces })
+    }
+
+    /// Loads six faces as `format`, ordered +X, -X, +Y, -Y, +Z, -Z.
+    pub fn load_faces<P: AsRef<Path>>(paths: [P; 6], format: TextureFormat) -> Result<Self> {
+        let [px, nx, py, ny, pz, nz] = paths.map(|path| Texture::load_as(path, format));
+        Self::from_faces([px?, nx?, py?, ny?, pz?, nz?])
+    }
+
+    /// Projects an equirectangular (latitude/longitude) panorama, e.g. an HDR environment, onto six `face_size` faces.
+    /// The faces keep the panorama's format, load it as [TextureFormat::Rgba16Float] for a filterable HDR cubemap.
+    pub fn from_equirectangular(texture: &Texture, face_size: u32) -> Result<Self> {
+        if texture.format.is_compressed() {
+            return Err(anyhow!(
+                "Can't project a compressed ({:?}) panorama",
+                texture.format
+            ));
+        }
+        if texture.width == 0 || texture.height == 0 || face_size == 0 {
+            return Err(anyhow!("Can't project an empty panorama"));
+        }
+
+        let faces = std::array::from_fn(|face| {
+            let mut data = Vec::with_capacity(texture.format.data_size(face_size, face_size));
+            for y in 0..face_size {
+                for x in 0..face_size {
+                    let u = (x as f32 + 0.5) / face_size as f32 * 2.0 - 1.0;
+                    let v = (y as f32 + 0.5) / face_size as f32 * 2.0 - 1.0;
+                    let direction = match face {
+                        0 => [1.0, -v, -u],
+                        1 => [-1.0, -v, u],
+                        2 => [u, 1.0, v],
+                        3 => [u, -1.0, -v],
+                        4 => [u, -v, 1.0],
+                        _ => [-u, -v, -1.0],
+                    };
+                    let length = direction.iter().map(|v| v * v).sum::<f32>().sqrt();
+                    let [dx, dy, dz] = direction.map(|v| v / length);
+
+                    let longitude = dz.atan2(dx);
+                    let latitude = dy.asin();
+                    let pixel = texture.sample_wrapped(
+                        0.5 + longitude / std::f32::consts::TAU,
+                        0.5 - latitude / std::f32::consts::PI,
+                    );
+                    push_pixel(texture.format, &mut data, pixel);
+                }
+            }
+
+            Texture {
+                data,
+                width: face_size,
+                height: face_size,
+                format: texture.format,
+            }
+        });
+
+        Ok(Self { faces })
+    }
+
+    pub fn face_size(&self) -> u32 {
+        self.faces[0].width
+    }
+
+    pub fn format(&self) -> TextureFormat {
+        self.faces[0].format
+    }
+}
+
+const KTX2_MAGIC: [u8; 12] = [
+    0xAB, 0x4B, 0x54, 0x58, 0x20, 0x32, 0x30, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A,
+];
+
+fn is_compressed_container(bytes: &[u8]) -> bool {
+    bytes.starts_with(&KTX2_MAGIC) || bytes.starts_with(b"DDS ")
+}
+
+fn read(path: &Path) -> Result<Vec<u8>> {
+    std::fs::read(path).map_err(|err| anyhow!("Failed to read {}: {}", path.display(), err))
+}
+
+fn ktx2_format(format: ktx2::Format) -> Option<wgpu::TextureFormat> {
+    use ktx2::Format;
+    use wgpu::{AstcBlock, AstcChannel, TextureFormat as F};
+
+    Some(match format {
+        Format::BC1_RGBA_UNORM_BLOCK | Format::BC1_RGB_UNORM_BLOCK => F::Bc1RgbaUnorm,
+        Format::BC1_RGBA_SRGB_BLOCK | Format::BC1_RGB_SRGB_BLOCK => F::Bc1RgbaUnormSrgb,
+        Format::BC2_UNORM_BLOCK => F::Bc2RgbaUnorm,
+        Format::BC2_SRGB_BLOCK => F::Bc2RgbaUnormSrgb,
+        Format::BC3_UNORM_BLOCK => F::Bc3RgbaUnorm,
+        Format::BC3_SRGB_BLOCK => F::Bc3RgbaUnormSrgb,
+        Format::BC4_UNORM_BLOCK => F::Bc4RUnorm,
+        Format::BC4_SNORM_BLOCK => F::Bc4RSnorm,
+        Format::BC5_UNORM_BLOCK => F::Bc5RgUnorm,
+        Format::BC5_SNORM_BLOCK => F::Bc5RgSnorm,
+        Format::BC6H_UFLOAT_BLOCK => F::Bc6hRgbUfloat,
+        Format::BC6H_SFLOAT_BLOCK => F::Bc6hRgbFloat,
+        Format::BC7_UNORM_BLOCK => F::Bc7RgbaUnorm,
+        Format::BC7_SRGB_BLOCK => F::Bc7RgbaUnormSrgb,
+        Format::ETC2_R8G8B8_UNORM_BLOCK => F::Etc2Rgb8Unorm,
+        Format::ETC2_R8G8B8_SRGB_BLOCK => F::Etc2Rgb8UnormSrgb,
+        Format::ETC2_R8G8B8A1_UNORM_BLOCK => F::Etc2Rgb8A1Unorm,
+        Format::ETC2_R8G8B8A1_SRGB_BLOCK => F::Etc2Rgb8A1UnormSrgb,
+        Format::ETC2_R8G8B8A8_UNORM_BLOCK => F::Etc2Rgba8Unorm,
+        Format::ETC2_R8G8B8A8_SRGB_BLOCK => F::Etc2Rgba8UnormSrgb,
+        Format::EAC_R11_UNORM_BLOCK => F::EacR11Unorm,
+        Format::EAC_R11_SNORM_BLOCK => F::EacR11Snorm,
+        Format::EAC_R11G11_UNORM_BLOCK => F::EacRg11Unorm,
+        Format::EAC_R11G11_SNORM_BLOCK => F::EacRg11Snorm,
+        Format::ASTC_4x4_UNORM_BLOCK => F::Astc {
+            block: AstcBlock::B4x4,
+            channel: AstcChannel::Unorm,
+        },
+        Format::ASTC_4x4_SRGB_BLOCK => F::Astc {
+            block: AstcBlock::B4x4,
+            channel: AstcChannel::UnormSrgb,
+        },
+        Format::ASTC_5x5_UNORM_BLOCK => F::Astc {
+            block: AstcBlock::B5x5,
+            channel: AstcChannel::Unorm,
+        },
+        Format::ASTC_5x5_SRGB_BLOCK => F::Astc {
+            block: AstcBlock::B5x5,
+            channel: AstcChannel::UnormSrgb,
+        },
+        Format::ASTC_6x6_UNORM_BLOCK => F::Astc {
+            block: AstcBlock::B6x6,
+            channel: AstcChannel::Unorm,
+        },
+        Format::ASTC_6x6_SRGB_BLOCK => F::Astc {
+            block: AstcBlock::B6x6,
+            channel: AstcChannel::UnormSrgb,
+        },
+        Format::ASTC_8x8_UNORM_BLOCK => F::Astc {
+            block: AstcBlock::B8x8,
+            channel: AstcChannel::Unorm,
+        },
+        Format::ASTC_8x8_SRGB_BLOCK => F::Astc {
+            block: AstcBlock::B8x8,
+            channel: AstcChannel::UnormSrgb,
+        },
+        _ => return None,
+    })
+}
+
+fn dds_format(dds: &ddsfile::Dds) -> Option<wgpu::TextureFormat> {
+    use ddsfile::{D3DFormat, DxgiFormat};
+    use wgpu::TextureFormat as F;
+
+    if let Some(format) = dds.get_dxgi_format() {
+        return Some(match format {
+            DxgiFormat::BC1_UNorm => F::Bc1RgbaUnorm,
+            DxgiFormat::BC1_UNorm_sRGB => F::Bc1RgbaUnormSrgb,
+            DxgiFormat::BC2_UNorm => F::Bc2RgbaUnorm,
+            DxgiFormat::BC2_UNorm_sRGB => F::Bc2RgbaUnormSrgb,
+            DxgiFormat::BC3_UNorm => F::Bc3RgbaUnorm,
+            DxgiFormat::BC3_UNorm_sRGB => F::Bc3RgbaUnormSrgb,
+            DxgiFormat::BC4_UNorm => F::Bc4RUnorm,
+            DxgiFormat::BC4_SNorm => F::Bc4RSnorm,
+            DxgiFormat::BC5_UNorm => F::Bc5RgUnorm,
+            DxgiFormat::BC5_SNorm => F::Bc5RgSnorm,
+            DxgiFormat::BC6H_UF16 => F::Bc6hRgbUfloat,
+            DxgiFormat::BC6H_SF16 => F::Bc6hRgbFloat,
+            DxgiFormat::BC7_UNorm => F::Bc7RgbaUnorm,
+            DxgiFormat::BC7_UNorm_sRGB => F::Bc7RgbaUnormSrgb,
+            _ => return None,
+        });
+    }
+
+    match dds.get_d3d_format()? {
+        D3DFormat::DXT1 => Some(F::Bc1RgbaUnorm),
+        D3DFormat::DXT2 | D3DFormat::DXT3 => Some(F::Bc2RgbaUnorm),
+        D3DFormat::DXT4 | D3DFormat::DXT5 => Some(F::Bc3RgbaUnorm),
+        _ => None,
+    }
+}
@@ -0,0 +1,8 @@
+// arbitrary 2d shapes, for everything the sdf primitives in shapes can't do:
+// - paths are built with lyon (lines, beziers, arcs) or parsed from svg path data
+// - fills and strokes (caps, joins, dashes) are tessellated on the cpu into a PathGeometry
+// - a PathGeometry is drawn like any other mesh with the shape pipeline
+// - a subset of svg is enough for icons, see svg::Svg
+
+pub mod path;
+pub mod svg;
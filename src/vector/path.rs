@@ -0,0 +1,314 @@
+use anyhow::{anyhow, Result};
+use lyon::{
+    extra::parser::{ParserOptions, PathParser, Source},
+    math::Point,
+    path::{iterator::PathIterator, Builder, PathEvent},
+    tessellation::{
+        BuffersBuilder, FillOptions, FillTessellator, FillVertex, StrokeOptions, StrokeTessellator,
+        StrokeVertex, VertexBuffers,
+    },
+};
+
+pub use lyon::path::{FillRule, Path};
+
+use crate::{
+    geometry::{Geometry, IndexFormat, Indices},
+    plain::Plain,
+    shapes::{LineCap, ShapeVertex},
+};
+
+/// How far tessellated curves may stray from the real curve, in path units.
+pub const DEFAULT_TOLERANCE: f32 = 0.1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LineJoin {
+    /// Sharp corners, beveled once they get longer than the miter limit.
+    Miter,
+    Round,
+    Bevel,
+}
+
+#[derive(Clone, Debug)]
+pub struct StrokeStyle {
+    pub width: f32,
+    pub cap: LineCap,
+    pub join: LineJoin,
+    /// Miters longer than this many times the stroke width are beveled instead, at least 1.
+    pub miter_limit: f32,
+    /// Alternating dash and gap lengths, a solid stroke when empty.
+    /// Patterns with an odd number of lengths are repeated to make them even, like in svg.
+    pub dashes: Vec<f32>,
+    /// How far into the dash pattern the stroke starts.
+    pub dash_offset: f32,
+}
+
+impl Default for StrokeStyle {
+    fn default() -> Self {
+        Self {
+            width: 1.0,
+            cap: LineCap::Butt,
+            join: LineJoin::Miter,
+            miter_limit: 4.0,
+            dashes: Vec::new(),
+            dash_offset: 0.0,
+        }
+    }
+}
+
+impl StrokeStyle {
+    pub fn new(width: f32) -> Self {
+        Self {
+            width,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_cap(mut self, cap: LineCap) -> Self {
+        self.cap = cap;
+        self
+    }
+
+    pub fn with_join(mut self, join: LineJoin) -> Self {
+        self.join = join;
+        self
+    }
+
+    pub fn with_miter_limit(mut self, miter_limit: f32) -> Self {
+        self.miter_limit = miter_limit;
+        self
+    }
+
+    pub fn with_dashes(mut self, dashes: Vec<f32>, offset: f32) -> Self {
+        self.dashes = dashes;
+        self.dash_offset = offset;
+        self
+    }
+}
+
+/// Triangles in the path's coordinates, on the z = 0 plane.
+///
+/// Draw it with [crate::shapes::shape_pipeline], the instance's albedo is the colour:
+///
+/// ```ignore
+/// let geometry = fill_path(&parse_path("M 0 0 L 10 0 L 5 10 Z")?, FillRule::NonZero, DEFAULT_TOLERANCE)?;
+/// let mesh = render.add_mesh::<PathGeometry, ShapeInstance, BasicMaterial>(Mesh {
+///     material: BasicMaterial {},
+///     geometry,
+/// });
+///
+/// // every frame
+/// render.add_instance(mesh, pipeline, ShapeInstance { transform, albedo });
+/// ```
+#[derive(Debug)]
+pub struct PathGeometry {
+    pub vertices: Vec<ShapeVertex>,
+    pub indices: Indices,
+}
+
+impl PathGeometry {
+    fn from_buffers(buffers: VertexBuffers<ShapeVertex, u32>) -> Self {
+        let indices = Indices::for_vertex_count(buffers.indices, buffers.vertices.len());
+        Self {
+            vertices: buffers.vertices,
+            indices,
+        }
+    }
+
+    /// Paths without an area (or strokes without a length) don't produce any triangles.
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+}
+
+impl Geometry for PathGeometry {
+    fn contents(&self) -> &[u8] {
+        self.vertices.as_bytes()
+    }
+
+    fn length(&self) -> u32 {
+        self.vertices.len() as u32
+    }
+
+    fn indices(&self) -> Option<&[u8]> {
+        Some(self.indices.as_bytes())
+    }
+
+    fn index_format(&self) -> IndexFormat {
+        self.indices.format()
+    }
+}
+
+fn shape_vertex(position: Point) -> ShapeVertex {
+    ShapeVertex {
+        pos: [position.x, position.y, 0.0],
+    }
+}
+
+/// Parses svg path data, e.g. `M 0 0 L 10 0 Q 10 10 0 10 Z`.
+pub fn parse_path(data: &str) -> Result<Path> {
+    let mut builder = Path::builder();
+    PathParser::new()
+        .parse(
+            &ParserOptions::DEFAULT,
+            &mut Source::new(data.chars()),
+            &mut builder,
+        )
+        .map_err(|err| anyhow!("Invalid path data: {}", err))?;
+    Ok(builder.build())
+}
+
+/// Tessellates the inside of `path`, open subpaths are closed with a straight line.
+pub fn fill_path(path: &Path, rule: FillRule, tolerance: f32) -> Result<PathGeometry> {
+    let mut buffers = VertexBuffers::new();
+    FillTessellator::new()
+        .tessellate_path(
+            path,
+            &FillOptions::tolerance(tolerance).with_fill_rule(rule),
+            &mut BuffersBuilder::new(&mut buffers, |vertex: FillVertex| {
+                shape_vertex(vertex.position())
+            }),
+        )
+        .map_err(|err| anyhow!("Couldn't fill path: {}", err))?;
+    Ok(PathGeometry::from_buffers(buffers))
+}
+
+/// Tessellates the outline of `path`, centred on the path.
+pub fn stroke_path(path: &Path, style: &StrokeStyle, tolerance: f32) -> Result<PathGeometry> {
+    let cap = match style.cap {
+        LineCap::Butt => lyon::path::LineCap::Butt,
+        LineCap::Round => lyon::path::LineCap::Round,
+        LineCap::Square => lyon::path::LineCap::Square,
+    };
+    let join = match style.join {
+        LineJoin::Miter => lyon::path::LineJoin::Miter,
+        LineJoin::Round => lyon::path::LineJoin::Round,
+        LineJoin::Bevel => lyon::path::LineJoin::Bevel,
+    };
+    let options = StrokeOptions::tolerance(tolerance)
+        .with_line_width(style.width)
+        .with_line_cap(cap)
+        .with_line_join(join)
+        // lyon asserts on limits below 1
+        .with_miter_limit(style.miter_limit.max(StrokeOptions::MINIMUM_MITER_LIMIT));
+
+    let dashed;
+    let path = if style.dashes.is_empty() {
+        path
+    } else {
+        dashed = dash_path(path, &style.dashes, style.dash_offset, tolerance);
+        &dashed
+    };
+
+    let mut buffers = VertexBuffers::new();
+    StrokeTessellator::new()
+        .tessellate_path(
+            path,
+            &options,
+            &mut BuffersBuilder::new(&mut buffers, |vertex: StrokeVertex| {
+                shape_vertex(vertex.position())
+            }),
+        )
+        .map_err(|err| anyhow!("Couldn't stroke path: {}", err))?;
+    Ok(PathGeometry::from_buffers(buffers))
+}
+
+/// Cuts `path` into a subpath per dash, see [StrokeStyle::dashes]. Curves are flattened into lines first.
+///
+/// The pattern starts over at the beginning of every subpath.
+/// Patterns that don't add up to a positive length or contain negative lengths leave the path as it is.
+pub fn dash_path(path: &Path, dashes: &[f32], offset: f32, tolerance: f32) -> Path {
+    let pattern = if dashes.len() % 2 == 1 {
+        [dashes, dashes].concat()
+    } else {
+        dashes.to_vec()
+    };
+    if pattern.iter().sum::<f32>() <= 0.0 || pattern.iter().any(|length| *length < 0.0) {
+        return path.clone();
+    }
+
+    let mut dasher = Dasher {
+        pattern: &pattern,
+        offset,
+        builder: Path::builder(),
+        idx: 0,
+        remaining: 0.0,
+    };
+    for event in path.iter().flattened(tolerance) {
+        match event {
+            PathEvent::Begin { at } => dasher.begin(at),
+            PathEvent::Line { from, to } => dasher.line(from, to),
+            PathEvent::End { last, first, close } => {
+                if close {
+                    dasher.line(last, first);
+                }
+                dasher.end();
+            }
+            // flattening leaves nothing but lines
+            _ => {}
+        }
+    }
+    dasher.builder.build()
+}
+
+struct Dasher<'a> {
+    pattern: &'a [f32],
+    offset: f32,
+    builder: Builder,
+    // where we are in the pattern, even entries are dashes and odd ones gaps
+    idx: usize,
+    remaining: f32,
+}
+
+impl<'a> Dasher<'a> {
+    fn in_dash(&self) -> bool {
+        self.idx.is_multiple_of(2)
+    }
+
+    fn advance(&mut self) {
+        self.idx = (self.idx + 1) % self.pattern.len();
+        self.remaining = self.pattern[self.idx];
+    }
+
+    fn begin(&mut self, at: Point) {
+        let total = self.pattern.iter().sum::<f32>();
+        let mut offset = self.offset.rem_euclid(total);
+        self.idx = 0;
+        self.remaining = self.pattern[0];
+        while offset >= self.remaining {
+            offset -= self.remaining;
+            self.advance();
+        }
+        self.remaining -= offset;
+
+        if self.in_dash() {
+            self.builder.begin(at);
+        }
+    }
+
+    fn line(&mut self, from: Point, to: Point) {
+        let length = (to - from).length();
+        let mut travelled = 0.0;
+        while length - travelled > self.remaining {
+            travelled += self.remaining;
+            let at = from.lerp(to, travelled / length);
+            if self.in_dash() {
+                self.builder.line_to(at);
+                self.builder.end(false);
+            } else {
+                self.builder.begin(at);
+            }
+            self.advance();
+        }
+        self.remaining -= length - travelled;
+
+        if self.in_dash() {
+            self.builder.line_to(to);
+        }
+    }
+
+    fn end(&mut self) {
+        if self.in_dash() {
+            self.builder.end(false);
+        }
+    }
+}
@@ -0,0 +1,510 @@
+use anyhow::{anyhow, Result};
+use lyon::{
+    math::{point, vector, Angle, Box2D, Point, Transform},
+    path::{builder::BorderRadii, traits::SvgPathBuilder, ArcFlags, Polygon, Winding},
+};
+use roxmltree::{Document, Node};
+
+use crate::{
+    shapes::LineCap,
+    vector::path::{
+        fill_path, parse_path, stroke_path, FillRule, LineJoin, Path, PathGeometry, StrokeStyle,
+    },
+};
+
+#[derive(Clone, Debug)]
+pub struct SvgFill {
+    pub color: [f32; 4],
+    pub rule: FillRule,
+}
+
+#[derive(Clone, Debug)]
+pub struct SvgStroke {
+    pub color: [f32; 4],
+    pub style: StrokeStyle,
+}
+
+/// A single element of the svg, with its transforms already applied to the path.
+#[derive(Clone, Debug)]
+pub struct SvgShape {
+    pub path: Path,
+    pub fill: Option<SvgFill>,
+    pub stroke: Option<SvgStroke>,
+}
+
+/// The subset of svg that icons tend to use:
+/// - `path`, `rect`, `circle`, `ellipse`, `line`, `polyline` and `polygon` inside of any number of `g`s
+/// - solid colour fills and strokes, with fill rules, caps, joins, dashes and opacity
+/// - `transform`s and `style` attributes
+///
+/// Gradients, patterns, text, `use`, clipping and masking aren't supported, those elements are skipped.
+/// So are fills and strokes with gradients or colours other than hex, `rgb()` and the basic named ones, with a warning.
+///
+/// Coordinates are the view box's, flipped so that +y is up like everywhere else and the view box's bottom left is at the origin.
+#[derive(Clone, Debug)]
+pub struct Svg {
+    pub width: f32,
+    pub height: f32,
+    /// In the order they're drawn.
+    pub shapes: Vec<SvgShape>,
+}
+
+impl Svg {
+    pub fn parse(svg: &str) -> Result<Self> {
+        let document = Document::parse(svg).map_err(|err| anyhow!("Invalid svg: {}", err))?;
+        let root = document.root_element();
+        if root.tag_name().name() != "svg" {
+            return Err(anyhow!(
+                "Expected an svg element, got {}",
+                root.tag_name().name()
+            ));
+        }
+
+        let [x, y, width, height] = match root.attribute("viewBox") {
+            Some(view_box) => numbers(view_box)?
+                .try_into()
+                .map_err(|_| anyhow!("Invalid viewBox {}", view_box))?,
+            None => [
+                0.0,
+                0.0,
+                length(root.attribute("width").unwrap_or("100"))?,
+                length(root.attribute("height").unwrap_or("100"))?,
+            ],
+        };
+
+        let style = Style {
+            transform: Transform::translation(-x, -(y + height)).then_scale(1.0, -1.0),
+            ..Default::default()
+        };
+        let mut shapes = Vec::new();
+        visit(root, &style, &mut shapes)?;
+
+        Ok(Self {
+            width,
+            height,
+            shapes,
+        })
+    }
+
+    pub fn load<P: AsRef<std::path::Path>>(path: P) -> Result<Self> {
+        let svg = std::fs::read_to_string(path)?;
+        Self::parse(&svg)
+    }
+
+    /// A mesh and colour for every fill and stroke in the order they're drawn, see [crate::vector::path::PathGeometry].
+    pub fn tessellate(&self, tolerance: f32) -> Result<Vec<(PathGeometry, [f32; 4])>> {
+        let mut meshes = Vec::new();
+        for shape in &self.shapes {
+            if let Some(fill) = &shape.fill {
+                meshes.push((fill_path(&shape.path, fill.rule, tolerance)?, fill.color));
+            }
+            if let Some(stroke) = &shape.stroke {
+                meshes.push((
+                    stroke_path(&shape.path, &stroke.style, tolerance)?,
+                    stroke.color,
+                ));
+            }
+        }
+        meshes.retain(|(geometry, _)| !geometry.is_empty());
+        Ok(meshes)
+    }
+}
+
+// the inherited presentation attributes
+#[derive(Clone)]
+struct Style {
+    fill: Option<[f32; 4]>,
+    fill_rule: FillRule,
+    fill_opacity: f32,
+    stroke: Option<[f32; 4]>,
+    stroke_opacity: f32,
+    stroke_style: StrokeStyle,
+    opacity: f32,
+    transform: Transform,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Self {
+            fill: Some([0.0, 0.0, 0.0, 1.0]),
+            fill_rule: FillRule::NonZero,
+            fill_opacity: 1.0,
+            stroke: None,
+            stroke_opacity: 1.0,
+            stroke_style: StrokeStyle::default(),
+            opacity: 1.0,
+            transform: Transform::identity(),
+        }
+    }
+}
+
+impl Style {
+    fn child(&self, node: Node) -> Result<Self> {
+        let mut style = self.clone();
+        // the element's own opacity, multiplied into the inherited one once the attributes are in
+        style.opacity = 1.0;
+        if let Some(transform) = node.attribute("transform") {
+            style.transform = parse_transform(transform)?.then(&self.transform);
+        }
+
+        // style declarations win over presentation attributes
+        for attribute in node.attributes() {
+            style.set(attribute.name(), attribute.value())?;
+        }
+        let declarations = node.attribute("style").unwrap_or_default();
+        for declaration in declarations.split(';') {
+            if let Some((name, value)) = declaration.split_once(':') {
+                style.set(name.trim(), value.trim())?;
+            }
+        }
+        style.opacity *= self.opacity;
+
+        Ok(style)
+    }
+
+    fn set(&mut self, name: &str, value: &str) -> Result<()> {
+        if value == "inherit" {
+            return Ok(());
+        }
+
+        match name {
+            "fill" => self.fill = paint(value)?,
+            "fill-rule" => {
+                self.fill_rule = match value {
+                    "evenodd" => FillRule::EvenOdd,
+                    _ => FillRule::NonZero,
+                }
+            }
+            "fill-opacity" => self.fill_opacity = opacity(value)?,
+            "stroke" => self.stroke = paint(value)?,
+            "stroke-opacity" => self.stroke_opacity = opacity(value)?,
+            "stroke-width" => self.stroke_style.width = length(value)?,
+            "stroke-linecap" => {
+                self.stroke_style.cap = match value {
+                    "round" => LineCap::Round,
+                    "square" => LineCap::Square,
+                    _ => LineCap::Butt,
+                }
+            }
+            "stroke-linejoin" => {
+                self.stroke_style.join = match value {
+                    "round" => LineJoin::Round,
+                    "bevel" => LineJoin::Bevel,
+                    _ => LineJoin::Miter,
+                }
+            }
+            "stroke-miterlimit" => self.stroke_style.miter_limit = number(value)?,
+            "stroke-dasharray" => {
+                self.stroke_style.dashes = match value {
+                    "none" => Vec::new(),
+                    _ => numbers(value)?,
+                }
+            }
+            "stroke-dashoffset" => self.stroke_style.dash_offset = length(value)?,
+            // unlike the others opacity isn't inherited, it applies to the element and everything in it
+            "opacity" => self.opacity = opacity(value)?,
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn shape(&self, path: Path) -> SvgShape {
+        // strokes are tessellated after the transform, so they have to be scaled along with it
+        let scale = self.transform.determinant().abs().sqrt();
+        let mut stroke_style = self.stroke_style.clone();
+        stroke_style.width *= scale;
+        stroke_style.dash_offset *= scale;
+        stroke_style
+            .dashes
+            .iter_mut()
+            .for_each(|dash| *dash *= scale);
+
+        let with_opacity =
+            |[r, g, b, a]: [f32; 4], opacity: f32| [r, g, b, a * opacity * self.opacity];
+        SvgShape {
+            path: path.transformed(&self.transform),
+            fill: self.fill.map(|color| SvgFill {
+                color: with_opacity(color, self.fill_opacity),
+                rule: self.fill_rule,
+            }),
+            stroke: self.stroke.map(|color| SvgStroke {
+                color: with_opacity(color, self.stroke_opacity),
+                style: stroke_style,
+            }),
+        }
+    }
+}
+
+fn visit(node: Node, parent: &Style, shapes: &mut Vec<SvgShape>) -> Result<()> {
+    if node.attribute("display") == Some("none") {
+        return Ok(());
+    }
+    let style = parent.child(node)?;
+
+    let path = match node.tag_name().name() {
+        // nested svgs are treated like groups, their viewports are ignored
+        "svg" | "g" => {
+            for child in node.children().filter(Node::is_element) {
+                visit(child, &style, shapes)?;
+            }
+            None
+        }
+        "path" => Some(parse_path(node.attribute("d").unwrap_or_default())?),
+        "rect" => rect(node)?,
+        "circle" => {
+            let radius = attribute(node, "r")?;
+            if radius > 0.0 {
+                let mut builder = Path::builder();
+                builder.add_circle(
+                    point(attribute(node, "cx")?, attribute(node, "cy")?),
+                    radius,
+                    Winding::Positive,
+                );
+                Some(builder.build())
+            } else {
+                None
+            }
+        }
+        "ellipse" => {
+            let radii = vector(attribute(node, "rx")?, attribute(node, "ry")?);
+            if radii.x > 0.0 && radii.y > 0.0 {
+                let mut builder = Path::builder();
+                builder.add_ellipse(
+                    point(attribute(node, "cx")?, attribute(node, "cy")?),
+                    radii,
+                    Angle::zero(),
+                    Winding::Positive,
+                );
+                Some(builder.build())
+            } else {
+                None
+            }
+        }
+        "line" => {
+            let mut builder = Path::builder();
+            builder.begin(point(attribute(node, "x1")?, attribute(node, "y1")?));
+            builder.line_to(point(attribute(node, "x2")?, attribute(node, "y2")?));
+            builder.end(false);
+            Some(builder.build())
+        }
+        name @ ("polyline" | "polygon") => {
+            let points = numbers(node.attribute("points").unwrap_or_default())?
+                .chunks_exact(2)
+                .map(|xy| point(xy[0], xy[1]))
+                .collect::<Vec<Point>>();
+            let mut builder = Path::builder();
+            if points.len() > 1 {
+                builder.add_polygon(Polygon {
+                    points: &points,
+                    closed: name == "polygon",
+                });
+            }
+            Some(builder.build())
+        }
+        // defs, gradients, text etc.
+        _ => None,
+    };
+
+    if let Some(path) = path {
+        shapes.push(style.shape(path));
+    }
+    Ok(())
+}
+
+fn rect(node: Node) -> Result<Option<Path>> {
+    let (x, y) = (attribute(node, "x")?, attribute(node, "y")?);
+    let (width, height) = (attribute(node, "width")?, attribute(node, "height")?);
+    if width <= 0.0 || height <= 0.0 {
+        return Ok(None);
+    }
+
+    // a missing radius takes the other one's value, both are clamped to half the rect
+    let rx = node.attribute("rx").map(length).transpose()?;
+    let ry = node.attribute("ry").map(length).transpose()?;
+    let rx = rx.or(ry).unwrap_or(0.0).clamp(0.0, width / 2.0);
+    let ry = ry.or(Some(rx)).unwrap_or(0.0).clamp(0.0, height / 2.0);
+
+    let rect = Box2D::new(point(x, y), point(x + width, y + height));
+    if rx == ry {
+        let mut builder = Path::builder();
+        builder.add_rounded_rectangle(&rect, &BorderRadii::new(rx), Winding::Positive);
+        return Ok(Some(builder.build()));
+    }
+
+    // elliptical corners
+    let radii = vector(rx, ry);
+    let flags = ArcFlags {
+        large_arc: false,
+        sweep: true,
+    };
+    let mut builder = Path::svg_builder();
+    builder.move_to(point(x + rx, y));
+    builder.line_to(point(x + width - rx, y));
+    builder.arc_to(radii, Angle::zero(), flags, point(x + width, y + ry));
+    builder.line_to(point(x + width, y + height - ry));
+    builder.arc_to(
+        radii,
+        Angle::zero(),
+        flags,
+        point(x + width - rx, y + height),
+    );
+    builder.line_to(point(x + rx, y + height));
+    builder.arc_to(radii, Angle::zero(), flags, point(x, y + height - ry));
+    builder.line_to(point(x, y + ry));
+    builder.arc_to(radii, Angle::zero(), flags, point(x + rx, y));
+    builder.close();
+    Ok(Some(builder.build()))
+}
+
+/// A length attribute, missing ones are 0.
+fn attribute(node: Node, name: &str) -> Result<f32> {
+    node.attribute(name).map_or(Ok(0.0), length)
+}
+
+fn number(value: &str) -> Result<f32> {
+    value
+        .trim()
+        .parse()
+        .map_err(|_| anyhow!("Invalid number {}", value))
+}
+
+/// Only user units are supported, which is what px are.
+fn length(value: &str) -> Result<f32> {
+    number(value.trim().trim_end_matches("px"))
+}
+
+fn opacity(value: &str) -> Result<f32> {
+    let opacity = match value.strip_suffix('%') {
+        Some(percent) => number(percent)? / 100.0,
+        None => number(value)?,
+    };
+    Ok(opacity.clamp(0.0, 1.0))
+}
+
+/// Numbers separated by commas and/or whitespace.
+fn numbers(value: &str) -> Result<Vec<f32>> {
+    value
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|number| !number.is_empty())
+        .map(length)
+        .collect()
+}
+
+fn parse_transform(value: &str) -> Result<Transform> {
+    let mut transform = Transform::identity();
+    for function in value.split(')') {
+        let function = function.trim().trim_start_matches(',').trim();
+        if function.is_empty() {
+            continue;
+        }
+
+        let (name, args) = function
+            .split_once('(')
+            .ok_or(anyhow!("Invalid transform {}", value))?;
+        let next = match (name.trim(), numbers(args)?.as_slice()) {
+            ("matrix", &[a, b, c, d, e, f]) => Transform::new(a, b, c, d, e, f),
+            ("translate", &[x]) => Transform::translation(x, 0.0),
+            ("translate", &[x, y]) => Transform::translation(x, y),
+            ("scale", &[s]) => Transform::scale(s, s),
+            ("scale", &[x, y]) => Transform::scale(x, y),
+            ("rotate", &[angle]) => Transform::rotation(Angle::degrees(angle)),
+            ("rotate", &[angle, x, y]) => Transform::translation(-x, -y)
+                .then_rotate(Angle::degrees(angle))
+                .then_translate(vector(x, y)),
+            ("skewX", &[angle]) => {
+                Transform::new(1.0, 0.0, angle.to_radians().tan(), 1.0, 0.0, 0.0)
+            }
+            ("skewY", &[angle]) => {
+                Transform::new(1.0, angle.to_radians().tan(), 0.0, 1.0, 0.0, 0.0)
+            }
+            _ => return Err(anyhow!("Invalid transform {}", function)),
+        };
+        // the rightmost transform is applied first
+        transform = next.then(&transform);
+    }
+    Ok(transform)
+}
+
+/// A fill or stroke colour, `None` for `none` and anything that isn't supported.
+fn paint(value: &str) -> Result<Option<[f32; 4]>> {
+    if value.starts_with("url(") {
+        log::warn!(
+            "Gradients and patterns aren't supported, skipping {}",
+            value
+        );
+        return Ok(None);
+    }
+    if let Some(hex) = value.strip_prefix('#') {
+        let digits = hex
+            .chars()
+            .map(|c| c.to_digit(16).map(|digit| digit as f32))
+            .collect::<Option<Vec<_>>>()
+            .ok_or(anyhow!("Invalid colour {}", value))?;
+        let channels = match digits.len() {
+            // #rgb(a), each digit is doubled
+            3 | 4 => digits.iter().map(|digit| digit * 17.0).collect::<Vec<_>>(),
+            6 | 8 => digits
+                .chunks(2)
+                .map(|pair| pair[0] * 16.0 + pair[1])
+                .collect(),
+            _ => return Err(anyhow!("Invalid colour {}", value)),
+        };
+        let alpha = channels.get(3).map_or(1.0, |alpha| alpha / 255.0);
+        return Ok(Some([
+            channels[0] / 255.0,
+            channels[1] / 255.0,
+            channels[2] / 255.0,
+            alpha,
+        ]));
+    }
+    if let Some(args) = value
+        .strip_prefix("rgba(")
+        .or(value.strip_prefix("rgb("))
+        .and_then(|args| args.strip_suffix(')'))
+    {
+        let args = args.split(',').map(str::trim).collect::<Vec<_>>();
+        if args.len() != 3 && args.len() != 4 {
+            return Err(anyhow!("Invalid colour {}", value));
+        }
+        let channel = |arg: &str| -> Result<f32> {
+            match arg.strip_suffix('%') {
+                Some(percent) => Ok(number(percent)? / 100.0),
+                None => Ok(number(arg)? / 255.0),
+            }
+        };
+        let alpha = args.get(3).map_or(Ok(1.0), |alpha| opacity(alpha))?;
+        return Ok(Some([
+            channel(args[0])?,
+            channel(args[1])?,
+            channel(args[2])?,
+            alpha,
+        ]));
+    }
+
+    let color = match value {
+        "none" | "transparent" => return Ok(None),
+        // there's no text to take a colour from
+        "black" | "currentColor" => [0.0, 0.0, 0.0],
+        "white" => [1.0, 1.0, 1.0],
+        "red" => [1.0, 0.0, 0.0],
+        "lime" => [0.0, 1.0, 0.0],
+        "green" => [0.0, 0.5, 0.0],
+        "blue" => [0.0, 0.0, 1.0],
+        "yellow" => [1.0, 1.0, 0.0],
+        "cyan" | "aqua" => [0.0, 1.0, 1.0],
+        "magenta" | "fuchsia" => [1.0, 0.0, 1.0],
+        "gray" | "grey" => [0.5, 0.5, 0.5],
+        "silver" => [0.75, 0.75, 0.75],
+        "maroon" => [0.5, 0.0, 0.0],
+        "olive" => [0.5, 0.5, 0.0],
+        "navy" => [0.0, 0.0, 0.5],
+        "purple" => [0.5, 0.0, 0.5],
+        "teal" => [0.0, 0.5, 0.5],
+        "orange" => [1.0, 0.65, 0.0],
+        _ => {
+            log::warn!("Unsupported colour, skipping {}", value);
+            return Ok(None);
+        }
+    };
+    Ok(Some([color[0], color[1], color[2], 1.0]))
+}
@@ -0,0 +1,204 @@
+use gggg::{
+    atlas::{Atlas, AtlasFull, Rect, RectHandle},
+    texture::TextureFormat,
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+/// Whether the rects, grown by `padding` on every side, share any pixels.
+fn overlaps(a: &Rect, b: &Rect, padding: u32) -> bool {
+    a.page == b.page
+        && a.x < b.x + b.w + padding * 2
+        && b.x < a.x + a.w + padding * 2
+        && a.y < b.y + b.h + padding * 2
+        && b.y < a.y + a.h + padding * 2
+}
+
+fn assert_packed(atlas: &Atlas, rects: &[Rect]) {
+    let padding = atlas.padding();
+    for (i, a) in rects.iter().enumerate() {
+        assert!(
+            a.x >= padding && a.y >= padding,
+            "{:?} is inside the padding",
+            a
+        );
+        assert!(
+            a.x + a.w + padding <= atlas.width && a.y + a.h + padding <= atlas.height,
+            "{:?} is outside of the {}x{} atlas",
+            a,
+            atlas.width,
+            atlas.height
+        );
+        assert!(
+            a.page < atlas.pages(),
+            "{:?} is on a page that doesn't exist",
+            a
+        );
+        for b in &rects[i + 1..] {
+            assert!(!overlaps(a, b, padding), "{:?} overlaps {:?}", a, b);
+        }
+    }
+}
+
+fn add(atlas: &mut Atlas, w: u32, h: u32) -> Rect {
+    let handle = atlas.add(w, h).unwrap();
+    *atlas.get_rect(handle).unwrap()
+}
+
+fn add_random(atlas: &mut Atlas, rng: &mut StdRng, count: usize) -> Vec<(RectHandle, Rect)> {
+    (0..count)
+        .map(|_| {
+            let handle = atlas
+                .add(rng.gen_range(1..48), rng.gen_range(1..48))
+                .unwrap();
+            (handle, *atlas.get_rect(handle).unwrap())
+        })
+        .collect()
+}
+
+#[test]
+fn random_rects_never_overlap() {
+    let mut rng = StdRng::seed_from_u64(1);
+    let mut atlas = Atlas::new(TextureFormat::Rgba8Unorm)
+        .with_padding(2)
+        .with_max_size(1024)
+        .with_max_pages(4);
+
+    let added = add_random(&mut atlas, &mut rng, 500);
+    let rects: Vec<_> = added.iter().map(|(_, rect)| *rect).collect();
+
+    assert_packed(&atlas, &rects);
+}
+
+#[test]
+fn rects_keep_their_positions_as_the_atlas_grows() {
+    let mut rng = StdRng::seed_from_u64(2);
+    let mut atlas = Atlas::new(TextureFormat::Rgba8Unorm).with_max_size(2048);
+
+    let first = atlas.add(10, 10).unwrap();
+    let initial_size = (atlas.width, atlas.height);
+    let added = add_random(&mut atlas, &mut rng, 300);
+
+    assert!(
+        (atlas.width, atlas.height) != initial_size,
+        "atlas never grew"
+    );
+    assert_eq!(atlas.pages(), 1);
+    assert_eq!(atlas.get_rect(first).unwrap().x, 1);
+    assert_eq!(atlas.get_rect(first).unwrap().y, 1);
+    for (handle, rect) in added {
+        assert_eq!(*atlas.get_rect(handle).unwrap(), rect);
+    }
+}
+
+#[test]
+fn padding_is_kept_between_rects() {
+    let mut atlas = Atlas::new(TextureFormat::Rgba8Unorm).with_padding(3);
+
+    let a = add(&mut atlas, 20, 20);
+    let b = add(&mut atlas, 20, 20);
+
+    assert_eq!((a.x, a.y), (3, 3));
+    let gap_x = b.x.saturating_sub(a.x + a.w);
+    let gap_y = b.y.saturating_sub(a.y + a.h);
+    assert!(
+        gap_x >= 6 || gap_y >= 6,
+        "{:?} and {:?} are too close",
+        a,
+        b
+    );
+}
+
+#[test]
+fn never_grows_beyond_max_size() {
+    let mut rng = StdRng::seed_from_u64(3);
+    let mut atlas = Atlas::new(TextureFormat::Rgba8Unorm)
+        .with_max_size(200)
+        .with_max_pages(8);
+
+    add_random(&mut atlas, &mut rng, 100);
+
+    assert!(atlas.width <= 200 && atlas.height <= 200);
+    assert!(
+        atlas.add(199, 10).is_err(),
+        "rect wider than a page with its padding"
+    );
+}
+
+#[test]
+fn power_of_two_atlases_stay_powers_of_two() {
+    let mut rng = StdRng::seed_from_u64(4);
+    let mut atlas = Atlas::new(TextureFormat::Rgba8Unorm)
+        .with_max_size(1000)
+        .with_max_pages(4)
+        .with_power_of_two(true);
+
+    add_random(&mut atlas, &mut rng, 200);
+
+    assert!(atlas.width.is_power_of_two() && atlas.height.is_power_of_two());
+    assert!(atlas.width <= 512 && atlas.height <= 512);
+}
+
+#[test]
+fn full_pages_overflow_into_new_pages() {
+    let mut atlas = Atlas::new(TextureFormat::Rgba8Unorm)
+        .with_padding(0)
+        .with_max_size(64)
+        .with_max_pages(3);
+
+    let rects: Vec<_> = (0..12).map(|_| add(&mut atlas, 32, 32)).collect();
+
+    assert_eq!(atlas.pages(), 3);
+    for page in 0..3 {
+        assert_eq!(rects.iter().filter(|rect| rect.page == page).count(), 4);
+    }
+    assert_packed(&atlas, &rects);
+    assert!(matches!(atlas.add(32, 32), Err(err) if err.is::<AtlasFull>()));
+}
+
+#[test]
+fn removed_space_is_reused() {
+    let mut atlas = Atlas::new(TextureFormat::Rgba8Unorm).with_padding(2);
+
+    let a = atlas.add(16, 16).unwrap();
+    add(&mut atlas, 16, 16);
+    let removed = atlas.remove(a).unwrap();
+    assert!(atlas.get_rect(a).is_none());
+    assert!(atlas.remove(a).is_none());
+
+    // a smaller rect goes into the top left of the freed space
+    let reused = add(&mut atlas, 8, 12);
+    assert_eq!(
+        (reused.x, reused.y, reused.page),
+        (removed.x, removed.y, removed.page)
+    );
+}
+
+#[test]
+fn reused_space_never_overlaps() {
+    let mut rng = StdRng::seed_from_u64(5);
+    let mut atlas = Atlas::new(TextureFormat::Rgba8Unorm)
+        .with_padding(1)
+        .with_max_size(512)
+        .with_max_pages(2);
+
+    let mut live = add_random(&mut atlas, &mut rng, 200);
+    for _ in 0..10 {
+        // remove about half of the rects and fill the gaps back up
+        let mut kept = Vec::new();
+        for (handle, rect) in live {
+            if rng.gen_bool(0.5) {
+                assert_eq!(atlas.remove(handle), Some(rect));
+            } else {
+                kept.push((handle, rect));
+            }
+        }
+        kept.extend(add_random(&mut atlas, &mut rng, 100));
+        live = kept;
+
+        let rects: Vec<_> = live.iter().map(|(_, rect)| *rect).collect();
+        assert_packed(&atlas, &rects);
+        for (handle, rect) in &live {
+            assert_eq!(atlas.get_rect(*handle), Some(rect));
+        }
+    }
+}
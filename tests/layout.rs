@@ -0,0 +1,312 @@
+use anyhow::Result;
+use gggg::text::{
+    layout::{
+        layout, layout_spans, Align, FontMetrics, LayoutOptions, LayoutSpan, TextLayout,
+        TextShaper, VerticalAlign, Wrap,
+    },
+    shaping::ShapedGlyph,
+};
+
+/// Every character is one em wide and the glyph id is the character, except for `missing` ones.
+struct MonoShaper {
+    missing: Vec<char>,
+}
+
+const MONO: MonoShaper = MonoShaper {
+    missing: Vec::new(),
+};
+
+impl TextShaper for MonoShaper {
+    fn shape(&self, text: &str, size: f32) -> Result<Vec<ShapedGlyph>> {
+        Ok(text
+            .char_indices()
+            .map(|(cluster, character)| ShapedGlyph {
+                face: 0,
+                glyph_id: if self.missing.contains(&character) {
+                    0
+                } else {
+                    character as u16
+                },
+                cluster,
+                x_advance: size,
+                y_advance: 0.0,
+                x_offset: 0.0,
+                y_offset: 0.0,
+            })
+            .collect())
+    }
+
+    fn metrics(&self, size: f32) -> FontMetrics {
+        FontMetrics {
+            ascent: size * 0.8,
+            descent: -size * 0.2,
+            line_gap: 0.0,
+            ..Default::default()
+        }
+    }
+}
+
+fn lines<'a>(layout: &TextLayout, text: &'a str) -> Vec<&'a str> {
+    layout
+        .lines
+        .iter()
+        .map(|line| &text[line.range.clone()])
+        .collect()
+}
+
+fn line_text(layout: &TextLayout, text: &str, line: usize) -> String {
+    layout.glyphs[layout.lines[line].glyphs.clone()]
+        .iter()
+        .map(|glyph| {
+            let text = if glyph.ellipsis {
+                layout.ellipsis
+            } else {
+                text
+            };
+            text[glyph.cluster..].chars().next().unwrap()
+        })
+        .collect()
+}
+
+#[test]
+fn word_wrap_breaks_between_words() {
+    let text = "hello world foo";
+    let options = LayoutOptions::new().with_max_width(60.0);
+
+    let layout = layout(&MONO, text, 10.0, &options).unwrap();
+
+    assert_eq!(lines(&layout, text), vec!["hello", "world", "foo"]);
+    assert!(!layout.truncated);
+    for line in &layout.lines {
+        assert!(line.width <= 60.0);
+    }
+}
+
+#[test]
+fn character_wrap_breaks_anywhere() {
+    let text = "hello world";
+    let options = LayoutOptions::new()
+        .with_max_width(40.0)
+        .with_wrap(Wrap::Character);
+
+    let layout = layout(&MONO, text, 10.0, &options).unwrap();
+
+    assert_eq!(lines(&layout, text), vec!["hell", "o wo", "rld"]);
+}
+
+#[test]
+fn words_too_long_for_a_line_are_broken_between_characters() {
+    let text = "a abcdefghij b";
+    let options = LayoutOptions::new().with_max_width(40.0);
+
+    let layout = layout(&MONO, text, 10.0, &options).unwrap();
+
+    assert_eq!(lines(&layout, text), vec!["a", "abcd", "efgh", "ij b"]);
+}
+
+#[test]
+fn lines_always_get_a_character() {
+    let text = "abc";
+    let options = LayoutOptions::new().with_max_width(5.0);
+
+    let layout = layout(&MONO, text, 10.0, &options).unwrap();
+
+    assert_eq!(lines(&layout, text), vec!["a", "b", "c"]);
+}
+
+#[test]
+fn newlines_always_break() {
+    let text = "one\ntwo\n\nthree";
+    let options = LayoutOptions::new().with_wrap(Wrap::None);
+
+    let layout = layout(&MONO, text, 10.0, &options).unwrap();
+
+    assert_eq!(lines(&layout, text), vec!["one", "two", "", "three"]);
+    assert_eq!(line_text(&layout, text, 3), "three");
+    // the ascent is 8 and every line is 10 tall
+    let baselines: Vec<f32> = layout.lines.iter().map(|line| line.baseline).collect();
+    assert_eq!(baselines, vec![-8.0, -18.0, -28.0, -38.0]);
+    assert_eq!(layout.bounds.height(), 40.0);
+    assert_eq!(layout.bounds.width(), 50.0);
+}
+
+#[test]
+fn without_wrapping_lines_are_as_wide_as_they_need() {
+    let text = "hello world";
+    let options = LayoutOptions::new()
+        .with_max_width(40.0)
+        .with_wrap(Wrap::None);
+
+    let layout = layout(&MONO, text, 10.0, &options).unwrap();
+
+    assert_eq!(lines(&layout, text), vec![text]);
+    assert_eq!(layout.lines[0].width, 110.0);
+}
+
+#[test]
+fn justified_lines_fill_the_width() {
+    let text = "aa bb cc dd\nee ff";
+    let options = LayoutOptions::new()
+        .with_max_width(70.0)
+        .with_align(Align::Justify);
+
+    let layout = layout(&MONO, text, 10.0, &options).unwrap();
+
+    assert_eq!(lines(&layout, text), vec!["aa bb", "cc dd", "ee ff"]);
+    let first = &layout.glyphs[layout.lines[0].glyphs.clone()];
+    assert_eq!(layout.lines[0].width, 70.0);
+    // the space is stretched by the 20 pixels the line is short of
+    assert_eq!(
+        first.iter().map(|glyph| glyph.x).collect::<Vec<_>>(),
+        vec![0.0, 10.0, 20.0, 50.0, 60.0]
+    );
+    // the last line of a paragraph stays as it is
+    assert_eq!(layout.lines[2].width, 50.0);
+}
+
+#[test]
+fn lines_are_aligned_within_the_max_width() {
+    let text = "ab";
+
+    let centered = layout(
+        &MONO,
+        text,
+        10.0,
+        &LayoutOptions::new()
+            .with_max_width(100.0)
+            .with_align(Align::Center),
+    )
+    .unwrap();
+    let right = layout(
+        &MONO,
+        text,
+        10.0,
+        &LayoutOptions::new()
+            .with_max_width(100.0)
+            .with_align(Align::Right),
+    )
+    .unwrap();
+
+    assert_eq!(centered.lines[0].x, 40.0);
+    assert_eq!(right.lines[0].x, 80.0);
+    assert_eq!(right.glyphs[1].x, 90.0);
+}
+
+#[test]
+fn lines_past_the_max_height_are_dropped() {
+    let text = "one two three four";
+    let options = LayoutOptions::new()
+        .with_max_width(50.0)
+        .with_max_height(25.0)
+        .with_vertical_align(VerticalAlign::Bottom);
+
+    let layout = layout(&MONO, text, 10.0, &options).unwrap();
+
+    assert_eq!(lines(&layout, text), vec!["one", "two"]);
+    assert!(layout.truncated);
+    assert!(layout.glyphs.iter().all(|glyph| !glyph.ellipsis));
+    // the two lines sit at the bottom of the 25 pixel box
+    assert_eq!(layout.bounds.max[1], -5.0);
+}
+
+#[test]
+fn no_lines_fit_into_less_than_a_line() {
+    let text = "one two";
+    let options = LayoutOptions::new()
+        .with_max_height(5.0)
+        .with_ellipsis(true);
+
+    let layout = layout(&MONO, text, 10.0, &options).unwrap();
+
+    assert!(layout.lines.is_empty());
+    assert!(layout.glyphs.is_empty());
+    assert!(layout.truncated);
+    assert_eq!(layout.bounds.height(), 0.0);
+}
+
+#[test]
+fn dropped_lines_end_the_last_line_with_an_ellipsis() {
+    let text = "one two three";
+    let options = LayoutOptions::new()
+        .with_max_width(50.0)
+        .with_max_lines(2)
+        .with_ellipsis(true);
+
+    let layout = layout(&MONO, text, 10.0, &options).unwrap();
+
+    assert!(layout.truncated);
+    assert_eq!(layout.ellipsis, "…");
+    assert_eq!(line_text(&layout, text, 0), "one");
+    assert_eq!(line_text(&layout, text, 1), "two…");
+}
+
+#[test]
+fn lines_that_are_too_wide_are_cut_short_with_an_ellipsis() {
+    let text = "hello world";
+    let options = LayoutOptions::new()
+        .with_max_width(45.0)
+        .with_wrap(Wrap::None)
+        .with_ellipsis(true);
+
+    let layout = layout(&MONO, text, 10.0, &options).unwrap();
+
+    assert!(layout.truncated);
+    assert_eq!(line_text(&layout, text, 0), "hel…");
+    assert!(layout.lines[0].width <= 45.0);
+}
+
+#[test]
+fn fonts_without_an_ellipsis_get_three_dots() {
+    let shaper = MonoShaper {
+        missing: vec!['…']
+    };
+    let text = "hello world";
+    let options = LayoutOptions::new()
+        .with_max_width(60.0)
+        .with_wrap(Wrap::None)
+        .with_ellipsis(true);
+
+    let layout = layout(&shaper, text, 10.0, &options).unwrap();
+
+    assert_eq!(layout.ellipsis, "...");
+    assert_eq!(line_text(&layout, text, 0), "hel...");
+    assert!(layout.glyphs.iter().all(|glyph| glyph.glyph_id != 0));
+}
+
+#[test]
+fn lines_are_as_tall_as_their_tallest_span() {
+    let text = "small BIG\nsmall";
+    let spans = [
+        LayoutSpan {
+            range: 0..6,
+            shaper: &MONO,
+            size: 10.0,
+        },
+        LayoutSpan {
+            range: 6..9,
+            shaper: &MONO,
+            size: 20.0,
+        },
+        LayoutSpan {
+            range: 9..text.len(),
+            shaper: &MONO,
+            size: 10.0,
+        },
+    ];
+
+    let layout = layout_spans(text, &spans, &LayoutOptions::new()).unwrap();
+
+    assert_eq!(lines(&layout, text), vec!["small BIG", "small"]);
+    // the first line has the big span's ascent of 16 and is 20 tall, the second is 10 tall
+    assert_eq!(layout.lines[0].baseline, -16.0);
+    assert_eq!(layout.lines[1].baseline, -28.0);
+    assert_eq!(layout.lines[0].width, 120.0);
+    assert_eq!(layout.bounds.height(), 30.0);
+    let spans_of_line: Vec<usize> = layout
+        .runs
+        .iter()
+        .filter(|run| run.line == 0)
+        .map(|run| run.span)
+        .collect();
+    assert_eq!(spans_of_line, vec![0, 1]);
+}
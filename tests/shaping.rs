@@ -0,0 +1,165 @@
+use std::fs;
+
+use gggg::text::shaping::{script_runs, shape, ShapedGlyph, ShapingFace};
+
+fn roboto() -> ShapingFace {
+    let data = fs::read(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/examples/draw-2d/Roboto.ttf"
+    ))
+    .unwrap();
+    ShapingFace::parse(data.into(), 0).unwrap()
+}
+
+fn clusters(glyphs: &[ShapedGlyph]) -> Vec<usize> {
+    glyphs.iter().map(|glyph| glyph.cluster).collect()
+}
+
+#[test]
+fn script_runs_split_where_the_script_changes() {
+    let text = "abc абв, def";
+
+    let runs = script_runs(text, 0..text.len());
+
+    // the space and comma stay with the run they're in
+    assert_eq!(runs, vec![0..4, 4..12, 12..text.len()]);
+    assert_eq!(&text[runs[1].clone()], "абв, ");
+}
+
+#[test]
+fn script_runs_stay_within_their_range() {
+    let text = "abc абв def";
+
+    assert_eq!(script_runs(text, 4..10), vec![4..10]);
+    assert_eq!(script_runs(text, 2..12), vec![2..4, 4..11, 11..12]);
+    assert_eq!(script_runs("12, 34", 0..6), vec![0..6]);
+}
+
+#[test]
+fn left_to_right_text_keeps_its_order() {
+    let glyphs = shape(&[roboto()], 32.0, "Hello");
+
+    assert_eq!(clusters(&glyphs), vec![0, 1, 2, 3, 4]);
+    assert!(glyphs.iter().all(|glyph| glyph.glyph_id != 0));
+}
+
+#[test]
+fn right_to_left_runs_are_reversed() {
+    // roboto has no hebrew, the missing glyphs are still placed
+    let text = "ab אבג cd";
+
+    let glyphs = shape(&[roboto()], 32.0, text);
+
+    assert_eq!(clusters(&glyphs), vec![0, 1, 2, 7, 5, 3, 9, 10, 11]);
+}
+
+#[test]
+fn right_to_left_paragraphs_put_left_to_right_runs_first() {
+    let text = "אבג ab";
+
+    let glyphs = shape(&[roboto()], 32.0, text);
+
+    assert_eq!(clusters(&glyphs), vec![7, 8, 6, 4, 2, 0]);
+}
+
+#[test]
+fn combining_marks_share_their_base_characters_cluster() {
+    let text = "ae\u{301}b";
+
+    let glyphs = shape(&[roboto()], 32.0, text);
+
+    assert!(clusters(&glyphs)
+        .iter()
+        .all(|cluster| [0, 1, 4].contains(cluster)));
+    assert_eq!(glyphs.first().unwrap().cluster, 0);
+    assert_eq!(glyphs.last().unwrap().cluster, 4);
+}
+
+#[test]
+fn glyphs_are_positioned_at_the_given_size() {
+    let faces = [roboto()];
+
+    let small = shape(&faces, 16.0, "Wave");
+    let large = shape(&faces, 32.0, "Wave");
+
+    assert_eq!(small.len(), large.len());
+    for (small, large) in small.iter().zip(&large) {
+        assert!((small.x_advance * 2.0 - large.x_advance).abs() < 1e-3);
+    }
+}
+
+fn sinhala() -> ShapingFace {
+    let data = fs::read(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fonts/NotoSerifSinhala-Regular.ttf"
+    ))
+    .unwrap();
+    ShapingFace::parse(data.into(), 0).unwrap()
+}
+
+fn faces(glyphs: &[ShapedGlyph]) -> Vec<usize> {
+    glyphs.iter().map(|glyph| glyph.face).collect()
+}
+
+#[test]
+fn missing_glyphs_come_from_the_fallback() {
+    // lanka in sinhala, roboto has none of it
+    let text = "a ලංකා b";
+
+    let glyphs = shape(&[roboto(), sinhala()], 32.0, text);
+
+    assert!(glyphs.iter().all(|glyph| glyph.glyph_id != 0));
+    assert_eq!(glyphs.first().unwrap().face, 0);
+    assert_eq!(glyphs.last().unwrap().face, 0);
+    let sinhala_glyphs: Vec<&ShapedGlyph> = glyphs
+        .iter()
+        .filter(|glyph| (2..text.len() - 2).contains(&glyph.cluster))
+        .collect();
+    assert!(!sinhala_glyphs.is_empty());
+    assert!(sinhala_glyphs.iter().all(|glyph| glyph.face == 1));
+    // the fallback's glyphs go where the missing ones were
+    let mut sorted = clusters(&glyphs);
+    sorted.sort_unstable();
+    assert_eq!(clusters(&glyphs), sorted);
+}
+
+#[test]
+fn vowel_signs_are_reshaped_with_their_base() {
+    // ka with the vowel sign i, a base and a mark in one cluster that the fallback shapes together
+    let text = "x\u{0d9a}\u{0dd2}y";
+
+    let primary_only = shape(&[roboto()], 32.0, text);
+    let glyphs = shape(&[roboto(), sinhala()], 32.0, text);
+
+    assert!(primary_only.iter().any(|glyph| glyph.glyph_id == 0));
+    assert_eq!(faces(&glyphs).first(), Some(&0));
+    assert_eq!(faces(&glyphs).last(), Some(&0));
+    let middle = &glyphs[1..glyphs.len() - 1];
+    assert!(!middle.is_empty());
+    assert!(middle
+        .iter()
+        .all(|glyph| glyph.face == 1 && glyph.glyph_id != 0));
+    assert!(middle.iter().all(|glyph| glyph.cluster == 1));
+}
+
+#[test]
+fn glyphs_no_face_has_are_left_missing_in_the_last_face() {
+    let text = "aאb";
+
+    let glyphs = shape(&[roboto(), sinhala()], 32.0, text);
+
+    assert_eq!(faces(&glyphs), vec![0, 1, 0]);
+    assert_eq!(glyphs[1].glyph_id, 0);
+    assert_eq!(clusters(&glyphs), vec![0, 1, 3]);
+}
+
+#[test]
+fn fallback_runs_keep_right_to_left_order() {
+    // hebrew isn't in either font, the reshaped run is still reversed
+    let text = "אבג";
+
+    let glyphs = shape(&[roboto(), sinhala()], 32.0, text);
+
+    assert_eq!(clusters(&glyphs), vec![4, 2, 0]);
+    assert!(glyphs.iter().all(|glyph| glyph.face == 1));
+}
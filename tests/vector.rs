@@ -0,0 +1,316 @@
+use std::f32::consts::PI;
+
+use gggg::{
+    geometry::Indices,
+    shapes::LineCap,
+    vector::{
+        path::{
+            dash_path, fill_path, parse_path, stroke_path, FillRule, PathGeometry, StrokeStyle,
+        },
+        svg::Svg,
+    },
+};
+use lyon::path::PathEvent;
+
+const TOLERANCE: f32 = 0.01;
+
+fn triangles(geometry: &PathGeometry) -> Vec<[[f32; 2]; 3]> {
+    let indices = match &geometry.indices {
+        Indices::U16(indices) => indices.iter().map(|idx| *idx as usize).collect::<Vec<_>>(),
+        Indices::U32(indices) => indices.iter().map(|idx| *idx as usize).collect(),
+    };
+    let vertex = |idx: usize| {
+        let [x, y, _] = geometry.vertices[idx].pos;
+        [x, y]
+    };
+    indices
+        .chunks_exact(3)
+        .map(|triangle| {
+            [
+                vertex(triangle[0]),
+                vertex(triangle[1]),
+                vertex(triangle[2]),
+            ]
+        })
+        .collect()
+}
+
+fn area(geometry: &PathGeometry) -> f32 {
+    triangles(geometry)
+        .iter()
+        .map(|[a, b, c]| {
+            ((b[0] - a[0]) * (c[1] - a[1]) - (c[0] - a[0]) * (b[1] - a[1])).abs() / 2.0
+        })
+        .sum()
+}
+
+/// [min x, min y, max x, max y]
+fn bounds(geometry: &PathGeometry) -> [f32; 4] {
+    geometry.vertices.iter().fold(
+        [f32::MAX, f32::MAX, f32::MIN, f32::MIN],
+        |[x0, y0, x1, y1], vertex| {
+            let [x, y, _] = vertex.pos;
+            [x0.min(x), y0.min(y), x1.max(x), y1.max(y)]
+        },
+    )
+}
+
+fn assert_close(actual: f32, expected: f32, epsilon: f32) {
+    assert!(
+        (actual - expected).abs() <= epsilon,
+        "expected {} to be within {} of {}",
+        actual,
+        epsilon,
+        expected
+    );
+}
+
+#[test]
+fn fills_a_square_with_two_triangles() {
+    let path = parse_path("M 0 0 L 10 0 L 10 10 L 0 10 Z").unwrap();
+    let geometry = fill_path(&path, FillRule::NonZero, TOLERANCE).unwrap();
+
+    assert_eq!(geometry.vertices.len(), 4);
+    assert_eq!(triangles(&geometry).len(), 2);
+    assert_close(area(&geometry), 100.0, 1e-3);
+    assert_eq!(bounds(&geometry), [0.0, 0.0, 10.0, 10.0]);
+}
+
+#[test]
+fn fill_rules_differ_for_nested_subpaths() {
+    // both squares wind the same way
+    let path = parse_path("M 0 0 L 10 0 L 10 10 L 0 10 Z M 2 2 L 8 2 L 8 8 L 2 8 Z").unwrap();
+
+    let non_zero = fill_path(&path, FillRule::NonZero, TOLERANCE).unwrap();
+    assert_close(area(&non_zero), 100.0, 1e-3);
+
+    let even_odd = fill_path(&path, FillRule::EvenOdd, TOLERANCE).unwrap();
+    assert_close(area(&even_odd), 100.0 - 36.0, 1e-3);
+}
+
+#[test]
+fn fills_curves_within_tolerance() {
+    let circle = parse_path("M 10 0 A 10 10 0 1 1 -10 0 A 10 10 0 1 1 10 0 Z").unwrap();
+    let geometry = fill_path(&circle, FillRule::NonZero, TOLERANCE).unwrap();
+    assert_close(area(&geometry), PI * 100.0, PI * 100.0 * 0.01);
+
+    let [x0, y0, x1, y1] = bounds(&geometry);
+    for value in [x0, y0] {
+        assert_close(value, -10.0, TOLERANCE);
+    }
+    for value in [x1, y1] {
+        assert_close(value, 10.0, TOLERANCE);
+    }
+
+    let quadratic = parse_path("M 0 0 Q 5 10 10 0 Z").unwrap();
+    let geometry = fill_path(&quadratic, FillRule::NonZero, TOLERANCE).unwrap();
+    // the area under a parabola is 2/3 of its bounding box
+    assert_close(area(&geometry), 2.0 / 3.0 * 10.0 * 5.0, 0.1);
+}
+
+#[test]
+fn stroke_caps_extend_the_line() {
+    let line = parse_path("M 0 0 L 10 0").unwrap();
+
+    let butt = stroke_path(&line, &StrokeStyle::new(2.0), TOLERANCE).unwrap();
+    assert_close(area(&butt), 20.0, 1e-3);
+    assert_eq!(bounds(&butt), [0.0, -1.0, 10.0, 1.0]);
+
+    let style = StrokeStyle::new(2.0).with_cap(LineCap::Square);
+    let square = stroke_path(&line, &style, TOLERANCE).unwrap();
+    assert_close(area(&square), 24.0, 1e-3);
+    assert_eq!(bounds(&square), [-1.0, -1.0, 11.0, 1.0]);
+
+    let style = StrokeStyle::new(2.0).with_cap(LineCap::Round);
+    let round = stroke_path(&line, &style, TOLERANCE).unwrap();
+    assert_close(area(&round), 20.0 + PI, 0.05);
+}
+
+#[test]
+fn strokes_closed_paths_all_the_way_round() {
+    let square = parse_path("M 0 0 L 10 0 L 10 10 L 0 10 Z").unwrap();
+    let geometry = stroke_path(&square, &StrokeStyle::new(2.0), TOLERANCE).unwrap();
+
+    // a 12x12 square with an 8x8 hole, the miters fill the corners
+    assert_close(area(&geometry), 144.0 - 64.0, 1e-3);
+    assert_eq!(bounds(&geometry), [-1.0, -1.0, 11.0, 11.0]);
+}
+
+#[test]
+fn dashes_split_the_path() {
+    let line = parse_path("M 0 0 L 10 0").unwrap();
+
+    let dashed = dash_path(&line, &[2.0, 2.0], 0.0, TOLERANCE);
+    let begins = dashed
+        .iter()
+        .filter(|event| matches!(event, PathEvent::Begin { .. }))
+        .count();
+    assert_eq!(begins, 3);
+
+    // dashes from 0 to 2, 4 to 6 and 8 to 10
+    let style = StrokeStyle::new(1.0).with_dashes(vec![2.0, 2.0], 0.0);
+    let geometry = stroke_path(&line, &style, TOLERANCE).unwrap();
+    assert_close(area(&geometry), 6.0, 1e-3);
+
+    // dashes from 0 to 1, 3 to 5 and 7 to 9
+    let style = StrokeStyle::new(1.0).with_dashes(vec![2.0, 2.0], 1.0);
+    let geometry = stroke_path(&line, &style, TOLERANCE).unwrap();
+    assert_close(area(&geometry), 5.0, 1e-3);
+
+    // an odd pattern is repeated, so 3 on 1 off 3 on 3 off 1 on 3 off
+    let line = parse_path("M 0 0 L 14 0").unwrap();
+    let style = StrokeStyle::new(1.0).with_dashes(vec![3.0, 1.0, 3.0], 0.0);
+    let geometry = stroke_path(&line, &style, TOLERANCE).unwrap();
+    assert_close(area(&geometry), 7.0, 1e-3);
+}
+
+#[test]
+fn dashes_continue_around_corners() {
+    let corner = parse_path("M 0 0 L 3 0 L 3 3").unwrap();
+    let dashed = dash_path(&corner, &[4.0, 1.0], 0.0, TOLERANCE);
+
+    // the first dash turns the corner, the second one is cut short by the end of the path
+    let events = dashed.iter().collect::<Vec<_>>();
+    let begins = events
+        .iter()
+        .filter(|event| matches!(event, PathEvent::Begin { .. }))
+        .count();
+    assert_eq!(begins, 2);
+    assert!(events.contains(&PathEvent::Line {
+        from: lyon::math::point(0.0, 0.0),
+        to: lyon::math::point(3.0, 0.0),
+    }));
+}
+
+#[test]
+fn invalid_path_data_is_an_error() {
+    assert!(parse_path("M 0 0 L 10").is_err());
+    assert!(parse_path("X 0 0").is_err());
+}
+
+#[test]
+fn imports_svg_shapes() {
+    let svg = Svg::parse(
+        r##"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 24 24">
+            <rect x="2" y="4" width="10" height="6" fill="#ff0000"/>
+            <circle cx="12" cy="12" r="5" fill="none" stroke="blue" stroke-width="2"/>
+            <defs><linearGradient id="unused"/></defs>
+        </svg>"##,
+    )
+    .unwrap();
+    assert_eq!(svg.width, 24.0);
+    assert_eq!(svg.height, 24.0);
+    assert_eq!(svg.shapes.len(), 2);
+
+    let meshes = svg.tessellate(TOLERANCE).unwrap();
+    assert_eq!(meshes.len(), 2);
+
+    let (rect, color) = &meshes[0];
+    assert_eq!(*color, [1.0, 0.0, 0.0, 1.0]);
+    assert_close(area(rect), 60.0, 1e-3);
+    // flipped so +y is up, with the bottom of the view box at 0
+    assert_eq!(bounds(rect), [2.0, 14.0, 12.0, 20.0]);
+
+    let (ring, color) = &meshes[1];
+    assert_eq!(*color, [0.0, 0.0, 1.0, 1.0]);
+    assert_close(area(ring), PI * (36.0 - 16.0), PI * 20.0 * 0.01);
+}
+
+#[test]
+fn svg_groups_pass_on_styles_and_transforms() {
+    let svg = Svg::parse(
+        r#"<svg viewBox="0 0 100 100">
+            <g fill="lime" opacity="0.5" transform="translate(10 0) scale(2)">
+                <rect width="1" height="1"/>
+                <path d="M 0 0 H 4 V 4 H 0 Z M 1 1 H 3 V 3 H 1 Z" style="fill-rule: evenodd; fill-opacity: 50%"/>
+                <line x1="0" y1="0" x2="5" y2="0" stroke="black"/>
+            </g>
+        </svg>"#,
+    )
+    .unwrap();
+    let meshes = svg.tessellate(TOLERANCE).unwrap();
+    // the line's fill has no area, so there's nothing to draw for it
+    assert_eq!(meshes.len(), 3);
+
+    let (rect, color) = &meshes[0];
+    assert_eq!(*color, [0.0, 1.0, 0.0, 0.5]);
+    assert_close(area(rect), 4.0, 1e-3);
+    assert_eq!(bounds(rect), [10.0, 98.0, 12.0, 100.0]);
+
+    let (frame, color) = &meshes[1];
+    assert_eq!(*color, [0.0, 1.0, 0.0, 0.25]);
+    assert_close(area(frame), 4.0 * (16.0 - 4.0), 1e-3);
+
+    // the stroke width is scaled along with the line
+    let (line, color) = &meshes[2];
+    assert_eq!(*color, [0.0, 0.0, 0.0, 0.5]);
+    assert_close(area(line), 10.0 * 2.0, 1e-3);
+}
+
+#[test]
+fn svg_rounded_rects_and_polygons() {
+    let svg = Svg::parse(
+        r##"<svg viewBox="0 0 20 20">
+            <rect width="10" height="10" rx="2"/>
+            <rect width="10" height="10" rx="2" ry="4"/>
+            <polygon points="0,0 10,0 0,10"/>
+            <polyline points="0 0 10 0 10 10" fill="none" stroke="#000" stroke-dasharray="5"/>
+        </svg>"##,
+    )
+    .unwrap();
+    let meshes = svg.tessellate(TOLERANCE).unwrap();
+    assert_eq!(meshes.len(), 4);
+
+    // the corners cut off 4 squares and add back 4 quarter circles
+    assert_close(area(&meshes[0].0), 100.0 - 16.0 + 4.0 * PI, 0.1);
+    // same with quarter ellipses
+    assert_close(area(&meshes[1].0), 100.0 - 32.0 + 8.0 * PI, 0.1);
+    assert_close(area(&meshes[2].0), 50.0, 1e-3);
+    // two dashes of 5, the second one starts at the corner
+    assert_close(area(&meshes[3].0), 10.0, 0.5);
+}
+
+#[test]
+fn unsupported_svg_paints_are_skipped() {
+    let svg = Svg::parse(
+        r#"<svg viewBox="0 0 10 10">
+            <rect width="1" height="1" fill="darkgray"/>
+            <rect width="1" height="1" fill="hsl(0, 100%, 50%)" stroke="red"/>
+            <rect width="1" height="1" fill="url(#gradient)"/>
+        </svg>"#,
+    )
+    .unwrap();
+
+    assert_eq!(svg.shapes.len(), 3);
+    assert!(svg.shapes.iter().all(|shape| shape.fill.is_none()));
+    assert_eq!(
+        svg.shapes[1].stroke.as_ref().unwrap().color,
+        [1.0, 0.0, 0.0, 1.0]
+    );
+}
+
+#[test]
+fn svg_opacity_is_applied_once_per_element() {
+    let svg = Svg::parse(
+        r#"<svg viewBox="0 0 10 10">
+            <g opacity="0.5">
+                <rect width="1" height="1" opacity="0.5" style="opacity: 0.5"/>
+                <rect width="1" height="1" style="opacity: 0.2"/>
+            </g>
+        </svg>"#,
+    )
+    .unwrap();
+
+    let alpha = |idx: usize| svg.shapes[idx].fill.as_ref().unwrap().color[3];
+    assert_close(alpha(0), 0.25, 1e-6);
+    assert_close(alpha(1), 0.1, 1e-6);
+}
+
+#[test]
+fn invalid_svgs_are_errors() {
+    assert!(Svg::parse("<svg").is_err());
+    assert!(Svg::parse("<html/>").is_err());
+    assert!(Svg::parse(r#"<svg><rect width="ten" height="1"/></svg>"#).is_err());
+    assert!(Svg::parse(r##"<svg><rect width="1" height="1" fill="#12"/></svg>"##).is_err());
+    assert!(Svg::parse(r#"<svg><g transform="wobble(1)"/></svg>"#).is_err());
+}